@@ -0,0 +1,67 @@
+// Rpc client, signer and transaction helpers shared by the commands
+use fruitbasket_client::pda;
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::program_pack::Pack;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signature, Signer};
+use solana_sdk::system_instruction;
+use solana_sdk::transaction::Transaction;
+
+use crate::Result;
+
+pub struct Cli {
+    pub rpc : RpcClient,
+    pub payer : Keypair,
+    pub group_key : Pubkey,
+    pub dex_program : Pubkey,
+}
+
+impl Cli {
+    pub fn new(url : &str, payer : Keypair, group_key : Option<Pubkey>, dex_program : Pubkey) -> Self {
+        // a group is derived from its owner
+        let group_key = group_key.unwrap_or_else(|| pda::group(&payer.pubkey()).0);
+        Cli {
+            rpc : RpcClient::new_with_commitment(url.to_string(), CommitmentConfig::confirmed()),
+            payer,
+            group_key,
+            dex_program,
+        }
+    }
+
+    pub fn send(&self, instructions : &[Instruction], signers : &[&Keypair]) -> Result<Signature> {
+        let blockhash = self.rpc.get_latest_blockhash()?;
+        let mut all_signers = vec![&self.payer];
+        all_signers.extend(signers);
+        let transaction = Transaction::new_signed_with_payer(instructions, Some(&self.payer.pubkey()), &all_signers, blockhash);
+        Ok(self.rpc.send_and_confirm_transaction(&transaction)?)
+    }
+
+    // instruction creating a rent exempt account of space bytes owned by owner
+    pub fn create_account(&self, account : &Keypair, space : usize, owner : &Pubkey) -> Result<Instruction> {
+        let lamports = self.rpc.get_minimum_balance_for_rent_exemption(space)?;
+        Ok(system_instruction::create_account(&self.payer.pubkey(), &account.pubkey(), lamports, space as u64, owner))
+    }
+
+    // new token account of mint owned by the keypair
+    pub fn create_token_account(&self, mint : &Pubkey) -> Result<Pubkey> {
+        let account = Keypair::new();
+        let instructions = [
+            self.create_account(&account, spl_token::state::Account::LEN, &spl_token::ID)?,
+            spl_token::instruction::initialize_account(&spl_token::ID, &account.pubkey(), mint, &self.payer.pubkey())?,
+        ];
+        self.send(&instructions, &[&account])?;
+        Ok(account.pubkey())
+    }
+
+    // associated token account of mint owned by the keypair, created when missing
+    pub fn associated_token_account(&self, mint : &Pubkey) -> Result<Pubkey> {
+        let owner = self.payer.pubkey();
+        let account = spl_associated_token_account::get_associated_token_address(&owner, mint);
+        if self.rpc.get_account_with_commitment(&account, self.rpc.commitment())?.value.is_none() {
+            self.send(&[spl_associated_token_account::create_associated_token_account(&owner, &owner, mint)], &[])?;
+        }
+        Ok(account)
+    }
+}
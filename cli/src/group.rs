@@ -0,0 +1,192 @@
+// Commands setting up and showing the group, its tokens and its baskets
+use fruitbasket::states::BasketComponentDescription;
+use fruitbasket_client::{instructions, rpc, serum};
+use serde::Deserialize;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use std::collections::HashSet;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::cli::Cli;
+use crate::Result;
+
+// components given to add_basket and to each add_basket_components, with their token descriptions, so the transactions stay under the size limit
+const COMPONENTS_PER_INSTRUCTION : usize = 8;
+
+#[derive(Deserialize)]
+struct BasketFile {
+    name : String,
+    description : String,
+    creator_fee_bps : u64,
+    components : Vec<ComponentFile>,
+}
+
+#[derive(Deserialize)]
+struct ComponentFile {
+    mint : String,
+    // amount of native tokens for one whole basket
+    amount : u64,
+    decimal : u8,
+}
+
+impl Cli {
+    pub fn init_group(&self, quote_mint : &Pubkey, quote_name : String, quote_pool : Option<Pubkey>) -> Result<()> {
+        let owner = self.payer.pubkey();
+        let quote_pool = match quote_pool {
+            Some(quote_pool) => quote_pool,
+            None => self.create_token_account(quote_mint)?,
+        };
+        self.send(&[
+            instructions::initialize_group(&owner, quote_mint, &quote_pool, quote_name),
+            instructions::create_fee_vault(&owner, &self.group_key, quote_mint),
+        ], &[])?;
+        println!("group : {}", self.group_key);
+        println!("quote transaction pool : {}", quote_pool);
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn add_token(&self, mint : &Pubkey, name : String, price_oracle : &Pubkey, product_oracle : &Pubkey, market : &Pubkey,
+                     token_pool : Option<Pubkey>, open_orders : Option<Pubkey>) -> Result<()> {
+        let owner = self.payer.pubkey();
+        let token_pool = match token_pool {
+            Some(token_pool) => token_pool,
+            None => self.create_token_account(mint)?,
+        };
+        let mut instructions = Vec::new();
+        let open_orders_account = Keypair::new();
+        let mut signers = Vec::new();
+        let open_orders = match open_orders {
+            Some(open_orders) => open_orders,
+            None => {
+                instructions.push(self.create_account(&open_orders_account, serum::open_orders_space(), &self.dex_program)?);
+                signers.push(&open_orders_account);
+                open_orders_account.pubkey()
+            },
+        };
+        instructions.push(instructions::add_token(&owner, &self.group_key, mint, price_oracle, product_oracle, &token_pool,
+                                                  market, &open_orders, &self.dex_program, name));
+        self.send(&instructions, &signers)?;
+        println!("token description : {}", fruitbasket_client::pda::token_desc(mint).0);
+        println!("token pool : {}", token_pool);
+        println!("open orders : {}", open_orders);
+        Ok(())
+    }
+
+    pub fn add_basket(&self, file : &Path) -> Result<()> {
+        let content = std::fs::read_to_string(file)?;
+        let basket_file : BasketFile = match file.extension().and_then(|x| x.to_str()) {
+            Some("json") => serde_json::from_str(&content)?,
+            _ => toml::from_str(&content)?,
+        };
+        let components = basket_file.components.iter()
+            .map(|x| Ok(BasketComponentDescription { token_mint : Pubkey::from_str(&x.mint)?, amount : x.amount, decimal : x.decimal }))
+            .collect::<Result<Vec<_>>>()?;
+        if components.len() > u8::MAX as usize {
+            return Err("too many components".into());
+        }
+
+        let client = self.payer.pubkey();
+        let group = rpc::load_group(&self.rpc, &self.group_key)?;
+        let basket_number = group.number_of_baskets;
+        let basket_key = fruitbasket_client::pda::basket(basket_number).0;
+        let mut chunks = components.chunks(COMPONENTS_PER_INSTRUCTION);
+        let first_chunk = chunks.next().unwrap_or(&[]).to_vec();
+        self.send(&[instructions::add_basket(&client, &self.group_key, basket_number, basket_file.name, basket_file.description,
+                                             components.len() as u8, first_chunk, basket_file.creator_fee_bps)], &[])?;
+        for chunk in chunks {
+            self.send(&[instructions::add_basket_components(&client, &basket_key, chunk.to_vec())], &[])?;
+        }
+
+        // backing vaults, nav and creator fees in the base mint of the group
+        for chunk in components.chunks(COMPONENTS_PER_INSTRUCTION) {
+            let vaults = chunk.iter().map(|x| instructions::create_basket_vault(&client, &basket_key, &x.token_mint)).collect::<Vec<_>>();
+            self.send(&vaults, &[])?;
+        }
+        self.send(&[
+            instructions::create_basket_nav(&client, &basket_key),
+            instructions::create_creator_fee_vault(&client, &basket_key, &group.base_mint),
+        ], &[])?;
+        // each basket trades its legs with its own open orders on the markets of its components
+        for component in &components {
+            let token_desc = rpc::load_token_desc(&self.rpc, &fruitbasket_client::pda::token_desc(&component.token_mint).0)?;
+            let markets = std::iter::once(token_desc.market).chain(token_desc.quote_markets.iter().map(|x| x.market));
+            let open_orders = markets.filter(|x| *x != Pubkey::default())
+                .map(|x| instructions::create_basket_open_orders(&client, &basket_key, &x, &self.dex_program))
+                .collect::<Vec<_>>();
+            if !open_orders.is_empty() {
+                self.send(&open_orders, &[])?;
+            }
+        }
+        // quote left by a rebalance is kept in the vault of the base mint
+        if !components.iter().any(|x| x.token_mint == group.base_mint) {
+            self.send(&[instructions::create_basket_vault(&client, &basket_key, &group.base_mint)], &[])?;
+        }
+        println!("basket : {}", basket_key);
+        println!("basket mint : {}", fruitbasket_client::pda::basket_mint(basket_number).0);
+        Ok(())
+    }
+
+    pub fn show_basket(&self, basket_key : &Pubkey) -> Result<()> {
+        let basket = rpc::load_basket(&self.rpc, basket_key)?;
+        println!("basket : {}", basket_key);
+        println!("name : {}", basket.name);
+        println!("description : {}", basket.description);
+        println!("group : {}", basket.group);
+        println!("creator : {} (fee {} bps)", basket.creator, basket.creator_fee_bps);
+        println!("mint : {} (decimals {})", basket.basket_mint, basket.decimal);
+        println!("price : {} +/- {} at slot {}", basket.price, basket.confidence, basket.price_slot);
+        println!("book price : buy {} sell {} for {} at slot {}",
+                 basket.book_price.buy_price, basket.book_price.sell_price, basket.book_price.amount, basket.book_price.slot);
+        println!("ema price : {} at slot {}", basket.ema_price, basket.ema_slot);
+        println!("pause flags : {:#04x}", basket.pause_flags);
+        match basket.audit_timestamp {
+            Some(timestamp) => println!("solvency : {} bps at {}", basket.solvency_bps, timestamp),
+            None => println!("solvency : never audited"),
+        }
+        println!("open contexts : {}", basket.open_contexts);
+        if let Some(rebalance_context) = basket.rebalance_context {
+            println!("rebalancing with context {}", rebalance_context);
+        }
+        println!("components : {} of {}", basket.components.len(), basket.number_of_components);
+        for component in basket.components.iter() {
+            println!("  {} amount {} decimals {} price {} +/- {}",
+                     component.token_mint, component.amount, component.decimal, component.price, component.confidence);
+        }
+        Ok(())
+    }
+
+    pub fn list_tokens(&self) -> Result<()> {
+        for (key, token_desc) in rpc::load_token_descs(&self.rpc)? {
+            println!("{} {} mint {} market {} price {} +/- {} at slot {}{}",
+                     key, token_desc.name, token_desc.token_mint, token_desc.market,
+                     token_desc.cache.last_price, token_desc.cache.last_confidence, token_desc.cache.last_update_slot,
+                     if token_desc.paused { " paused" } else { "" });
+        }
+        Ok(())
+    }
+
+    // quote currencies first, then the components of the baskets of the group and finally the baskets
+    pub fn update_prices(&self) -> Result<()> {
+        let group = rpc::load_group(&self.rpc, &self.group_key)?;
+        for price_oracle in group.quote_currencies.iter().filter_map(|x| x.price_oracle) {
+            self.send(&[instructions::update_quote_price(&self.group_key, &price_oracle)], &[])?;
+        }
+        let baskets = rpc::load_baskets(&self.rpc, &self.group_key)?;
+        let components = baskets.iter()
+                            .flat_map(|(_, basket)| basket.components.iter().map(|x| x.token_mint))
+                            .collect::<HashSet<_>>();
+        for (_, token_desc) in rpc::load_token_descs(&self.rpc)? {
+            if components.contains(&token_desc.token_mint) {
+                self.send(&[instructions::update_price(&token_desc.group, &token_desc)], &[])?;
+                println!("updated price of {}", token_desc.name);
+            }
+        }
+        for (basket_key, basket) in baskets.iter().filter(|(_, basket)| basket.is_complete()) {
+            self.send(&[instructions::update_basket_price(basket_key, basket)], &[])?;
+            println!("updated price of basket {}", basket.name);
+        }
+        Ok(())
+    }
+}
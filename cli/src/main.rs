@@ -0,0 +1,124 @@
+//! Command line interface of the fruitbasket program.
+//! It sets up a group with its tokens and baskets, shows their state, refreshes their prices
+//! and simulates, opens or reverts trade contexts, against a local test validator by default.
+use clap::{Parser, Subcommand};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::read_keypair_file;
+use std::path::PathBuf;
+
+mod cli;
+mod group;
+mod trade;
+
+use cli::Cli;
+
+pub type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+#[derive(Parser)]
+#[clap(name = "fruitbasket-cli", about = "Operates a fruitbasket group")]
+struct Opts {
+    /// RPC url of the cluster, a local test validator by default
+    #[clap(long, default_value = "http://127.0.0.1:8899")]
+    url : String,
+    /// keypair signing and paying for the transactions, the solana cli keypair by default
+    #[clap(long)]
+    keypair : Option<String>,
+    /// group to operate, the group owned by the keypair by default
+    #[clap(long)]
+    group : Option<Pubkey>,
+    /// serum dex program of the markets of the group
+    #[clap(long, default_value_t = anchor_spl::dex::ID)]
+    dex_program : Pubkey,
+    #[clap(subcommand)]
+    command : Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Create the group owned by the keypair
+    InitGroup {
+        #[clap(long)]
+        quote_mint : Pubkey,
+        /// name of the quote mint, which is the base mint of the group
+        #[clap(long, default_value = "USDC")]
+        quote_name : String,
+        /// quote token account of the keypair, a new account is created when missing
+        #[clap(long)]
+        quote_pool : Option<Pubkey>,
+    },
+    /// List a token with its oracles and its market quoted in the base mint
+    AddToken {
+        #[clap(long)]
+        mint : Pubkey,
+        #[clap(long)]
+        name : String,
+        #[clap(long)]
+        price_oracle : Pubkey,
+        #[clap(long)]
+        product_oracle : Pubkey,
+        #[clap(long)]
+        market : Pubkey,
+        /// token account of the keypair, a new account is created when missing
+        #[clap(long)]
+        token_pool : Option<Pubkey>,
+        /// open orders account on the market, a new account is created when missing
+        #[clap(long)]
+        open_orders : Option<Pubkey>,
+    },
+    /// Create a basket from a TOML or JSON file
+    AddBasket {
+        /// file with the name, description, creator fee and components of the basket
+        #[clap(long)]
+        file : PathBuf,
+    },
+    /// Show a basket with its components and prices
+    ShowBasket {
+        basket : Pubkey,
+    },
+    /// List the tokens of the program with their cached price
+    ListTokens,
+    /// Update the token prices and the basket prices of the group
+    UpdatePrices,
+    /// Open a buy context on a basket
+    Buy(trade::TradeOpts),
+    /// Open a sell context on a basket
+    Sell(trade::TradeOpts),
+    /// Simulate a market buy or sell of a basket on the current orderbooks
+    Simulate(trade::SimulateOpts),
+    /// List the open trade contexts of the baskets of the group
+    ListContexts {
+        /// only the contexts of this user
+        #[clap(long)]
+        user : Option<Pubkey>,
+    },
+    /// Revert a trade context, its legs are bought or sold back by the keeper
+    Revert {
+        context : Pubkey,
+    },
+}
+
+fn main() -> Result<()> {
+    let opts = Opts::parse();
+    let keypair_path = match opts.keypair {
+        Some(path) => path,
+        None => format!("{}/.config/solana/id.json", std::env::var("HOME")?),
+    };
+    let payer = read_keypair_file(&keypair_path)?;
+    let cli = Cli::new(&opts.url, payer, opts.group, opts.dex_program);
+
+    match opts.command {
+        Command::InitGroup { quote_mint, quote_name, quote_pool } => cli.init_group(&quote_mint, quote_name, quote_pool),
+        Command::AddToken { mint, name, price_oracle, product_oracle, market, token_pool, open_orders } => {
+            cli.add_token(&mint, name, &price_oracle, &product_oracle, &market, token_pool, open_orders)
+        },
+        Command::AddBasket { file } => cli.add_basket(&file),
+        Command::ShowBasket { basket } => cli.show_basket(&basket),
+        Command::ListTokens => cli.list_tokens(),
+        Command::UpdatePrices => cli.update_prices(),
+        Command::Buy(trade_opts) => cli.trade(fruitbasket_client::ContextSide::Buy, &trade_opts),
+        Command::Sell(trade_opts) => cli.trade(fruitbasket_client::ContextSide::Sell, &trade_opts),
+        Command::Simulate(simulate_opts) => cli.simulate(&simulate_opts),
+        Command::ListContexts { user } => cli.list_contexts(user),
+        Command::Revert { context } => cli.revert(&context),
+    }
+}
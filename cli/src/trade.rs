@@ -0,0 +1,144 @@
+// Commands opening, listing and reverting trade contexts
+use clap::Args;
+use fruitbasket_client::simulation::{self, RevertReason};
+use fruitbasket_client::state::TradeContext;
+use fruitbasket_client::{instructions, pda, rpc, ContextOrderType, ContextSide};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signer;
+
+use crate::cli::Cli;
+use crate::Result;
+
+#[derive(Args)]
+pub struct TradeOpts {
+    basket : Pubkey,
+    /// amount of native basket tokens
+    amount : u64,
+    /// maximum buy price or minimum sell price of a whole basket, in native quote units
+    #[clap(long)]
+    price : u64,
+    /// quote currency of the context, the base mint of the group by default
+    #[clap(long)]
+    quote_mint : Option<Pubkey>,
+    /// rest every leg on the orderbooks instead of sweeping them
+    #[clap(long)]
+    limit : bool,
+    /// order id of the context, the first free one by default
+    #[clap(long)]
+    order_id : Option<u8>,
+}
+
+#[derive(Args)]
+pub struct SimulateOpts {
+    basket : Pubkey,
+    /// amount of native basket tokens
+    amount : u64,
+    /// simulate a sell instead of a buy
+    #[clap(long)]
+    sell : bool,
+    /// quote currency of the context, the base mint of the group by default
+    #[clap(long)]
+    quote_mint : Option<Pubkey>,
+}
+
+impl Cli {
+    pub fn trade(&self, side : ContextSide, opts : &TradeOpts) -> Result<()> {
+        let user = self.payer.pubkey();
+        let group = rpc::load_group(&self.rpc, &self.group_key)?;
+        let basket = rpc::load_basket(&self.rpc, &opts.basket)?;
+        let quote_mint = opts.quote_mint.unwrap_or(group.base_mint);
+        let order_id = match opts.order_id {
+            Some(order_id) => order_id,
+            None => self.free_order_id(&user)?,
+        };
+        let quote_token_account = self.associated_token_account(&quote_mint)?;
+        let basket_token_account = self.associated_token_account(&basket.basket_mint)?;
+        let order_type = if opts.limit { ContextOrderType::Limit } else { ContextOrderType::Market };
+        self.send(&[instructions::init_trade_context(&self.group_key, &group, &user, &opts.basket, &basket, &quote_mint,
+                                                     &quote_token_account, &basket_token_account, order_id, side,
+                                                     opts.amount, opts.price, order_type)?], &[])?;
+        // the legs are processed and the context finalized by the keeper
+        println!("trade context : {}", pda::trade_context(&user, order_id).0);
+        Ok(())
+    }
+
+    // market context simulated on the current orderbooks, the buy is not bounded by the quote deposited
+    pub fn simulate(&self, opts : &SimulateOpts) -> Result<()> {
+        let group = rpc::load_group(&self.rpc, &self.group_key)?;
+        let basket = rpc::load_basket(&self.rpc, &opts.basket)?;
+        let quote_mint = opts.quote_mint.unwrap_or(group.base_mint);
+        let mut token_descs = Vec::new();
+        let mut books = Vec::new();
+        for component in basket.components.iter() {
+            let token_desc = rpc::load_token_desc(&self.rpc, &pda::token_desc(&component.token_mint).0)?;
+            let (market, _) = token_desc.market_for(&quote_mint, &group.base_mint)?;
+            books.push(rpc::load_order_book(&self.rpc, &market, &self.dex_program)?);
+            token_descs.push(token_desc);
+        }
+        let side = if opts.sell { ContextSide::Sell } else { ContextSide::Buy };
+        let simulation = simulation::simulate_trade(&group, &basket, &token_descs, &books, &quote_mint, side,
+                                                    opts.amount, u64::MAX)?;
+
+        for (leg, token_desc) in simulation.legs.iter().zip(token_descs.iter()) {
+            println!("{} amount {} filled {} dust {} quote {} (taker fee {}) over {} orders",
+                     token_desc.name, leg.amount, leg.filled, leg.dust, leg.quote_amount, leg.taker_fee, leg.fills.len());
+        }
+        println!("fee {} creator fee {}", simulation.fee, simulation.creator_fee);
+        match simulation.revert {
+            Some(RevertReason::TokenPaused(leg)) => println!("reverts : {} is paused", token_descs[leg].name),
+            Some(RevertReason::LegOutOfBand(leg)) => println!("reverts : {} cannot be filled within its band", token_descs[leg].name),
+            Some(RevertReason::InsufficientQuoteForFee) => println!("reverts : the quote left does not cover the fees"),
+            None if opts.sell => println!("quote received : {}", simulation.quote_amount()),
+            None => println!("quote spent : {}", simulation.quote_amount()),
+        }
+        Ok(())
+    }
+
+    pub fn list_contexts(&self, user : Option<Pubkey>) -> Result<()> {
+        let baskets = rpc::load_baskets(&self.rpc, &self.group_key)?;
+        for (context_key, trade_context) in rpc::load_trade_contexts(&self.rpc)? {
+            if !baskets.iter().any(|(key, _)| *key == trade_context.basket) || user.is_some_and(|x| x != trade_context.user) {
+                continue;
+            }
+            print_context(&context_key, &trade_context);
+        }
+        Ok(())
+    }
+
+    pub fn revert(&self, context_key : &Pubkey) -> Result<()> {
+        let trade_context = rpc::load_trade_context(&self.rpc, context_key)?.ok_or("trade context not found")?;
+        let group = rpc::load_group(&self.rpc, &self.group_key)?;
+        self.send(&[instructions::revert_trade_context(&group, context_key, &trade_context)?], &[])?;
+        println!("reverting context {}", context_key);
+        Ok(())
+    }
+
+    // a user opens at most one context per order id
+    fn free_order_id(&self, user : &Pubkey) -> Result<u8> {
+        for order_id in 0..=u8::MAX {
+            let context_key = pda::trade_context(user, order_id).0;
+            if self.rpc.get_account_with_commitment(&context_key, self.rpc.commitment())?.value.is_none() {
+                return Ok(order_id);
+            }
+        }
+        Err("every order id is used".into())
+    }
+}
+
+fn print_context(context_key : &Pubkey, trade_context : &TradeContext) {
+    let side = match trade_context.side {
+        ContextSide::Buy => "buy",
+        ContextSide::Sell => "sell",
+        ContextSide::Swap => "swap",
+        ContextSide::Rebalance => "rebalance",
+    };
+    let order_type = match trade_context.order_type {
+        ContextOrderType::Market => "market",
+        ContextOrderType::Limit => "limit",
+    };
+    let treated_legs = trade_context.legs.iter().filter(|x| x.treated).count();
+    println!("{} {} {} basket {} amount {} user {} legs {}/{} created on {}{}",
+             context_key, side, order_type, trade_context.basket, trade_context.amount, trade_context.user,
+             treated_legs, trade_context.legs.len(), trade_context.created_on,
+             if trade_context.reverting { " reverting" } else { "" });
+}
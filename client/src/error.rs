@@ -0,0 +1,49 @@
+use anchor_lang::prelude::{ProgramError, Pubkey};
+use std::fmt;
+
+#[derive(Debug)]
+pub enum ClientError {
+    // the account data is not the expected fruitbasket account
+    InvalidAccount(&'static str),
+    UnknownQuoteCurrency(Pubkey),
+    QuoteCurrencyNotPriced(Pubkey),
+    // the token is not listed in the token descriptions given
+    UnknownToken(Pubkey),
+    // the token has no market for the quote currency
+    UnknownMarket(Pubkey),
+    UnknownLeg(usize),
+    Program(ProgramError),
+    #[cfg(feature = "rpc")]
+    Rpc(Box<solana_client::client_error::ClientError>),
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ClientError::InvalidAccount(account) => write!(f, "account is not a valid {}", account),
+            ClientError::UnknownQuoteCurrency(mint) => write!(f, "{} is not a quote currency of the group", mint),
+            ClientError::QuoteCurrencyNotPriced(mint) => write!(f, "quote currency {} has no price yet", mint),
+            ClientError::UnknownToken(mint) => write!(f, "token {} is not listed", mint),
+            ClientError::UnknownMarket(mint) => write!(f, "no market for token {} and the quote currency", mint),
+            ClientError::UnknownLeg(leg) => write!(f, "trade context has no leg {}", leg),
+            ClientError::Program(err) => write!(f, "{}", err),
+            #[cfg(feature = "rpc")]
+            ClientError::Rpc(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {}
+
+impl From<ProgramError> for ClientError {
+    fn from(err : ProgramError) -> Self {
+        ClientError::Program(err)
+    }
+}
+
+#[cfg(feature = "rpc")]
+impl From<solana_client::client_error::ClientError> for ClientError {
+    fn from(err : solana_client::client_error::ClientError) -> Self {
+        ClientError::Rpc(Box::new(err))
+    }
+}
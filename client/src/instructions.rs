@@ -0,0 +1,682 @@
+// Instructions of every entrypoint of the fruitbasket program, with all their accounts
+// Signers and payers are given by the caller, every other account is derived or read from the decoded state.
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::{system_program, sysvar};
+use anchor_lang::{InstructionData, ToAccountMetas};
+use fruitbasket::{accounts, instruction};
+
+use crate::serum::MarketAccounts;
+use crate::state::{Basket, BasketComponent, Group, TokenDescription, TradeContext};
+use crate::{pda, BasketComponentDescription, ClientError, ContextOrderType, ContextSide, GroupRole};
+
+fn build(accounts : Vec<AccountMeta>, data : impl InstructionData) -> Instruction {
+    Instruction {
+        program_id : fruitbasket::ID,
+        accounts,
+        data : data.data(),
+    }
+}
+
+// token description and vault of each component, in the order of the basket
+fn component_vaults(basket_key : &Pubkey, components : &[BasketComponent]) -> Vec<AccountMeta> {
+    components.iter()
+        .flat_map(|component| vec![
+            AccountMeta::new_readonly(pda::token_desc(&component.token_mint).0, false),
+            AccountMeta::new(pda::basket_vault(basket_key, &component.token_mint).0, false),
+        ])
+        .collect()
+}
+
+pub fn initialize_group(owner : &Pubkey, quote_token_mint : &Pubkey, quote_token_transaction_pool : &Pubkey, base_mint_name : String) -> Instruction {
+    let (group, bump_group) = pda::group(owner);
+    build(
+        accounts::InitializeGroup {
+            owner : *owner,
+            fruit_basket_grp : group,
+            quote_token_mint : *quote_token_mint,
+            quote_token_transaction_pool : *quote_token_transaction_pool,
+            system_program : system_program::ID,
+            token_program : anchor_spl::token::ID,
+        }.to_account_metas(None),
+        instruction::InitializeGroup { _bump_group : bump_group, base_mint_name },
+    )
+}
+
+fn configure_group(owner : &Pubkey, group : &Pubkey) -> Vec<AccountMeta> {
+    accounts::ConfigureGroup { owner : *owner, group : *group }.to_account_metas(None)
+}
+
+pub fn set_leg_slippage(owner : &Pubkey, group : &Pubkey, slippage_bps : u64) -> Instruction {
+    build(configure_group(owner, group), instruction::SetLegSlippage { slippage_bps })
+}
+
+pub fn set_fees(owner : &Pubkey, group : &Pubkey, mint_fee_bps : u64, redeem_fee_bps : u64) -> Instruction {
+    build(configure_group(owner, group), instruction::SetFees { mint_fee_bps, redeem_fee_bps })
+}
+
+pub fn propose_owner(owner : &Pubkey, group : &Pubkey, new_owner : Pubkey) -> Instruction {
+    build(configure_group(owner, group), instruction::ProposeOwner { new_owner })
+}
+
+pub fn accept_ownership(new_owner : &Pubkey, group : &Pubkey) -> Instruction {
+    build(
+        accounts::AcceptOwnership { new_owner : *new_owner, group : *group }.to_account_metas(None),
+        instruction::AcceptOwnership {},
+    )
+}
+
+pub fn set_role(owner : &Pubkey, group : &Pubkey, role : GroupRole, member : Pubkey) -> Instruction {
+    build(configure_group(owner, group), instruction::SetRole { role, member })
+}
+
+pub fn set_group_pause(pauser : &Pubkey, group : &Pubkey, pause_flags : u8) -> Instruction {
+    build(configure_group(pauser, group), instruction::SetGroupPause { pause_flags })
+}
+
+pub fn set_basket_pause(pauser : &Pubkey, group : &Pubkey, basket : &Pubkey, pause_flags : u8) -> Instruction {
+    build(
+        accounts::PauseBasket { pauser : *pauser, group : *group, basket : *basket }.to_account_metas(None),
+        instruction::SetBasketPause { pause_flags },
+    )
+}
+
+pub fn set_token_pause(pauser : &Pubkey, group : &Pubkey, token_mint : &Pubkey, paused : bool) -> Instruction {
+    build(
+        accounts::PauseToken { pauser : *pauser, group : *group, token_desc : pda::token_desc(token_mint).0 }.to_account_metas(None),
+        instruction::SetTokenPause { paused },
+    )
+}
+
+pub fn set_max_price_age(owner : &Pubkey, group : &Pubkey, max_price_age_slots : u64) -> Instruction {
+    build(configure_group(owner, group), instruction::SetMaxPriceAge { max_price_age_slots })
+}
+
+pub fn set_ema_config(owner : &Pubkey, group : &Pubkey, ema_window_slots : u64, ema_bound : bool) -> Instruction {
+    build(configure_group(owner, group), instruction::SetEmaConfig { ema_window_slots, ema_bound })
+}
+
+pub fn set_max_creator_fee(owner : &Pubkey, group : &Pubkey, max_creator_fee_bps : u64) -> Instruction {
+    build(configure_group(owner, group), instruction::SetMaxCreatorFee { max_creator_fee_bps })
+}
+
+pub fn set_audit_auto_pause(pauser : &Pubkey, group : &Pubkey, audit_auto_pause : bool) -> Instruction {
+    build(configure_group(pauser, group), instruction::SetAuditAutoPause { audit_auto_pause })
+}
+
+pub fn create_fee_vault(owner : &Pubkey, group : &Pubkey, quote_token_mint : &Pubkey) -> Instruction {
+    let (fee_vault, bump) = pda::fee_vault(group, quote_token_mint);
+    build(
+        accounts::CreateFeeVault {
+            owner : *owner,
+            group : *group,
+            quote_token_mint : *quote_token_mint,
+            fee_vault,
+            fruit_basket_authority : pda::authority().0,
+            system_program : system_program::ID,
+            token_program : anchor_spl::token::ID,
+            rent : sysvar::rent::ID,
+        }.to_account_metas(None),
+        instruction::CreateFeeVault { _bump : bump },
+    )
+}
+
+pub fn withdraw_fees(owner : &Pubkey, group : &Pubkey, quote_token_mint : &Pubkey, destination : &Pubkey, amount : u64) -> Instruction {
+    build(
+        accounts::WithdrawFees {
+            owner : *owner,
+            group : *group,
+            fee_vault : pda::fee_vault(group, quote_token_mint).0,
+            destination : *destination,
+            fruit_basket_authority : pda::authority().0,
+            token_program : anchor_spl::token::ID,
+        }.to_account_metas(None),
+        instruction::WithdrawFees { amount },
+    )
+}
+
+// the authority of the transaction pool is given to the program
+pub fn add_quote_currency(owner : &Pubkey, group : &Pubkey, quote_token_mint : &Pubkey, quote_token_transaction_pool : &Pubkey, price_oracle : &Pubkey) -> Instruction {
+    build(
+        accounts::AddQuoteCurrency {
+            owner : *owner,
+            group : *group,
+            quote_token_mint : *quote_token_mint,
+            quote_token_transaction_pool : *quote_token_transaction_pool,
+            price_oracle : *price_oracle,
+            token_program : anchor_spl::token::ID,
+        }.to_account_metas(None),
+        instruction::AddQuoteCurrency {},
+    )
+}
+
+pub fn update_quote_price(group : &Pubkey, price_oracle : &Pubkey) -> Instruction {
+    build(
+        accounts::UpdateQuotePrice { group : *group, oracle_ai : *price_oracle }.to_account_metas(None),
+        instruction::UpdateQuotePrice {},
+    )
+}
+
+// the open orders account is created beforehand with the dex program as owner, see serum::open_orders_space
+#[allow(clippy::too_many_arguments)]
+pub fn add_token(owner : &Pubkey, group : &Pubkey, mint : &Pubkey, price_oracle : &Pubkey, product_oracle : &Pubkey, token_pool : &Pubkey,
+                 market : &Pubkey, open_orders : &Pubkey, dex_program : &Pubkey, name : String) -> Instruction {
+    let (token_desc, bump) = pda::token_desc(mint);
+    build(
+        accounts::AddToken {
+            owner : *owner,
+            fruit_basket_grp : *group,
+            mint : *mint,
+            price_oracle : *price_oracle,
+            product_oracle : *product_oracle,
+            token_pool : *token_pool,
+            market : *market,
+            token_desc,
+            open_orders_account : *open_orders,
+            fruitbasket_authority : pda::authority().0,
+            token_program : anchor_spl::token::ID,
+            system_program : system_program::ID,
+            dex_program : *dex_program,
+            rent : sysvar::rent::ID,
+        }.to_account_metas(None),
+        instruction::AddToken { _bump : bump, name },
+    )
+}
+
+pub fn configure_token(owner : &Pubkey, group : &Pubkey, token_mint : &Pubkey, max_confidence_bps : u64, max_deviation_bps : u64) -> Instruction {
+    build(
+        accounts::ConfigureToken { owner : *owner, group : *group, token_desc : pda::token_desc(token_mint).0 }.to_account_metas(None),
+        instruction::ConfigureToken { max_confidence_bps, max_deviation_bps },
+    )
+}
+
+pub fn add_token_market(owner : &Pubkey, group : &Pubkey, token_mint : &Pubkey, quote_token_mint : &Pubkey,
+                        market : &Pubkey, open_orders : &Pubkey, dex_program : &Pubkey) -> Instruction {
+    build(
+        accounts::AddTokenMarket {
+            owner : *owner,
+            group : *group,
+            token_desc : pda::token_desc(token_mint).0,
+            quote_token_mint : *quote_token_mint,
+            market : *market,
+            open_orders_account : *open_orders,
+            fruitbasket_authority : pda::authority().0,
+            dex_program : *dex_program,
+            rent : sysvar::rent::ID,
+        }.to_account_metas(None),
+        instruction::AddTokenMarket {},
+    )
+}
+
+// token descriptions of the components, checked by the program when they are added to a basket
+fn component_token_descs(components : &[BasketComponentDescription]) -> Vec<AccountMeta> {
+    components.iter().map(|x| AccountMeta::new_readonly(pda::token_desc(&x.token_mint).0, false)).collect()
+}
+
+// the basket number is the number of baskets of the group when the basket is created
+#[allow(clippy::too_many_arguments)]
+pub fn add_basket(client : &Pubkey, group : &Pubkey, basket_number : u64, basket_name : String, basket_desc : String,
+                  number_of_components : u8, basket_components : Vec<BasketComponentDescription>, creator_fee_bps : u64) -> Instruction {
+    let (basket, basket_bump) = pda::basket(basket_number);
+    let (basket_mint, basket_mint_bump) = pda::basket_mint(basket_number);
+    let mut accounts = accounts::AddBasket {
+        client : *client,
+        group : *group,
+        basket,
+        basket_mint,
+        system_program : system_program::ID,
+        token_program : anchor_spl::token::ID,
+        rent : sysvar::rent::ID,
+    }.to_account_metas(None);
+    accounts.extend(component_token_descs(&basket_components));
+    build(
+        accounts,
+        instruction::AddBasket {
+            basket_number,
+            _basket_bump : basket_bump,
+            _basket_mint_bump : basket_mint_bump,
+            basket_name,
+            basket_desc,
+            number_of_components,
+            basket_components,
+            creator_fee_bps,
+        },
+    )
+}
+
+pub fn add_basket_components(client : &Pubkey, basket : &Pubkey, basket_components : Vec<BasketComponentDescription>) -> Instruction {
+    let mut accounts = accounts::AddBasketComponents { client : *client, basket : *basket }.to_account_metas(None);
+    accounts.extend(component_token_descs(&basket_components));
+    build(accounts, instruction::AddBasketComponents { basket_components })
+}
+
+pub fn create_creator_fee_vault(payer : &Pubkey, basket : &Pubkey, quote_token_mint : &Pubkey) -> Instruction {
+    let (creator_fee_vault, bump) = pda::creator_fee_vault(basket, quote_token_mint);
+    build(
+        accounts::CreateCreatorFeeVault {
+            payer : *payer,
+            basket : *basket,
+            quote_token_mint : *quote_token_mint,
+            creator_fee_vault,
+            fruit_basket_authority : pda::authority().0,
+            system_program : system_program::ID,
+            token_program : anchor_spl::token::ID,
+            rent : sysvar::rent::ID,
+        }.to_account_metas(None),
+        instruction::CreateCreatorFeeVault { _bump : bump },
+    )
+}
+
+pub fn claim_creator_fees(creator : &Pubkey, basket : &Pubkey, quote_token_mint : &Pubkey, destination : &Pubkey) -> Instruction {
+    build(
+        accounts::ClaimCreatorFees {
+            creator : *creator,
+            basket : *basket,
+            creator_fee_vault : pda::creator_fee_vault(basket, quote_token_mint).0,
+            destination : *destination,
+            fruit_basket_authority : pda::authority().0,
+            token_program : anchor_spl::token::ID,
+        }.to_account_metas(None),
+        instruction::ClaimCreatorFees {},
+    )
+}
+
+pub fn create_basket_vault(payer : &Pubkey, basket : &Pubkey, token_mint : &Pubkey) -> Instruction {
+    let (basket_vault, bump) = pda::basket_vault(basket, token_mint);
+    build(
+        accounts::CreateBasketVault {
+            payer : *payer,
+            basket : *basket,
+            token_mint : *token_mint,
+            basket_vault,
+            fruit_basket_authority : pda::authority().0,
+            system_program : system_program::ID,
+            token_program : anchor_spl::token::ID,
+            rent : sysvar::rent::ID,
+        }.to_account_metas(None),
+        instruction::CreateBasketVault { _bump : bump },
+    )
+}
+
+// backing of count components from first_component
+pub fn view_basket_backing(basket_key : &Pubkey, basket : &Basket, first_component : u8, count : usize) -> Instruction {
+    let mut accounts = accounts::ViewBasketBacking { basket : *basket_key, basket_token_mint : basket.basket_mint }.to_account_metas(None);
+    let first = first_component as usize;
+    accounts.extend(component_vaults(basket_key, &basket.components[first..first + count]));
+    build(accounts, instruction::ViewBasketBacking { first_component })
+}
+
+// audit of count components from first_component, the solvency is recorded by the instruction auditing the last component
+pub fn audit_basket(basket_key : &Pubkey, basket : &Basket, first_component : u8, count : usize) -> Instruction {
+    let mut accounts = accounts::AuditBasket {
+        group : basket.group,
+        basket : *basket_key,
+        basket_token_mint : basket.basket_mint,
+    }.to_account_metas(None);
+    let first = first_component as usize;
+    accounts.extend(component_vaults(basket_key, &basket.components[first..first + count]));
+    build(accounts, instruction::AuditBasket { first_component })
+}
+
+pub fn update_price(group : &Pubkey, token_desc : &TokenDescription) -> Instruction {
+    build(
+        accounts::UpdatePrice {
+            group : *group,
+            token_desc : pda::token_desc(&token_desc.token_mint).0,
+            oracle_ai : token_desc.price_oracle,
+        }.to_account_metas(None),
+        instruction::UpdatePrice {},
+    )
+}
+
+pub fn update_basket_price(basket_key : &Pubkey, basket : &Basket) -> Instruction {
+    let mut accounts = accounts::UpdateBasketPrice {
+        group : basket.group,
+        basket : *basket_key,
+        basket_nav : pda::basket_nav(basket_key).0,
+    }.to_account_metas(None);
+    accounts.extend(basket.components.iter().map(|x| AccountMeta::new_readonly(pda::token_desc(&x.token_mint).0, false)));
+    build(accounts, instruction::UpdateBasketPrice {})
+}
+
+pub fn create_basket_nav(payer : &Pubkey, basket : &Pubkey) -> Instruction {
+    let (basket_nav, bump) = pda::basket_nav(basket);
+    build(
+        accounts::CreateBasketNav {
+            payer : *payer,
+            basket : *basket,
+            basket_nav,
+            system_program : system_program::ID,
+        }.to_account_metas(None),
+        instruction::CreateBasketNav { bump },
+    )
+}
+
+pub fn create_basket_open_orders(payer : &Pubkey, basket : &Pubkey, market : &Pubkey, dex_program : &Pubkey) -> Instruction {
+    let (open_orders, bump) = pda::basket_open_orders(basket, market);
+    build(
+        accounts::CreateBasketOpenOrders {
+            payer : *payer,
+            basket : *basket,
+            market : *market,
+            open_orders,
+            fruitbasket_authority : pda::authority().0,
+            dex_program : *dex_program,
+            system_program : system_program::ID,
+            rent : sysvar::rent::ID,
+        }.to_account_metas(None),
+        instruction::CreateBasketOpenOrders { bump },
+    )
+}
+
+// markets are the base mint markets of the components walked from first_component, in the order of the basket
+// prices are updated by the instruction walking the last component
+pub fn update_basket_book_price(basket_key : &Pubkey, basket : &Basket, first_component : u8, markets : &[MarketAccounts], dex_program : &Pubkey, amount : u64) -> Instruction {
+    let mut accounts = accounts::UpdateBasketBookPrice {
+        group : basket.group,
+        basket : *basket_key,
+        dex_program : *dex_program,
+    }.to_account_metas(None);
+    for (component, market) in basket.components.iter().skip(first_component as usize).zip(markets.iter()) {
+        accounts.push(AccountMeta::new_readonly(pda::token_desc(&component.token_mint).0, false));
+        accounts.push(AccountMeta::new_readonly(market.market, false));
+        accounts.push(AccountMeta::new_readonly(market.bids, false));
+        accounts.push(AccountMeta::new_readonly(market.asks, false));
+    }
+    build(accounts, instruction::UpdateBasketBookPrice { amount, first_component })
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn init_trade_context(group_key : &Pubkey, group : &Group, user : &Pubkey, basket_key : &Pubkey, basket : &Basket,
+                          quote_token_mint : &Pubkey, quote_token_account : &Pubkey, basket_token_account : &Pubkey,
+                          order_id : u8, side : ContextSide, amount : u64, max_buy_or_min_sell_price : u64,
+                          order_type : ContextOrderType) -> Result<Instruction, ClientError> {
+    let quote = group.quote_currency(quote_token_mint)?;
+    let (trade_context, context_bump) = pda::trade_context(user, order_id);
+    Ok(build(
+        accounts::InitTradeContext {
+            group : *group_key,
+            user : *user,
+            basket : *basket_key,
+            quote_token_account : *quote_token_account,
+            basket_token_account : *basket_token_account,
+            quote_token_mint : *quote_token_mint,
+            basket_token_mint : basket.basket_mint,
+            trade_context,
+            quote_token_transaction_pool : quote.transaction_pool,
+            fruit_basket_authority : pda::authority().0,
+            token_program : anchor_spl::token::ID,
+            system_program : system_program::ID,
+        }.to_account_metas(None),
+        instruction::InitTradeContext {
+            _order_id : order_id,
+            _context_bump : context_bump,
+            side,
+            amount,
+            max_buy_or_min_sell_price,
+            order_type,
+        },
+    ))
+}
+
+// the market is the market of the leg token for the quote currency of the context
+#[allow(clippy::too_many_arguments)]
+pub fn process_token_for_context(group_key : &Pubkey, group : &Group, context_key : &Pubkey, trade_context : &TradeContext, leg : usize,
+                                 token_desc : &TokenDescription, market : &MarketAccounts, dex_program : &Pubkey) -> Result<Instruction, ClientError> {
+    let token_mint = trade_context.legs.get(leg).ok_or(ClientError::UnknownLeg(leg))?.token_mint;
+    let quote = group.quote_currency(&trade_context.quote_token_mint)?;
+    let (market_key, _) = token_desc.market_for(&quote.mint, &group.base_mint)?;
+    let leg_basket = trade_context.leg_basket(leg)?;
+    Ok(build(
+        accounts::ProcessTokenOnContext {
+            group : *group_key,
+            trade_context : *context_key,
+            token_desc : pda::token_desc(&token_mint).0,
+            token_mint,
+            quote_token_mint : quote.mint,
+            fruitbasket : trade_context.basket,
+            market : market.market,
+            open_orders : pda::basket_open_orders(&leg_basket, &market_key).0,
+            request_queue : market.request_queue,
+            event_queue : market.event_queue,
+            bids : market.bids,
+            asks : market.asks,
+            token_vault : market.coin_vault,
+            quote_token_vault : market.pc_vault,
+            vault_signer : market.vault_signer,
+            token_pool : pda::basket_vault(&leg_basket, &token_mint).0,
+            quote_token_transaction_pool : quote.transaction_pool,
+            fruit_basket_authority : pda::authority().0,
+            dex_program : *dex_program,
+            token_program : anchor_spl::token::ID,
+            rent : sysvar::rent::ID,
+        }.to_account_metas(None),
+        instruction::ProcessTokenForContext {},
+    ))
+}
+
+pub fn finalize_context(group_key : &Pubkey, group : &Group, context_key : &Pubkey, trade_context : &TradeContext, basket : &Basket) -> Result<Instruction, ClientError> {
+    let quote = group.quote_currency(&trade_context.quote_token_mint)?;
+    Ok(build(
+        accounts::FinalizeContext {
+            fruitbasket_group : *group_key,
+            trade_context : *context_key,
+            fruitbasket : trade_context.basket,
+            quote_token_account : trade_context.quote_token_account,
+            basket_token_account : trade_context.basket_token_account,
+            quote_token_transaction_pool : quote.transaction_pool,
+            fee_vault : pda::fee_vault(group_key, &quote.mint).0,
+            creator_fee_vault : pda::creator_fee_vault(&trade_context.basket, &quote.mint).0,
+            fruit_basket_authority : pda::authority().0,
+            quote_token_mint : quote.mint,
+            basket_token_mint : basket.basket_mint,
+            user : trade_context.user,
+            token_program : anchor_spl::token::ID,
+            system_program : system_program::ID,
+        }.to_account_metas(None),
+        instruction::FinalizeContext {},
+    ))
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn init_swap_context(group_key : &Pubkey, group : &Group, user : &Pubkey, source_basket_key : &Pubkey, source_basket : &Basket,
+                         destination_basket_key : &Pubkey, quote_token_mint : &Pubkey, quote_token_account : &Pubkey,
+                         source_basket_token_account : &Pubkey, destination_basket_token_account : &Pubkey,
+                         order_id : u8, amount : u64, destination_amount : u64, max_quote_in : u64) -> Result<Instruction, ClientError> {
+    let quote = group.quote_currency(quote_token_mint)?;
+    let (trade_context, context_bump) = pda::trade_context(user, order_id);
+    Ok(build(
+        accounts::InitSwapContext {
+            group : *group_key,
+            user : *user,
+            source_basket : *source_basket_key,
+            destination_basket : *destination_basket_key,
+            quote_token_account : *quote_token_account,
+            source_basket_token_account : *source_basket_token_account,
+            destination_basket_token_account : *destination_basket_token_account,
+            quote_token_mint : *quote_token_mint,
+            source_basket_token_mint : source_basket.basket_mint,
+            trade_context,
+            quote_token_transaction_pool : quote.transaction_pool,
+            fruit_basket_authority : pda::authority().0,
+            token_program : anchor_spl::token::ID,
+            system_program : system_program::ID,
+        }.to_account_metas(None),
+        instruction::InitSwapContext {
+            _order_id : order_id,
+            _context_bump : context_bump,
+            amount,
+            destination_amount,
+            max_quote_in,
+        },
+    ))
+}
+
+// legs shared by both baskets are passed as remaining accounts to be moved between the basket vaults
+pub fn finalize_swap_context(group_key : &Pubkey, group : &Group, context_key : &Pubkey, trade_context : &TradeContext,
+                             source_basket : &Basket, destination_basket : &Basket) -> Result<Instruction, ClientError> {
+    let quote = group.quote_currency(&trade_context.quote_token_mint)?;
+    let destination = trade_context.destination.as_ref().ok_or(ClientError::InvalidAccount("swap context"))?;
+    let mut accounts = accounts::FinalizeSwapContext {
+        fruitbasket_group : *group_key,
+        trade_context : *context_key,
+        source_basket : trade_context.basket,
+        destination_basket : destination.basket,
+        quote_token_account : trade_context.quote_token_account,
+        source_basket_token_account : trade_context.basket_token_account,
+        destination_basket_token_account : destination.basket_token_account,
+        quote_token_transaction_pool : quote.transaction_pool,
+        fee_vault : pda::fee_vault(group_key, &quote.mint).0,
+        source_creator_fee_vault : pda::creator_fee_vault(&trade_context.basket, &quote.mint).0,
+        destination_creator_fee_vault : pda::creator_fee_vault(&destination.basket, &quote.mint).0,
+        fruit_basket_authority : pda::authority().0,
+        quote_token_mint : quote.mint,
+        source_basket_token_mint : source_basket.basket_mint,
+        destination_basket_token_mint : destination_basket.basket_mint,
+        user : trade_context.user,
+        token_program : anchor_spl::token::ID,
+        system_program : system_program::ID,
+    }.to_account_metas(None);
+    for leg in trade_context.legs.iter().filter(|x| x.transfer_amount > 0) {
+        accounts.push(AccountMeta::new_readonly(pda::token_desc(&leg.token_mint).0, false));
+        accounts.push(AccountMeta::new(pda::basket_vault(&trade_context.basket, &leg.token_mint).0, false));
+        accounts.push(AccountMeta::new(pda::basket_vault(&destination.basket, &leg.token_mint).0, false));
+    }
+    Ok(build(accounts, instruction::FinalizeSwapContext {}))
+}
+
+// legs are netted against the vaults of the current components
+pub fn init_rebalance_context(group_key : &Pubkey, curator : &Pubkey, basket_key : &Pubkey, basket : &Basket,
+                              order_id : u8, new_components : Vec<BasketComponentDescription>) -> Instruction {
+    let (trade_context, context_bump) = pda::trade_context(curator, order_id);
+    let mut accounts = accounts::InitRebalanceContext {
+        group : *group_key,
+        curator : *curator,
+        basket : *basket_key,
+        basket_token_mint : basket.basket_mint,
+        trade_context,
+        system_program : system_program::ID,
+    }.to_account_metas(None);
+    accounts.extend(component_vaults(basket_key, &basket.components));
+    build(accounts, instruction::InitRebalanceContext {
+        _order_id : order_id,
+        _context_bump : context_bump,
+        new_components,
+    })
+}
+
+// rebalances trade against the base mint, the quote left is kept in the basket vault of the base mint
+pub fn finalize_rebalance_context(group_key : &Pubkey, group : &Group, context_key : &Pubkey, trade_context : &TradeContext) -> Instruction {
+    build(
+        accounts::FinalizeRebalanceContext {
+            group : *group_key,
+            trade_context : *context_key,
+            basket : trade_context.basket,
+            quote_token_transaction_pool : group.quote_token_transaction_pool,
+            basket_quote_vault : pda::basket_vault(&trade_context.basket, &group.base_mint).0,
+            fruit_basket_authority : pda::authority().0,
+            curator : trade_context.user,
+            token_program : anchor_spl::token::ID,
+        }.to_account_metas(None),
+        instruction::FinalizeRebalanceContext {},
+    )
+}
+
+pub fn revert_trade_context(group : &Group, context_key : &Pubkey, trade_context : &TradeContext) -> Result<Instruction, ClientError> {
+    let quote = group.quote_currency(&trade_context.quote_token_mint)?;
+    Ok(build(
+        accounts::RevertTradeContext {
+            trade_context : *context_key,
+            fruitbasket : trade_context.basket,
+            quote_token_transaction_pool : quote.transaction_pool,
+        }.to_account_metas(None),
+        instruction::RevertTradeContext {},
+    ))
+}
+
+// the authority is the user of the context, or the curator of its basket for a limit context
+pub fn cancel_trade_context(group_key : &Pubkey, group : &Group, authority : &Pubkey, context_key : &Pubkey, trade_context : &TradeContext)
+                            -> Result<Instruction, ClientError> {
+    let quote = group.quote_currency(&trade_context.quote_token_mint)?;
+    Ok(build(
+        accounts::CancelTradeContext {
+            group : *group_key,
+            authority : *authority,
+            trade_context : *context_key,
+            fruitbasket : trade_context.basket,
+            quote_token_transaction_pool : quote.transaction_pool,
+        }.to_account_metas(None),
+        instruction::CancelTradeContext {},
+    ))
+}
+
+// the basket tokens of a redeem are burned here, those of a creation are minted by finalize_in_kind_context
+pub fn init_in_kind_context(user : &Pubkey, basket_key : &Pubkey, basket : &Basket, basket_token_account : &Pubkey, amount : u64, redeem : bool) -> Instruction {
+    let (in_kind_context, context_bump) = pda::in_kind_context(user, basket_key);
+    build(
+        accounts::InitInKindContext {
+            user : *user,
+            group : basket.group,
+            fruitbasket : *basket_key,
+            basket_token_account : *basket_token_account,
+            basket_token_mint : basket.basket_mint,
+            in_kind_context,
+            token_program : anchor_spl::token::ID,
+            system_program : system_program::ID,
+        }.to_account_metas(None),
+        instruction::InitInKindContext {
+            _context_bump : context_bump,
+            amount,
+            redeem,
+        },
+    )
+}
+
+// token accounts of the user for the components from first_component, in the order of the basket
+// the user signs when the components are taken from the accounts of the user, anyone can give them back
+pub fn process_in_kind(user : &Pubkey, basket_key : &Pubkey, basket : &Basket, first_component : u8, token_accounts : &[Pubkey],
+                       from_user : bool) -> Instruction {
+    let mut accounts = accounts::ProcessInKind {
+        user : *user,
+        in_kind_context : pda::in_kind_context(user, basket_key).0,
+        fruitbasket : *basket_key,
+        fruit_basket_authority : pda::authority().0,
+        token_program : anchor_spl::token::ID,
+    }.to_account_metas(None);
+    accounts[0].is_signer = from_user;
+    let components = basket.components.iter().skip(first_component as usize);
+    for (component, token_account) in components.zip(token_accounts.iter()) {
+        accounts.push(AccountMeta::new_readonly(pda::token_desc(&component.token_mint).0, false));
+        accounts.push(AccountMeta::new(*token_account, false));
+        accounts.push(AccountMeta::new(pda::basket_vault(basket_key, &component.token_mint).0, false));
+    }
+    build(accounts, instruction::ProcessInKind { first_component })
+}
+
+pub fn finalize_in_kind_context(user : &Pubkey, basket_key : &Pubkey, basket : &Basket, basket_token_account : &Pubkey) -> Instruction {
+    build(
+        accounts::FinalizeInKindContext {
+            in_kind_context : pda::in_kind_context(user, basket_key).0,
+            fruitbasket : *basket_key,
+            basket_token_account : *basket_token_account,
+            basket_token_mint : basket.basket_mint,
+            user : *user,
+            fruit_basket_authority : pda::authority().0,
+            token_program : anchor_spl::token::ID,
+        }.to_account_metas(None),
+        instruction::FinalizeInKindContext {},
+    )
+}
+
+// the authority is the user of the context, the creator of its basket or a basket curator of the group
+pub fn cancel_in_kind_context(authority : &Pubkey, user : &Pubkey, basket_key : &Pubkey, basket : &Basket) -> Instruction {
+    build(
+        accounts::CancelInKindContext {
+            group : basket.group,
+            authority : *authority,
+            in_kind_context : pda::in_kind_context(user, basket_key).0,
+            fruitbasket : *basket_key,
+        }.to_account_metas(None),
+        instruction::CancelInKindContext {},
+    )
+}
@@ -0,0 +1,16 @@
+//! Rust client of the fruitbasket program.
+//! Derives the program addresses, builds the instructions of every entrypoint with all their accounts
+//! and decodes the program accounts into plain rust types.
+//! Trade contexts can be simulated against snapshots of the serum orderbooks before they are created.
+pub mod error;
+pub mod instructions;
+pub mod pda;
+#[cfg(feature = "rpc")]
+pub mod rpc;
+pub mod serum;
+pub mod simulation;
+pub mod state;
+
+pub use error::ClientError;
+pub use fruitbasket::ID as PROGRAM_ID;
+pub use fruitbasket::states::{BasketComponentDescription, ContextOrderType, ContextSide, GroupRole};
@@ -0,0 +1,59 @@
+// Program derived addresses of the fruitbasket program, with their bump seeds
+use anchor_lang::prelude::Pubkey;
+use fruitbasket::{FRUIT_BASKET, FRUIT_BASKET_AUTHORITY, FRUIT_BASKET_CONTEXT, FRUIT_BASKET_CREATOR_FEE_VAULT, FRUIT_BASKET_FEE_VAULT,
+                  FRUIT_BASKET_GROUP, FRUIT_BASKET_IN_KIND, FRUIT_BASKET_MINT, FRUIT_BASKET_NAV, FRUIT_BASKET_OPEN_ORDERS, FRUIT_BASKET_TOKEN, FRUIT_BASKET_VAULT};
+
+// group of an owner, an owner can only create one group
+pub fn group(owner : &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[FRUIT_BASKET_GROUP, &owner.to_bytes()], &fruitbasket::ID)
+}
+
+// authority of all the token accounts and mints of the program
+pub fn authority() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[FRUIT_BASKET_AUTHORITY], &fruitbasket::ID)
+}
+
+pub fn basket(basket_number : u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[FRUIT_BASKET, &basket_number.to_le_bytes()], &fruitbasket::ID)
+}
+
+pub fn basket_mint(basket_number : u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[FRUIT_BASKET_MINT, &basket_number.to_le_bytes()], &fruitbasket::ID)
+}
+
+// trade context of a user, or of a curator for a rebalance, the order id allows several open contexts
+pub fn trade_context(user : &Pubkey, order_id : u8) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[FRUIT_BASKET_CONTEXT, &user.to_bytes(), &[order_id]], &fruitbasket::ID)
+}
+
+// in kind context of a user, a single one per basket
+pub fn in_kind_context(user : &Pubkey, basket : &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[FRUIT_BASKET_IN_KIND, &user.to_bytes(), &basket.to_bytes()], &fruitbasket::ID)
+}
+
+pub fn token_desc(token_mint : &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[FRUIT_BASKET_TOKEN, &token_mint.to_bytes()], &fruitbasket::ID)
+}
+
+pub fn fee_vault(group : &Pubkey, quote_token_mint : &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[FRUIT_BASKET_FEE_VAULT, &group.to_bytes(), &quote_token_mint.to_bytes()], &fruitbasket::ID)
+}
+
+pub fn creator_fee_vault(basket : &Pubkey, quote_token_mint : &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[FRUIT_BASKET_CREATOR_FEE_VAULT, &basket.to_bytes(), &quote_token_mint.to_bytes()], &fruitbasket::ID)
+}
+
+// pyth compatible price account of a basket
+pub fn basket_nav(basket : &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[FRUIT_BASKET_NAV, &basket.to_bytes()], &fruitbasket::ID)
+}
+
+// vault of a basket holding one of its components
+pub fn basket_vault(basket : &Pubkey, token_mint : &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[FRUIT_BASKET_VAULT, &basket.to_bytes(), &token_mint.to_bytes()], &fruitbasket::ID)
+}
+
+// serum open orders of a basket on the market of one of its components
+pub fn basket_open_orders(basket : &Pubkey, market : &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[FRUIT_BASKET_OPEN_ORDERS, &basket.to_bytes(), &market.to_bytes()], &fruitbasket::ID)
+}
@@ -0,0 +1,89 @@
+// Loads the program accounts through an rpc node
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::Discriminator;
+use fruitbasket::states::{self, BASKET_DESC_MAGIC, BASKET_TRADE_CONTEXT_MAGIC, TOKEN_DESC_MAGIC};
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::rpc_client::RpcClient;
+use solana_client::rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig};
+use solana_client::rpc_filter::{Memcmp, RpcFilterType};
+
+use crate::serum::{MarketAccounts, OrderBook};
+use crate::state::{Basket, Group, TokenDescription, TradeContext, BASKET_GROUP_OFFSET, MAGIC_OFFSET};
+use crate::ClientError;
+
+pub fn load_group(rpc : &RpcClient, group : &Pubkey) -> Result<Group, ClientError> {
+    Group::decode(&rpc.get_account_data(group)?)
+}
+
+pub fn load_basket(rpc : &RpcClient, basket : &Pubkey) -> Result<Basket, ClientError> {
+    Basket::decode(&rpc.get_account_data(basket)?)
+}
+
+pub fn load_token_desc(rpc : &RpcClient, token_desc : &Pubkey) -> Result<TokenDescription, ClientError> {
+    TokenDescription::decode(&rpc.get_account_data(token_desc)?)
+}
+
+// None once the context is closed by its finalize
+pub fn load_trade_context(rpc : &RpcClient, trade_context : &Pubkey) -> Result<Option<TradeContext>, ClientError> {
+    match rpc.get_account_with_commitment(trade_context, rpc.commitment())?.value {
+        Some(account) => Ok(Some(TradeContext::decode(&account.data)?)),
+        None => Ok(None),
+    }
+}
+
+pub fn load_baskets(rpc : &RpcClient, group : &Pubkey) -> Result<Vec<(Pubkey, Basket)>, ClientError> {
+    let group_filter = RpcFilterType::Memcmp(Memcmp::new_raw_bytes(BASKET_GROUP_OFFSET, group.to_bytes().to_vec()));
+    program_accounts::<states::Basket>(rpc, BASKET_DESC_MAGIC, Some(group_filter))?.iter()
+        .map(|(key, data)| Ok((*key, Basket::decode(data)?)))
+        .collect()
+}
+
+// token descriptions are not tied to a group, every token of the program is loaded
+pub fn load_token_descs(rpc : &RpcClient) -> Result<Vec<(Pubkey, TokenDescription)>, ClientError> {
+    program_accounts::<states::TokenDescription>(rpc, TOKEN_DESC_MAGIC, None)?.iter()
+        .map(|(key, data)| Ok((*key, TokenDescription::decode(data)?)))
+        .collect()
+}
+
+// open trade contexts of every basket, the oldest first
+pub fn load_trade_contexts(rpc : &RpcClient) -> Result<Vec<(Pubkey, TradeContext)>, ClientError> {
+    let mut trade_contexts = program_accounts::<states::BasketTradeContext>(rpc, BASKET_TRADE_CONTEXT_MAGIC, None)?.iter()
+        .map(|(key, data)| Ok((*key, TradeContext::decode(data)?)))
+        .collect::<Result<Vec<_>, ClientError>>()?;
+    trade_contexts.sort_by_key(|(_, trade_context)| trade_context.created_on);
+    Ok(trade_contexts)
+}
+
+pub fn load_market(rpc : &RpcClient, market : &Pubkey, dex_program : &Pubkey) -> Result<MarketAccounts, ClientError> {
+    MarketAccounts::decode(market, &rpc.get_account_data(market)?, dex_program)
+}
+
+// snapshot of the resting orders of a market, its bids and asks are loaded in a single request
+pub fn load_order_book(rpc : &RpcClient, market : &Pubkey, dex_program : &Pubkey) -> Result<OrderBook, ClientError> {
+    let market_data = rpc.get_account_data(market)?;
+    let market_accounts = MarketAccounts::decode(market, &market_data, dex_program)?;
+    let accounts = rpc.get_multiple_accounts(&[market_accounts.bids, market_accounts.asks])?;
+    match (&accounts[0], &accounts[1]) {
+        (Some(bids), Some(asks)) => OrderBook::decode(&market_data, &bids.data, &asks.data),
+        _ => Err(ClientError::InvalidAccount("serum orderbook")),
+    }
+}
+
+// accounts of type T whose magic matches, the discriminator filters out accounts of other types
+fn program_accounts<T : Discriminator>(rpc : &RpcClient, magic : u32, filter : Option<RpcFilterType>) -> Result<Vec<(Pubkey, Vec<u8>)>, ClientError> {
+    let mut filters = vec![
+        RpcFilterType::Memcmp(Memcmp::new_raw_bytes(0, T::discriminator().to_vec())),
+        RpcFilterType::Memcmp(Memcmp::new_raw_bytes(MAGIC_OFFSET, magic.to_le_bytes().to_vec())),
+    ];
+    filters.extend(filter);
+    let config = RpcProgramAccountsConfig {
+        filters : Some(filters),
+        account_config : RpcAccountInfoConfig {
+            encoding : Some(UiAccountEncoding::Base64),
+            ..RpcAccountInfoConfig::default()
+        },
+        ..RpcProgramAccountsConfig::default()
+    };
+    let accounts = rpc.get_program_accounts_with_config(&fruitbasket::ID, config)?;
+    Ok(accounts.into_iter().map(|(key, account)| (key, account.data)).collect())
+}
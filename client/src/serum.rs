@@ -0,0 +1,111 @@
+// Serum accounts needed to trade a leg on a market
+use anchor_lang::prelude::Pubkey;
+use anchor_spl::dex::serum_dex::critbit::{Slab, SlabView};
+use anchor_spl::dex::serum_dex::state::{gen_vault_signer_key, MarketState, OpenOrders, ACCOUNT_HEAD_PADDING, ACCOUNT_TAIL_PADDING};
+use std::mem::size_of;
+
+use crate::ClientError;
+
+pub struct MarketAccounts {
+    pub market : Pubkey,
+    pub request_queue : Pubkey,
+    pub event_queue : Pubkey,
+    pub bids : Pubkey,
+    pub asks : Pubkey,
+    pub coin_vault : Pubkey,
+    pub pc_vault : Pubkey,
+    pub vault_signer : Pubkey,
+}
+
+// resting orders of a market, prices are in quote lots per coin lot and quantities in coin lots
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct OrderBook {
+    pub coin_lot_size : u64,
+    pub pc_lot_size : u64,
+    // best price first
+    pub bids : Vec<BookLevel>,
+    pub asks : Vec<BookLevel>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BookLevel {
+    pub price : u64,
+    pub quantity : u64,
+}
+
+impl MarketAccounts {
+    pub fn decode(market : &Pubkey, data : &[u8], dex_program : &Pubkey) -> Result<Self, ClientError> {
+        let state = market_state(data)?;
+        Ok(MarketAccounts {
+            market : *market,
+            request_queue : words_to_pubkey(state.req_q),
+            event_queue : words_to_pubkey(state.event_q),
+            bids : words_to_pubkey(state.bids),
+            asks : words_to_pubkey(state.asks),
+            coin_vault : words_to_pubkey(state.coin_vault),
+            pc_vault : words_to_pubkey(state.pc_vault),
+            vault_signer : gen_vault_signer_key(state.vault_signer_nonce, market, dex_program)?,
+        })
+    }
+}
+
+impl OrderBook {
+    // snapshot of the market and of its bids and asks accounts
+    pub fn decode(market_data : &[u8], bids_data : &[u8], asks_data : &[u8]) -> Result<Self, ClientError> {
+        let state = market_state(market_data)?;
+        let mut bids = slab_orders(bids_data)?;
+        let mut asks = slab_orders(asks_data)?;
+        bids.sort_unstable_by(|a, b| b.price.cmp(&a.price));
+        asks.sort_unstable_by(|a, b| a.price.cmp(&b.price));
+        Ok(OrderBook {
+            coin_lot_size : state.coin_lot_size,
+            pc_lot_size : state.pc_lot_size,
+            bids,
+            asks,
+        })
+    }
+}
+
+// space of the open orders account given when adding a token or a token market, owned by the dex program
+pub fn open_orders_space() -> usize {
+    ACCOUNT_HEAD_PADDING.len() + size_of::<OpenOrders>() + ACCOUNT_TAIL_PADDING.len()
+}
+
+// the market is copied out of the data as the data may not be aligned
+fn market_state(data : &[u8]) -> Result<MarketState, ClientError> {
+    let start = ACCOUNT_HEAD_PADDING.len();
+    let size = size_of::<MarketState>();
+    if data.len() < start + size || data[..start] != ACCOUNT_HEAD_PADDING[..] {
+        return Err(ClientError::InvalidAccount("serum market"));
+    }
+    let mut state : MarketState = bytemuck::Zeroable::zeroed();
+    bytemuck::bytes_of_mut(&mut state).copy_from_slice(&data[start..start + size]);
+    Ok(state)
+}
+
+// orders of a bids or asks account, the slab follows the padding and the account flags
+fn slab_orders(data : &[u8]) -> Result<Vec<BookLevel>, ClientError> {
+    let start = ACCOUNT_HEAD_PADDING.len() + size_of::<u64>();
+    let end = data.len().saturating_sub(ACCOUNT_TAIL_PADDING.len());
+    if end <= start || data[..ACCOUNT_HEAD_PADDING.len()] != ACCOUNT_HEAD_PADDING[..] {
+        return Err(ClientError::InvalidAccount("serum orderbook"));
+    }
+    // the nodes of the slab are read in place so they are copied into aligned words
+    let mut words = vec![0u64; (end - start + 7) / 8];
+    let bytes = &mut bytemuck::cast_slice_mut::<u64, u8>(&mut words)[..end - start];
+    bytes.copy_from_slice(&data[start..end]);
+    let slab = Slab::new(bytes);
+    Ok((0..slab.capacity() as u32)
+        .filter_map(|handle| slab.get(handle).and_then(|node| node.as_leaf()))
+        .map(|leaf| BookLevel { price : leaf.price().get(), quantity : leaf.quantity() })
+        .collect())
+}
+
+// serum stores its keys as little endian words
+fn words_to_pubkey(words : [u64; 4]) -> Pubkey {
+    let mut bytes = [0u8; 32];
+    for (chunk, word) in bytes.chunks_mut(8).zip(words.iter()) {
+        chunk.copy_from_slice(&word.to_le_bytes());
+    }
+    Pubkey::new_from_array(bytes)
+}
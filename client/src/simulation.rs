@@ -0,0 +1,225 @@
+// Outcome of a market buy or sell context simulated against snapshots of the serum orderbooks.
+// Legs are processed in the order of the basket with the bounds of the program, orders are matched
+// like serum matches an immediate or cancel order of an open orders account without SRM, with the base fee tier.
+use anchor_lang::prelude::{ProgramError, Pubkey};
+use fruitbasket::states::ContextSide;
+
+use crate::serum::{BookLevel, OrderBook};
+use crate::state::{Basket, Group, TokenDescription};
+use crate::ClientError;
+
+pub struct TradeSimulation {
+    pub side : ContextSide,
+    // amount of basket tokens
+    pub amount : u64,
+    // legs up to the one making the context revert
+    pub legs : Vec<LegSimulation>,
+    pub quote_spent : u64,
+    pub quote_received : u64,
+    pub fee : u64,
+    pub creator_fee : u64,
+    pub revert : Option<RevertReason>,
+}
+
+pub struct LegSimulation {
+    pub token_mint : Pubkey,
+    // native tokens of the leg
+    pub amount : u64,
+    // native tokens traded, a whole number of coin lots
+    pub filled : u64,
+    // native tokens left under a coin lot, they are not traded
+    pub dust : u64,
+    // quote native units paid or received for the leg, serum taker fee included
+    pub quote_amount : u64,
+    pub taker_fee : u64,
+    // bound of the leg in quote lots per coin lot
+    pub limit_price : u64,
+    // orders taken, prices in quote lots per coin lot and quantities in coin lots
+    pub fills : Vec<BookLevel>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RevertReason {
+    // the token of the leg is paused
+    TokenPaused(usize),
+    // the leg cannot be filled within its band or with the quote left
+    LegOutOfBand(usize),
+    // quote left once the legs are processed does not cover the fees
+    InsufficientQuoteForFee,
+}
+
+impl TradeSimulation {
+    pub fn would_revert(&self) -> bool {
+        self.revert.is_some()
+    }
+
+    // quote paid for a buy or received for a sell once the fees are taken, the bound to give to the context
+    pub fn quote_amount(&self) -> u64 {
+        let fees = self.fee + self.creator_fee;
+        if self.side == ContextSide::Buy {
+            self.quote_spent + fees
+        } else {
+            self.quote_received.saturating_sub(fees)
+        }
+    }
+}
+
+// books are given in the order of the basket components, quote_deposit is the quote the legs of a buy can spend,
+// the deposit of the context without its fee reserve
+#[allow(clippy::too_many_arguments)]
+pub fn simulate_trade(
+    group : &Group,
+    basket : &Basket,
+    token_descs : &[TokenDescription],
+    books : &[OrderBook],
+    quote_mint : &Pubkey,
+    side : ContextSide,
+    amount : u64,
+    quote_deposit : u64,
+) -> Result<TradeSimulation, ClientError> {
+    if side != ContextSide::Buy && side != ContextSide::Sell {
+        return Err(ClientError::Program(ProgramError::InvalidArgument));
+    }
+    let is_buy_side = side == ContextSide::Buy;
+    let quote = group.quote_currency(quote_mint)?;
+    let mut simulation = TradeSimulation {
+        side,
+        amount,
+        legs : Vec::new(),
+        quote_spent : 0,
+        quote_received : 0,
+        fee : 0,
+        creator_fee : 0,
+        revert : None,
+    };
+    let mut quote_left = if is_buy_side { quote_deposit } else { 0 };
+
+    for (position, component) in basket.components.iter().enumerate() {
+        let token_desc = token_descs.iter()
+            .find(|x| x.token_mint == component.token_mint)
+            .ok_or(ClientError::UnknownToken(component.token_mint))?;
+        let book = books.get(position).ok_or(ClientError::UnknownLeg(position))?;
+        if token_desc.paused {
+            simulation.revert = Some(RevertReason::TokenPaused(position));
+            return Ok(simulation);
+        }
+
+        let token_amount = (amount as u128 * component.amount as u128 / 10u128.pow(component.decimal.into())) as u64;
+        let token_amount = native_token_amount(token_amount, token_desc.decimal);
+        let leg_price = quote.in_quote_native(token_desc.cache.price_with_slippage(is_buy_side, group.leg_slippage_bps))?;
+        let limit_price = leg_price_in_lots(leg_price, token_desc.decimal, book.coin_lot_size, book.pc_lot_size, is_buy_side).max(1);
+        let max_coin_qty = token_amount / book.coin_lot_size;
+        let max_pc_qty = if is_buy_side {
+            remove_taker_fee(quote_left) / book.pc_lot_size
+        } else {
+            u64::MAX
+        };
+
+        let fills = if is_buy_side {
+            take_orders(&book.asks, |price| price <= limit_price, max_coin_qty, max_pc_qty)
+        } else {
+            take_orders(&book.bids, |price| price >= limit_price, max_coin_qty, max_pc_qty)
+        };
+        let filled = fills.iter().map(|x| x.quantity).sum::<u64>() * book.coin_lot_size;
+        let pc_amount = fills.iter().map(|x| x.price * x.quantity).sum::<u64>() * book.pc_lot_size;
+        let taker_fee = taker_fee(pc_amount);
+        let quote_amount = if is_buy_side { pc_amount + taker_fee } else { pc_amount - taker_fee };
+        let remaining = token_amount - filled;
+        simulation.legs.push(LegSimulation {
+            token_mint : component.token_mint,
+            amount : token_amount,
+            filled,
+            dust : remaining.min(book.coin_lot_size - 1),
+            quote_amount,
+            taker_fee,
+            limit_price,
+            fills,
+        });
+        // the program cannot send an order without quantity and fails a leg left with a lot or more
+        if max_coin_qty == 0 || remaining >= book.coin_lot_size {
+            simulation.revert = Some(RevertReason::LegOutOfBand(position));
+            return Ok(simulation);
+        }
+        if is_buy_side {
+            quote_left = quote_left.saturating_sub(quote_amount);
+            simulation.quote_spent += quote_amount;
+        } else {
+            quote_left += quote_amount;
+            simulation.quote_received += quote_amount;
+        }
+    }
+
+    // fees of finalize_context, on the quote spent for a buy and on the quote received for a sell
+    let (fee_base, fee_bps) = if is_buy_side {
+        (simulation.quote_spent, group.mint_fee_bps)
+    } else {
+        (simulation.quote_received, group.redeem_fee_bps)
+    };
+    simulation.fee = bps_of(fee_base, fee_bps);
+    simulation.creator_fee = bps_of(fee_base, basket.creator_fee_bps);
+    // a buy deposits its fees on top of the quote its legs can spend
+    let fee_reserve = if is_buy_side { bps_of(quote_deposit, group.mint_fee_bps + basket.creator_fee_bps) } else { 0 };
+    if simulation.fee + simulation.creator_fee > quote_left.saturating_add(fee_reserve) {
+        simulation.revert = Some(RevertReason::InsufficientQuoteForFee);
+    }
+    Ok(simulation)
+}
+
+// orders crossed best first, serum stops at the first order it cannot take a lot of
+fn take_orders(orders : &[BookLevel], crosses : impl Fn(u64) -> bool, max_coin_qty : u64, max_pc_qty : u64) -> Vec<BookLevel> {
+    let mut coin_qty_remaining = max_coin_qty;
+    let mut pc_qty_remaining = max_pc_qty;
+    let mut fills = Vec::new();
+    for order in orders.iter() {
+        if !crosses(order.price) {
+            break;
+        }
+        let quantity = order.quantity.min(coin_qty_remaining).min(pc_qty_remaining / order.price);
+        if quantity == 0 {
+            break;
+        }
+        coin_qty_remaining -= quantity;
+        pc_qty_remaining -= quantity * order.price;
+        fills.push(BookLevel { price : order.price, quantity });
+    }
+    fills
+}
+
+// taker rate of the base fee tier of the deployed serum dex, 4 bps as a 64.64 fixed point number
+// the serum_dex crate still has the former rate of 22 bps
+const TAKER_RATE : u128 = (4 << 64) / 10000;
+
+// taker fee rounded up like serum does
+fn taker_fee(pc_qty : u64) -> u64 {
+    let exact_fee = TAKER_RATE * pc_qty as u128;
+    (exact_fee >> 64) as u64 + (exact_fee as u64 != 0) as u64
+}
+
+// quote an order can trade once the taker fee is put aside
+fn remove_taker_fee(pc_qty_incl_fee : u64) -> u64 {
+    (((pc_qty_incl_fee as u128) << 64) / ((1 << 64) + TAKER_RATE)) as u64
+}
+
+fn bps_of(amount : u64, bps : u64) -> u64 {
+    (amount as u128 * bps as u128 / 10000) as u64
+}
+
+// same conversions as the program
+fn native_token_amount(amount : u64, token_decimal : u8) -> u64 {
+    if token_decimal != 6 {
+        amount * 10u64.pow(token_decimal.into()) / 10u64.pow(6)
+    } else {
+        amount
+    }
+}
+
+fn leg_price_in_lots(leg_price : u64, token_decimal : u8, coin_lot_size : u64, pc_lot_size : u64, is_buy_side : bool) -> u64 {
+    let numerator = leg_price as u128 * coin_lot_size as u128;
+    let denominator = 10u128.pow(token_decimal.into()) * pc_lot_size as u128;
+    let price = if is_buy_side {
+        numerator / denominator
+    } else {
+        numerator.div_ceil(denominator)
+    };
+    price as u64
+}
@@ -0,0 +1,445 @@
+// Program accounts decoded into plain rust types
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::{AccountDeserialize, Discriminator};
+use fruitbasket::states::{self, BasketComponentDescription, Cache, ContextOrderType, ContextSide,
+                          BASKET_DESC_MAGIC, BASKET_TRADE_CONTEXT_MAGIC, TOKEN_DESC_MAGIC};
+use std::mem::size_of;
+
+use crate::ClientError;
+
+// offset of the magic of basket, token description and trade context accounts, after the anchor discriminator
+pub const MAGIC_OFFSET : usize = 8;
+// offset of the group of a basket : discriminator, magic, name, description, number of components and creator
+pub const BASKET_GROUP_OFFSET : usize = 8 + 4 + 128 + 256 + 1 + 32;
+
+pub struct Group {
+    pub owner : Pubkey,
+    // roles are None when the owner holds them
+    pub pending_owner : Option<Pubkey>,
+    pub token_lister : Option<Pubkey>,
+    pub basket_curator : Option<Pubkey>,
+    pub pauser : Option<Pubkey>,
+    pub fee_manager : Option<Pubkey>,
+    pub pause_flags : u8,
+    pub audit_auto_pause : bool,
+    pub token_count : u64,
+    pub base_mint : Pubkey,
+    pub base_mint_name : String,
+    pub number_of_baskets : u64,
+    pub quote_token_transaction_pool : Pubkey,
+    pub leg_slippage_bps : u64,
+    pub mint_fee_bps : u64,
+    pub redeem_fee_bps : u64,
+    pub max_creator_fee_bps : u64,
+    pub max_price_age_slots : u64,
+    pub ema_window_slots : u64,
+    pub ema_bound : bool,
+    // first quote currency is the base mint
+    pub quote_currencies : Vec<QuoteCurrency>,
+}
+
+pub struct QuoteCurrency {
+    pub mint : Pubkey,
+    pub transaction_pool : Pubkey,
+    // None for the base mint
+    pub price_oracle : Option<Pubkey>,
+    // price of a whole quote token in base mint native units
+    pub price : u64,
+    pub decimal : u8,
+}
+
+pub struct Basket {
+    pub name : String,
+    pub description : String,
+    pub number_of_components : u8,
+    pub creator : Pubkey,
+    pub group : Pubkey,
+    pub creator_fee_bps : u64,
+    pub components : Vec<BasketComponent>,
+    pub basket_mint : Pubkey,
+    // price of a whole basket in quote native units
+    pub price : u64,
+    pub confidence : u64,
+    pub decimal : u8,
+    pub price_slot : u64,
+    pub book_price : BookPrice,
+    pub ema_price : u64,
+    pub ema_slot : u64,
+    pub pause_flags : u8,
+    // components swapped in once the rebalance context is finalized
+    pub pending_components : Vec<BasketComponentDescription>,
+    pub rebalance_context : Option<Pubkey>,
+    pub solvency_bps : u64,
+    // None if the basket was never audited
+    pub audit_timestamp : Option<i64>,
+    // components audited for the next solvency, 0 if no audit is pending
+    pub audit_cursor : u8,
+    // buy, sell and swap contexts not finalized yet, the basket cannot be rebalanced until they are
+    pub open_contexts : u64,
+}
+
+pub struct BasketComponent {
+    pub token_mint : Pubkey,
+    // amount of tokens for one whole basket
+    pub amount : u64,
+    pub decimal : u8,
+    // last cached price of a whole token, 0 if the component was never priced
+    pub price : u64,
+    pub confidence : u64,
+    pub publish_slot : u64,
+}
+
+pub struct BookPrice {
+    pub buy_price : u64,
+    pub sell_price : u64,
+    pub amount : u64,
+    pub slot : u64,
+    // components walked for the next book prices, 0 if no walk is pending
+    pub cursor : u8,
+}
+
+pub struct TokenDescription {
+    pub id : u64,
+    pub group : Pubkey,
+    pub token_mint : Pubkey,
+    pub price_oracle : Pubkey,
+    pub product_oracle : Pubkey,
+    pub name : String,
+    pub token_pool : Pubkey,
+    pub decimal : u8,
+    pub open_orders : Pubkey,
+    // market quoted in the base mint of the group
+    pub market : Pubkey,
+    pub cache : Cache,
+    pub max_confidence_bps : u64,
+    pub max_deviation_bps : u64,
+    pub quote_markets : Vec<QuoteMarket>,
+    pub paused : bool,
+}
+
+pub struct QuoteMarket {
+    pub quote_mint : Pubkey,
+    pub market : Pubkey,
+    pub open_orders : Pubkey,
+}
+
+pub struct TradeContext {
+    pub side : ContextSide,
+    pub order_type : ContextOrderType,
+    pub basket : Pubkey,
+    pub reverting : bool,
+    // amount of basket tokens
+    pub amount : u64,
+    pub quote_amount_left : u64,
+    pub quote_token_account : Pubkey,
+    pub basket_token_account : Pubkey,
+    pub user : Pubkey,
+    pub quote_token_mint : Pubkey,
+    pub quote_deposited : u64,
+    // part of the quote deposited kept for the fees of a buy
+    pub fee_reserve : u64,
+    pub created_on : u64,
+    pub limit_price : u64,
+    pub legs : Vec<Leg>,
+    pub quote_spent : u64,
+    pub quote_received : u64,
+    // fees taken when the context is finalized, set when it was created
+    pub mint_fee_bps : u64,
+    pub redeem_fee_bps : u64,
+    pub creator_fee_bps : u64,
+    // None for buy, sell and rebalance contexts
+    pub destination : Option<SwapDestination>,
+}
+
+pub struct Leg {
+    pub token_mint : Pubkey,
+    pub side : ContextSide,
+    // token amounts are in 6 decimals units whatever the decimals of the token
+    pub amount_left : u64,
+    pub initial_amount : u64,
+    pub treated : bool,
+    pub limit_price : u64,
+    pub order_quantity : u64,
+    pub filled_quantity : u64,
+    // moved between the basket vaults when a swap is finalized
+    pub transfer_amount : u64,
+}
+
+pub struct SwapDestination {
+    pub basket : Pubkey,
+    pub basket_token_account : Pubkey,
+    pub amount : u64,
+    pub creator_fee_bps : u64,
+}
+
+impl Group {
+    // the group is a zero copy account, it is copied out of the data as the data may not be aligned
+    pub fn decode(data : &[u8]) -> Result<Self, ClientError> {
+        let size = size_of::<states::FruitBasketGroup>();
+        if data.len() < 8 + size || data[..8] != states::FruitBasketGroup::discriminator() {
+            return Err(ClientError::InvalidAccount("fruitbasket group"));
+        }
+        let mut group : states::FruitBasketGroup = bytemuck::Zeroable::zeroed();
+        bytemuck::bytes_of_mut(&mut group).copy_from_slice(&data[8..8 + size]);
+        Ok(Group::from(&group))
+    }
+
+    pub fn quote_currency(&self, mint : &Pubkey) -> Result<&QuoteCurrency, ClientError> {
+        self.quote_currencies.iter()
+            .find(|x| x.mint == *mint)
+            .ok_or(ClientError::UnknownQuoteCurrency(*mint))
+    }
+}
+
+impl QuoteCurrency {
+    // amount in base mint native units converted into native units of the quote currency
+    pub fn in_quote_native(&self, amount : u64) -> Result<u64, ClientError> {
+        if self.price == 0 {
+            return Err(ClientError::QuoteCurrencyNotPriced(self.mint));
+        }
+        Ok((amount as u128 * 10u128.pow(self.decimal.into()) / self.price as u128) as u64)
+    }
+}
+
+impl From<&states::FruitBasketGroup> for Group {
+    fn from(group : &states::FruitBasketGroup) -> Self {
+        let quote_currencies = group.quote_currencies;
+        Group {
+            owner : group.owner,
+            pending_owner : optional_key(group.pending_owner),
+            token_lister : optional_key(group.token_lister),
+            basket_curator : optional_key(group.basket_curator),
+            pauser : optional_key(group.pauser),
+            fee_manager : optional_key(group.fee_manager),
+            pause_flags : group.pause_flags,
+            audit_auto_pause : group.audit_auto_pause == 1,
+            token_count : group.token_count,
+            base_mint : group.base_mint,
+            base_mint_name : name_to_string(&group.base_mint_name),
+            number_of_baskets : group.number_of_baskets,
+            quote_token_transaction_pool : group.quote_token_transaction_pool,
+            leg_slippage_bps : group.leg_slippage_bps,
+            mint_fee_bps : group.mint_fee_bps,
+            redeem_fee_bps : group.redeem_fee_bps,
+            max_creator_fee_bps : group.max_creator_fee_bps,
+            max_price_age_slots : group.max_price_age_slots,
+            ema_window_slots : group.ema_window_slots,
+            ema_bound : group.ema_bound == 1,
+            quote_currencies : quote_currencies[..group.nb_quote_currencies as usize].iter()
+                .map(|x| QuoteCurrency {
+                    mint : x.mint,
+                    transaction_pool : x.transaction_pool,
+                    price_oracle : optional_key(x.price_oracle),
+                    price : x.price,
+                    decimal : x.decimal,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Basket {
+    pub fn decode(data : &[u8]) -> Result<Self, ClientError> {
+        let basket = decode_account::<states::Basket>(data, "basket")?;
+        if basket.magic != BASKET_DESC_MAGIC {
+            return Err(ClientError::InvalidAccount("basket"));
+        }
+        Ok(Basket::from(&basket))
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.components.len() == self.number_of_components as usize
+    }
+
+    pub fn is_rebalancing(&self) -> bool {
+        self.rebalance_context.is_some()
+    }
+}
+
+impl From<&states::Basket> for Basket {
+    fn from(basket : &states::Basket) -> Self {
+        Basket {
+            name : name_to_string(&basket.basket_name),
+            description : name_to_string(&basket.desc),
+            number_of_components : basket.number_of_components,
+            creator : basket.creator,
+            group : basket.group,
+            creator_fee_bps : basket.creator_fee_bps,
+            components : basket.components.iter().zip(basket.component_prices.iter())
+                .map(|(component, price)| BasketComponent {
+                    token_mint : component.token_mint,
+                    amount : component.amount,
+                    decimal : component.decimal,
+                    price : price.price,
+                    confidence : price.confidence,
+                    publish_slot : price.publish_slot,
+                })
+                .collect(),
+            basket_mint : basket.basket_mint,
+            price : basket.last_price,
+            confidence : basket.confidence,
+            decimal : basket.decimal,
+            price_slot : basket.price_slot,
+            book_price : BookPrice {
+                buy_price : basket.book_buy_price,
+                sell_price : basket.book_sell_price,
+                amount : basket.book_price_amount,
+                slot : basket.book_price_slot,
+                cursor : basket.book_cursor,
+            },
+            ema_price : basket.ema_price,
+            ema_slot : basket.ema_slot,
+            pause_flags : basket.pause_flags,
+            pending_components : basket.pending_components.clone(),
+            rebalance_context : optional_key(basket.rebalance_context),
+            solvency_bps : basket.solvency_bps,
+            audit_timestamp : if basket.audit_timestamp == 0 { None } else { Some(basket.audit_timestamp) },
+            audit_cursor : basket.audit_cursor,
+            open_contexts : basket.open_contexts,
+        }
+    }
+}
+
+impl TokenDescription {
+    pub fn decode(data : &[u8]) -> Result<Self, ClientError> {
+        let token_desc = decode_account::<states::TokenDescription>(data, "token description")?;
+        if token_desc.magic != TOKEN_DESC_MAGIC {
+            return Err(ClientError::InvalidAccount("token description"));
+        }
+        Ok(TokenDescription::from(&token_desc))
+    }
+
+    // market and open orders used to trade the token against a quote currency
+    pub fn market_for(&self, quote_mint : &Pubkey, base_mint : &Pubkey) -> Result<(Pubkey, Pubkey), ClientError> {
+        if quote_mint == base_mint {
+            return Ok((self.market, self.open_orders));
+        }
+        self.quote_markets.iter()
+            .find(|x| x.quote_mint == *quote_mint)
+            .map(|x| (x.market, x.open_orders))
+            .ok_or(ClientError::UnknownMarket(self.token_mint))
+    }
+}
+
+impl From<&states::TokenDescription> for TokenDescription {
+    fn from(token_desc : &states::TokenDescription) -> Self {
+        TokenDescription {
+            id : token_desc.id,
+            group : token_desc.group,
+            token_mint : token_desc.token_mint,
+            price_oracle : token_desc.price_oracle,
+            product_oracle : token_desc.product_oracle,
+            name : name_to_string(&token_desc.token_name),
+            token_pool : token_desc.token_pool,
+            decimal : token_desc.token_decimal,
+            open_orders : token_desc.token_open_orders,
+            market : token_desc.market,
+            cache : token_desc.cache,
+            max_confidence_bps : token_desc.max_confidence_bps,
+            max_deviation_bps : token_desc.max_deviation_bps,
+            quote_markets : token_desc.quote_markets.iter()
+                .filter(|x| x.quote_mint != Pubkey::default())
+                .map(|x| QuoteMarket {
+                    quote_mint : x.quote_mint,
+                    market : x.market,
+                    open_orders : x.open_orders,
+                })
+                .collect(),
+            paused : token_desc.paused == 1,
+        }
+    }
+}
+
+impl TradeContext {
+    pub fn decode(data : &[u8]) -> Result<Self, ClientError> {
+        let trade_context = decode_account::<states::BasketTradeContext>(data, "trade context")?;
+        if trade_context.magic != BASKET_TRADE_CONTEXT_MAGIC {
+            return Err(ClientError::InvalidAccount("trade context"));
+        }
+        Ok(TradeContext::from(&trade_context))
+    }
+
+    // the context can be finalized once all its legs are treated
+    pub fn is_treated(&self) -> bool {
+        self.legs.iter().all(|x| x.treated)
+    }
+
+    pub fn pending_legs(&self) -> Vec<usize> {
+        (0..self.legs.len()).filter(|x| !self.legs[*x].treated).collect()
+    }
+
+    // basket whose vault holds the tokens of a leg, bought tokens of a swap back the destination basket
+    pub fn leg_basket(&self, leg : usize) -> Result<Pubkey, ClientError> {
+        let side = self.legs.get(leg).ok_or(ClientError::UnknownLeg(leg))?.side;
+        match &self.destination {
+            Some(destination) if side == ContextSide::Buy => Ok(destination.basket),
+            _ => Ok(self.basket),
+        }
+    }
+}
+
+impl From<&states::BasketTradeContext> for TradeContext {
+    fn from(trade_context : &states::BasketTradeContext) -> Self {
+        let legs = (0..trade_context.token_mints.len())
+            .map(|x| Leg {
+                token_mint : trade_context.token_mints[x],
+                side : trade_context.leg_sides[x],
+                amount_left : trade_context.token_amounts[x],
+                initial_amount : trade_context.initial_token_amounts[x],
+                treated : trade_context.tokens_treated[x] == 1,
+                limit_price : trade_context.leg_limit_prices[x],
+                order_quantity : trade_context.leg_order_quantities[x],
+                filled_quantity : trade_context.leg_filled_quantities[x],
+                transfer_amount : trade_context.leg_transfer_amounts[x],
+            })
+            .collect();
+        let destination = if trade_context.side == ContextSide::Swap {
+            Some(SwapDestination {
+                basket : trade_context.destination_basket,
+                basket_token_account : trade_context.destination_basket_token_account,
+                amount : trade_context.destination_amount,
+                creator_fee_bps : trade_context.destination_creator_fee_bps,
+            })
+        } else {
+            None
+        };
+        TradeContext {
+            side : trade_context.side,
+            order_type : trade_context.order_type,
+            basket : trade_context.basket,
+            reverting : trade_context.reverting == 1,
+            amount : trade_context.amount,
+            quote_amount_left : trade_context.usdc_amount_left,
+            quote_token_account : trade_context.quote_token_account,
+            basket_token_account : trade_context.basket_token_account,
+            user : trade_context.user,
+            quote_token_mint : trade_context.quote_token_mint,
+            quote_deposited : trade_context.initial_usdc_transfer_amount,
+            fee_reserve : trade_context.fee_reserve,
+            created_on : trade_context.created_on,
+            limit_price : trade_context.limit_price,
+            legs,
+            quote_spent : trade_context.quote_spent,
+            quote_received : trade_context.quote_received,
+            mint_fee_bps : trade_context.mint_fee_bps,
+            redeem_fee_bps : trade_context.redeem_fee_bps,
+            creator_fee_bps : trade_context.creator_fee_bps,
+            destination,
+        }
+    }
+}
+
+fn decode_account<T : AccountDeserialize>(data : &[u8], account : &'static str) -> Result<T, ClientError> {
+    let mut data = data;
+    T::try_deserialize(&mut data).map_err(|_| ClientError::InvalidAccount(account))
+}
+
+fn optional_key(key : Pubkey) -> Option<Pubkey> {
+    if key == Pubkey::default() { None } else { Some(key) }
+}
+
+// names are stored as fixed size arrays padded with zeros
+fn name_to_string(name : &[u8]) -> String {
+    let end = name.iter().position(|x| *x == 0).unwrap_or(name.len());
+    String::from_utf8_lossy(&name[..end]).into_owned()
+}
@@ -0,0 +1,81 @@
+// Drives the trade contexts from their creation to their finalize
+use fruitbasket_client::state::TradeContext;
+use fruitbasket_client::{instructions, pda, ContextOrderType, ContextSide};
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signer;
+
+use crate::keeper::{GroupState, Keeper};
+use crate::Result;
+
+impl Keeper {
+    pub fn drive_context(&self, state : &GroupState, context_key : &Pubkey, trade_context : &TradeContext) -> Result<()> {
+        if trade_context.is_treated() {
+            return self.finalize(state, context_key, trade_context);
+        }
+        if self.is_expired(state, trade_context) {
+            println!("reverting context {}", context_key);
+            self.send(&[instructions::revert_trade_context(&state.group, context_key, trade_context)?])?;
+            // legs are bought or sold back on the next crank
+            return Ok(());
+        }
+
+        // sell legs pay for the buy legs of swaps and rebalances, so they are processed first
+        let mut pending_legs = trade_context.pending_legs();
+        pending_legs.sort_by_key(|x| trade_context.legs[*x].side != ContextSide::Sell);
+        for leg in pending_legs {
+            let instructions = self.process_leg_instructions(state, context_key, trade_context, leg)?;
+            if let Err(err) = self.send(&instructions) {
+                eprintln!("leg {} of context {} not processed : {}", trade_context.legs[leg].token_mint, context_key, err);
+            }
+        }
+
+        match self.load_trade_context(context_key)? {
+            Some(trade_context) if trade_context.is_treated() => self.finalize(state, context_key, &trade_context),
+            _ => Ok(()),
+        }
+    }
+
+    // limit contexts are cancelled by their user and rebalance contexts by their curator
+    fn is_expired(&self, state : &GroupState, trade_context : &TradeContext) -> bool {
+        let age = (state.clock.unix_timestamp as u64).saturating_sub(trade_context.created_on);
+        !trade_context.reverting
+            && trade_context.order_type == ContextOrderType::Market
+            && trade_context.side != ContextSide::Rebalance
+            && age > self.revert_timeout_secs
+    }
+
+    // the open orders of the basket backed by the leg are created on its first leg on the market
+    fn process_leg_instructions(&self, state : &GroupState, context_key : &Pubkey, trade_context : &TradeContext, leg : usize) -> Result<Vec<Instruction>> {
+        let token_mint = trade_context.legs[leg].token_mint;
+        let token_desc = state.token_descs.get(&token_mint).ok_or("unknown token description")?;
+        let (market, _) = token_desc.market_for(&trade_context.quote_token_mint, &state.group.base_mint)?;
+        let leg_basket = trade_context.leg_basket(leg)?;
+        let mut leg_instructions = Vec::new();
+        let open_orders = pda::basket_open_orders(&leg_basket, &market).0;
+        if self.rpc.get_account_with_commitment(&open_orders, self.rpc.commitment())?.value.is_none() {
+            leg_instructions.push(instructions::create_basket_open_orders(&self.payer.pubkey(), &leg_basket, &market, &self.dex_program));
+        }
+        let market = self.load_market(&market)?;
+        leg_instructions.push(instructions::process_token_for_context(&self.group_key, &state.group, context_key, trade_context, leg,
+                                                                      token_desc, &market, &self.dex_program)?);
+        Ok(leg_instructions)
+    }
+
+    fn finalize(&self, state : &GroupState, context_key : &Pubkey, trade_context : &TradeContext) -> Result<()> {
+        let basket = state.baskets.get(&trade_context.basket).ok_or("unknown basket")?;
+        let instruction = match &trade_context.destination {
+            Some(destination) => {
+                let destination_basket = state.baskets.get(&destination.basket).ok_or("unknown destination basket")?;
+                instructions::finalize_swap_context(&self.group_key, &state.group, context_key, trade_context, basket, destination_basket)?
+            },
+            None if trade_context.side == ContextSide::Rebalance => {
+                instructions::finalize_rebalance_context(&self.group_key, &state.group, context_key, trade_context)
+            },
+            None => instructions::finalize_context(&self.group_key, &state.group, context_key, trade_context, basket)?,
+        };
+        self.send(&[instruction])?;
+        println!("finalized context {}", context_key);
+        Ok(())
+    }
+}
@@ -0,0 +1,88 @@
+// Keeper state and rpc helpers
+use fruitbasket_client::rpc;
+use fruitbasket_client::serum::MarketAccounts;
+use fruitbasket_client::state::{Basket, Group, TokenDescription, TradeContext};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::account::from_account;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signature, Signer};
+use solana_sdk::sysvar::{self, clock::Clock};
+use solana_sdk::transaction::Transaction;
+use std::collections::HashMap;
+
+use crate::Result;
+
+pub struct Keeper {
+    pub rpc : RpcClient,
+    pub payer : Keypair,
+    pub group_key : Pubkey,
+    pub dex_program : Pubkey,
+    pub revert_timeout_secs : u64,
+}
+
+// accounts of the group loaded at the beginning of each crank
+pub struct GroupState {
+    pub group : Group,
+    pub baskets : HashMap<Pubkey, Basket>,
+    // token descriptions by token mint
+    pub token_descs : HashMap<Pubkey, TokenDescription>,
+    pub clock : Clock,
+}
+
+impl Keeper {
+    pub fn new(url : &str, payer : Keypair, group_key : Pubkey, dex_program : Pubkey, revert_timeout_secs : u64) -> Self {
+        Keeper {
+            rpc : RpcClient::new_with_commitment(url.to_string(), CommitmentConfig::confirmed()),
+            payer,
+            group_key,
+            dex_program,
+            revert_timeout_secs,
+        }
+    }
+
+    pub fn crank(&self) -> Result<()> {
+        let state = self.load_group_state()?;
+        self.update_prices(&state)?;
+        for (context_key, trade_context) in self.load_trade_contexts(&state)? {
+            // a failing context must not block the others
+            if let Err(err) = self.drive_context(&state, &context_key, &trade_context) {
+                eprintln!("context {} : {}", context_key, err);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn load_group_state(&self) -> Result<GroupState> {
+        let group = rpc::load_group(&self.rpc, &self.group_key)?;
+        let baskets = rpc::load_baskets(&self.rpc, &self.group_key)?.into_iter().collect();
+        let token_descs = rpc::load_token_descs(&self.rpc)?.into_iter()
+                            .map(|(_, token_desc)| (token_desc.token_mint, token_desc))
+                            .collect();
+        let clock_account = self.rpc.get_account(&sysvar::clock::id())?;
+        let clock = from_account::<Clock, _>(&clock_account).ok_or("cannot decode the clock sysvar")?;
+        Ok(GroupState { group, baskets, token_descs, clock })
+    }
+
+    // open trade contexts of the baskets of the group, the oldest first
+    pub fn load_trade_contexts(&self, state : &GroupState) -> Result<Vec<(Pubkey, TradeContext)>> {
+        Ok(rpc::load_trade_contexts(&self.rpc)?.into_iter()
+            .filter(|(_, trade_context)| state.baskets.contains_key(&trade_context.basket))
+            .collect())
+    }
+
+    pub fn load_trade_context(&self, context_key : &Pubkey) -> Result<Option<TradeContext>> {
+        Ok(rpc::load_trade_context(&self.rpc, context_key)?)
+    }
+
+    pub fn load_market(&self, market : &Pubkey) -> Result<MarketAccounts> {
+        Ok(rpc::load_market(&self.rpc, market, &self.dex_program)?)
+    }
+
+    pub fn send(&self, instructions : &[Instruction]) -> Result<Signature> {
+        let blockhash = self.rpc.get_latest_blockhash()?;
+        let transaction = Transaction::new_signed_with_payer(instructions, Some(&self.payer.pubkey()), &[&self.payer], blockhash);
+        Ok(self.rpc.send_and_confirm_transaction(&transaction)?)
+    }
+}
@@ -0,0 +1,62 @@
+//! Off-chain keeper of the fruitbasket program.
+//! It keeps the token and basket prices of a group fresh, processes every leg of the open trade contexts,
+//! finalizes them once all their legs are treated and reverts market contexts which stay open for too long.
+use clap::Parser;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::read_keypair_file;
+use std::{thread, time::Duration};
+
+mod contexts;
+mod keeper;
+mod prices;
+
+use keeper::Keeper;
+
+pub type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+#[derive(Parser)]
+#[clap(name = "fruitbasket-keeper", about = "Drives the trade contexts of a fruitbasket group")]
+struct Opts {
+    /// RPC url of the cluster, a local test validator by default
+    #[clap(long, default_value = "http://127.0.0.1:8899")]
+    url : String,
+    /// keypair paying for the keeper transactions, the solana cli keypair by default
+    #[clap(long)]
+    keypair : Option<String>,
+    /// group whose baskets are kept
+    #[clap(long)]
+    group : Pubkey,
+    /// serum dex program of the markets of the group
+    #[clap(long, default_value_t = anchor_spl::dex::ID)]
+    dex_program : Pubkey,
+    /// market contexts older than this number of seconds are reverted
+    #[clap(long, default_value = "60")]
+    revert_timeout_secs : u64,
+    /// delay between two cranks in milliseconds
+    #[clap(long, default_value = "1000")]
+    interval_ms : u64,
+    /// crank once and exit
+    #[clap(long)]
+    once : bool,
+}
+
+fn main() -> Result<()> {
+    let opts = Opts::parse();
+    let keypair_path = match opts.keypair {
+        Some(path) => path,
+        None => format!("{}/.config/solana/id.json", std::env::var("HOME")?),
+    };
+    let payer = read_keypair_file(&keypair_path)?;
+    let keeper = Keeper::new(&opts.url, payer, opts.group, opts.dex_program, opts.revert_timeout_secs);
+
+    loop {
+        // a failed crank is retried on the next interval, the keeper never stops on chain errors
+        if let Err(err) = keeper.crank() {
+            eprintln!("crank failed : {}", err);
+        }
+        if opts.once {
+            return Ok(());
+        }
+        thread::sleep(Duration::from_millis(opts.interval_ms));
+    }
+}
@@ -0,0 +1,39 @@
+// Keeps the token caches and the basket prices of the group fresh
+use fruitbasket_client::instructions;
+use std::collections::HashSet;
+
+use crate::keeper::{GroupState, Keeper};
+use crate::Result;
+
+impl Keeper {
+    // prices are refreshed once they are half as old as the group allows
+    pub fn update_prices(&self, state : &GroupState) -> Result<()> {
+        let refresh_age = state.group.max_price_age_slots / 2;
+        let current_slot = state.clock.slot;
+        let components = state.baskets.values()
+                            .flat_map(|basket| basket.components.iter().map(|x| x.token_mint))
+                            .collect::<HashSet<_>>();
+        for token_mint in components.iter() {
+            let token_desc = match state.token_descs.get(token_mint) {
+                Some(token_desc) => token_desc,
+                None => continue,
+            };
+            if current_slot.saturating_sub(token_desc.cache.last_update_slot) < refresh_age {
+                continue;
+            }
+            if let Err(err) = self.send(&[instructions::update_price(&token_desc.group, token_desc)]) {
+                eprintln!("price of {} not updated : {}", token_mint, err);
+            }
+        }
+
+        for (basket_key, basket) in state.baskets.iter() {
+            if !basket.is_complete() || current_slot.saturating_sub(basket.price_slot) < refresh_age {
+                continue;
+            }
+            if let Err(err) = self.send(&[instructions::update_basket_price(basket_key, basket)]) {
+                eprintln!("price of basket {} not updated : {}", basket_key, err);
+            }
+        }
+        Ok(())
+    }
+}
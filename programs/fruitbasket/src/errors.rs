@@ -8,7 +8,7 @@ pub enum FruitBasketError {
     TokenCountLimitReached,
     #[msg("Unknown auhority")]
     UnknownAuthority,
-    #[msg("Current maximum component count is 10")]
+    #[msg("Basket components do not fit in the basket account")]
     ComponentCountOverflow,
     #[msg("There should be atleast 2 basket components")]
     ComponentCountUnderflow,
@@ -35,5 +35,89 @@ pub enum FruitBasketError {
     #[msg("Unknown market")]
     UnknownMarket,
     #[msg("Unknown Open Orders")]
-    UnknownOpenOrders
+    UnknownOpenOrders,
+    #[msg("Limit price should be greater than 0")]
+    InvalidLimitPrice,
+    #[msg("Limit order would cross the orderbook")]
+    LimitOrderWouldCross,
+    #[msg("Limit contexts can only be cancelled by the user")]
+    LimitContextRevertRequiresUser,
+    #[msg("Only limit contexts can be cancelled")]
+    NotALimitContext,
+    #[msg("Signer is not allowed to do this operation")]
+    Unauthorized,
+    #[msg("Leg could not be filled within the oracle price band")]
+    LegPriceOutOfBand,
+    #[msg("All the basket components are not added yet")]
+    BasketIncomplete,
+    #[msg("Not all basket components are priced yet")]
+    BasketNotPriced,
+    #[msg("Fee is higher than the maximum allowed fee")]
+    FeeTooHigh,
+    #[msg("Unknown fee vault")]
+    UnknownFeeVault,
+    #[msg("Not enough quote tokens left to pay the fee")]
+    InsufficientQuoteForFee,
+    #[msg("Creator fee is higher than the maximum allowed creator fee")]
+    CreatorFeeTooHigh,
+    #[msg("Amount should be greater than 0")]
+    InvalidAmount,
+    #[msg("Context side is not supported by this instruction")]
+    InvalidContextSide,
+    #[msg("Sell legs of a swap should be processed before its buy legs")]
+    SwapSellLegsPending,
+    #[msg("Source and destination baskets of a swap should be different")]
+    SwapSameBasket,
+    #[msg("Quote currency limit reached")]
+    QuoteCurrencyLimitReached,
+    #[msg("Quote currency is already registered")]
+    QuoteCurrencyAlreadyRegistered,
+    #[msg("Quote currency is not accepted by the group")]
+    UnknownQuoteCurrency,
+    #[msg("Quote currency is not priced yet")]
+    QuoteCurrencyNotPriced,
+    #[msg("Oracle price status is not trading")]
+    OracleNotTrading,
+    #[msg("Price is older than the maximum price age")]
+    StalePrice,
+    #[msg("Oracle price moved too far from the cached price")]
+    PriceDeviationTooHigh,
+    #[msg("Not enough liquidity on the orderbook for the basket size")]
+    InsufficientBookDepth,
+    #[msg("Moving average window should be greater than 0")]
+    InvalidEmaWindow,
+    #[msg("Owner and pending owner are the same")]
+    AlreadyOwner,
+    #[msg("Operation is paused")]
+    Paused,
+    #[msg("Basket is being rebalanced")]
+    BasketRebalancing,
+    #[msg("Only the curator can revert a rebalance")]
+    RebalanceRevertRequiresCurator,
+    #[msg("Token account is not the vault of the basket for this token")]
+    UnknownBasketVault,
+    #[msg("Leg amount does not match the amount traded")]
+    InvalidLegAmount,
+    #[msg("Leg slippage is too high")]
+    SlippageTooHigh,
+    #[msg("Oracle confidence threshold should be greater than 0 and at most the maximum threshold")]
+    ConfidenceThresholdOutOfRange,
+    #[msg("Price deviation threshold is too high")]
+    DeviationTooHigh,
+    #[msg("Basket cannot be rebalanced while it has open trade contexts")]
+    BasketHasOpenContexts,
+    #[msg("Components should continue the pending orderbook walk of the basket")]
+    BookWalkMismatch,
+    #[msg("Components should continue the pending audit of the basket")]
+    AuditMismatch,
+    #[msg("A token can back only one component of a basket")]
+    DuplicateComponent,
+    #[msg("Basket components should be tokens listed by the program")]
+    ComponentNotListed,
+    #[msg("Order size and quote budget of a leg should be greater than 0")]
+    EmptyOrder,
+    #[msg("Components should continue the pending transfer of the in kind context")]
+    InKindMismatch,
+    #[msg("Market should trade the token against the quote currency")]
+    MarketMintMismatch,
 }
\ No newline at end of file
@@ -0,0 +1,245 @@
+use crate::*;
+
+/// Emitted when a group is initialized
+#[event]
+pub struct GroupInitialized {
+    pub group : Pubkey,
+    pub owner : Pubkey,
+    pub base_mint : Pubkey,
+}
+
+/// Emitted when the owner of a group proposes a new owner, default if the transfer is cancelled
+#[event]
+pub struct OwnershipProposed {
+    pub group : Pubkey,
+    pub owner : Pubkey,
+    pub pending_owner : Pubkey,
+}
+
+/// Emitted when the pending owner of a group accepts the ownership
+#[event]
+pub struct OwnershipTransferred {
+    pub group : Pubkey,
+    pub previous_owner : Pubkey,
+    pub owner : Pubkey,
+}
+
+/// Emitted when a role of a group is delegated, default if the role is given back to the owner
+#[event]
+pub struct RoleSet {
+    pub group : Pubkey,
+    pub role : GroupRole,
+    pub member : Pubkey,
+}
+
+/// Emitted when the pause flags of a group, a basket or a token description are changed
+#[event]
+pub struct PauseSet {
+    pub group : Pubkey,
+    pub target : Pubkey,
+    pub pause_flags : u8,
+}
+
+/// Emitted when a token is added to a group
+#[event]
+pub struct TokenAdded {
+    pub group : Pubkey,
+    pub token_desc : Pubkey,
+    pub token_mint : Pubkey,
+    pub price_oracle : Pubkey,
+    pub market : Pubkey,
+}
+
+/// Emitted when a basket is created
+#[event]
+pub struct BasketCreated {
+    pub group : Pubkey,
+    pub basket : Pubkey,
+    pub basket_mint : Pubkey,
+    pub creator : Pubkey,
+    pub number_of_components : u8,
+    pub components : Vec<BasketComponentDescription>,
+    pub creator_fee_bps : u64,
+}
+
+/// Emitted when components are added to a basket which is not complete
+#[event]
+pub struct BasketComponentsAdded {
+    pub basket : Pubkey,
+    pub components : Vec<BasketComponentDescription>,
+}
+
+/// Emitted when the cached price of a token is updated from its oracle
+#[event]
+pub struct PriceUpdated {
+    pub token_desc : Pubkey,
+    pub token_mint : Pubkey,
+    pub price : u64,
+    pub confidence : u64,
+    pub exp : i32,
+    pub publish_slot : u64,
+    pub ema_price : u64,
+}
+
+/// Emitted when the price of a quote currency is updated from its oracle
+#[event]
+pub struct QuotePriceUpdated {
+    pub group : Pubkey,
+    pub quote_token_mint : Pubkey,
+    pub price : u64,
+}
+
+/// Emitted when the price of a basket is computed from the cached prices of its components
+#[event]
+pub struct BasketPriceUpdated {
+    pub basket : Pubkey,
+    pub price : u64,
+    pub confidence : u64,
+    pub price_slot : u64,
+    pub ema_price : u64,
+    pub is_priced : bool,
+}
+
+/// Emitted when the executable price of a basket is computed from the orderbooks
+#[event]
+pub struct BasketBookPriceUpdated {
+    pub basket : Pubkey,
+    pub buy_price : u64,
+    pub sell_price : u64,
+    pub amount : u64,
+}
+
+/// Emitted when a trade, swap or rebalance context is created
+#[event]
+pub struct TradeContextCreated {
+    pub trade_context : Pubkey,
+    pub basket : Pubkey,
+    pub user : Pubkey,
+    pub side : ContextSide,
+    pub order_type : ContextOrderType,
+    pub amount : u64,
+    pub quote_token_mint : Pubkey,
+    pub quote_deposited : u64,
+    pub destination_basket : Pubkey,
+    pub destination_amount : u64,
+}
+
+/// Emitted every time a leg of a context is processed on serum
+#[event]
+pub struct LegProcessed {
+    pub trade_context : Pubkey,
+    pub token_mint : Pubkey,
+    pub is_buy : bool,
+    pub reverting : bool,
+    pub quantity : u64,
+    pub quote_amount : u64,
+    pub remaining : u64,
+    pub treated : bool,
+}
+
+/// Emitted when a context is finalized and closed
+#[event]
+pub struct TradeContextFinalized {
+    pub trade_context : Pubkey,
+    pub basket : Pubkey,
+    pub user : Pubkey,
+    pub side : ContextSide,
+    pub reverted : bool,
+    // sold baskets are burned when the context is created, a reverted sell mints them back
+    pub basket_minted : u64,
+    pub quote_returned : u64,
+}
+
+/// Emitted when a context starts reverting, cancelled if the user asked for it
+#[event]
+pub struct TradeContextReverted {
+    pub trade_context : Pubkey,
+    pub basket : Pubkey,
+    pub user : Pubkey,
+    pub cancelled : bool,
+}
+
+/// Emitted when the pending components of a basket are swapped in by a rebalance
+#[event]
+pub struct BasketRebalanced {
+    pub basket : Pubkey,
+    pub trade_context : Pubkey,
+    pub components : Vec<BasketComponentDescription>,
+}
+
+/// Emitted every time a protocol fee is taken during finalize
+#[event]
+pub struct FeeTaken {
+    pub group : Pubkey,
+    pub basket : Pubkey,
+    pub trade_context : Pubkey,
+    pub user : Pubkey,
+    pub side : ContextSide,
+    pub quote_token_mint : Pubkey,
+    pub amount : u64,
+}
+
+/// Emitted by view_basket_backing, tokens held in the vaults of a basket against the tokens required by its supply
+#[event]
+pub struct BasketBacking {
+    pub basket : Pubkey,
+    pub supply : u64,
+    pub token_mints : Vec<Pubkey>,
+    pub vault_amounts : Vec<u64>,
+    pub required_amounts : Vec<u64>,
+}
+
+/// Emitted by audit_basket with the solvency recorded on the basket
+#[event]
+pub struct BasketAudited {
+    pub basket : Pubkey,
+    pub supply : u64,
+    pub solvency_bps : u64,
+    pub timestamp : i64,
+    pub paused : bool,
+}
+
+/// Emitted when the group owner withdraws protocol fees
+#[event]
+pub struct FeesWithdrawn {
+    pub group : Pubkey,
+    pub quote_token_mint : Pubkey,
+    pub destination : Pubkey,
+    pub amount : u64,
+}
+
+/// Emitted every time a creator fee is accrued for a basket during finalize
+#[event]
+pub struct CreatorFeeAccrued {
+    pub basket : Pubkey,
+    pub trade_context : Pubkey,
+    pub creator : Pubkey,
+    pub quote_token_mint : Pubkey,
+    pub amount : u64,
+}
+
+/// Emitted when the creator of a basket claims the accrued fees
+#[event]
+pub struct CreatorFeesClaimed {
+    pub basket : Pubkey,
+    pub creator : Pubkey,
+    pub quote_token_mint : Pubkey,
+    pub destination : Pubkey,
+    pub amount : u64,
+}
+
+/// Emitted when basket tokens are minted against the components given by the user
+#[event]
+pub struct BasketCreatedInKind {
+    pub basket : Pubkey,
+    pub user : Pubkey,
+    pub amount : u64,
+}
+
+/// Emitted when basket tokens are burned against the components given back to the user
+#[event]
+pub struct BasketRedeemedInKind {
+    pub basket : Pubkey,
+    pub user : Pubkey,
+    pub amount : u64,
+}
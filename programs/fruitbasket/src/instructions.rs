@@ -25,6 +25,101 @@ pub struct InitializeGroup<'info> {
     pub token_program : AccountInfo<'info>,
 }
 
+/// Configure group -> to change the parameters of the group.
+/// This should be done only by owner of the group
+#[derive(Accounts)]
+pub struct ConfigureGroup<'info> {
+    #[account(signer)]
+    pub owner : AccountInfo<'info>,
+
+    #[account(mut)]
+    pub group : AccountLoader<'info, FruitBasketGroup>,
+}
+
+/// Accept ownership -> the proposed owner takes over the group.
+/// This should be done only by the pending owner of the group
+#[derive(Accounts)]
+pub struct AcceptOwnership<'info> {
+    #[account(signer)]
+    pub new_owner : AccountInfo<'info>,
+
+    #[account(mut)]
+    pub group : AccountLoader<'info, FruitBasketGroup>,
+}
+
+/// Create fee vault -> token account owned by fruitbasket authority where protocol fees are collected.
+/// There is a fee vault for each quote token of the group
+#[derive(Accounts)]
+#[instruction(bump : u8)]
+pub struct CreateFeeVault<'info> {
+    #[account(mut, signer)]
+    pub owner : AccountInfo<'info>,
+
+    pub group : AccountLoader<'info, FruitBasketGroup>,
+
+    pub quote_token_mint : Account<'info, Mint>,
+
+    #[account(init,
+              seeds = [FRUIT_BASKET_FEE_VAULT, &group.key().to_bytes(), &quote_token_mint.key().to_bytes()],
+              bump = bump,
+              payer = owner,
+              token::mint = quote_token_mint,
+              token::authority = fruit_basket_authority)]
+    pub fee_vault : Account<'info, TokenAccount>,
+
+    pub fruit_basket_authority : AccountInfo<'info>,
+    pub system_program : Program<'info, System>,
+    pub token_program : Program<'info, anchor_spl::token::Token>,
+    pub rent : Sysvar<'info, Rent>,
+}
+
+/// Withdraw fees -> transfer collected protocol fees from the fee vault.
+/// This should be done only by owner or fee manager of the group
+#[derive(Accounts)]
+pub struct WithdrawFees<'info> {
+    #[account(signer)]
+    pub owner : AccountInfo<'info>,
+
+    pub group : AccountLoader<'info, FruitBasketGroup>,
+
+    #[account(mut)]
+    pub fee_vault : Account<'info, TokenAccount>,
+
+    #[account(mut, constraint = destination.mint == fee_vault.mint)]
+    pub destination : Account<'info, TokenAccount>,
+
+    pub fruit_basket_authority : AccountInfo<'info>,
+    pub token_program : AccountInfo<'info>,
+}
+
+/// Add quote currency -> to accept a new quote currency to buy and sell baskets.
+/// This should be done only by owner of the group
+#[derive(Accounts)]
+pub struct AddQuoteCurrency<'info> {
+    #[account(signer)]
+    pub owner : AccountInfo<'info>,
+
+    #[account(mut)]
+    pub group : AccountLoader<'info, FruitBasketGroup>,
+
+    pub quote_token_mint : Account<'info, Mint>,
+
+    #[account(mut,
+              constraint = quote_token_transaction_pool.mint == quote_token_mint.key())]
+    pub quote_token_transaction_pool : Account<'info, TokenAccount>,
+
+    pub price_oracle : AccountInfo<'info>,
+    pub token_program : AccountInfo<'info>,
+}
+
+// Permissionless instruction which should be called to update the price of a quote currency
+#[derive(Accounts)]
+pub struct UpdateQuotePrice<'info> {
+    #[account(mut)]
+    pub group : AccountLoader<'info, FruitBasketGroup>,
+    pub oracle_ai : AccountInfo<'info>,
+}
+
 /// Add Token ->  to add new token to the market.
 /// To add a token we need to know the market and pyth price and product keys
 #[derive(Accounts)]
@@ -60,12 +155,63 @@ pub struct AddToken<'info>{
     pub rent : AccountInfo<'info>,
 }
 
+/// Configure token -> to change the oracle guards of a token.
+/// This should be done only by owner or token lister of the group
+#[derive(Accounts)]
+pub struct ConfigureToken<'info> {
+    #[account(signer)]
+    pub owner : AccountInfo<'info>,
+
+    pub group : AccountLoader<'info, FruitBasketGroup>,
+
+    #[account(mut, constraint = token_desc.group == group.key())]
+    pub token_desc : Box<Account<'info, TokenDescription>>,
+}
+
+/// Pause token -> to halt the processing of the legs of a token.
+/// This should be done only by owner or pauser of the group
+#[derive(Accounts)]
+pub struct PauseToken<'info> {
+    #[account(signer)]
+    pub pauser : AccountInfo<'info>,
+
+    pub group : AccountLoader<'info, FruitBasketGroup>,
+
+    #[account(mut, constraint = token_desc.group == group.key())]
+    pub token_desc : Box<Account<'info, TokenDescription>>,
+}
+
+/// Add token market -> to trade a token against a quote currency other than the base mint.
+/// This should be done only by owner or token lister of the group
+/// The market should trade the token against the quote currency
+#[derive(Accounts)]
+pub struct AddTokenMarket<'info> {
+    #[account(signer)]
+    pub owner : AccountInfo<'info>,
+
+    pub group : AccountLoader<'info, FruitBasketGroup>,
+
+    #[account(mut, constraint = token_desc.group == group.key())]
+    pub token_desc : Box<Account<'info, TokenDescription>>,
+
+    pub quote_token_mint : Account<'info, Mint>,
+
+    pub market : AccountInfo<'info>,
+    #[account(mut)]
+    pub open_orders_account : AccountInfo<'info>,
+    pub fruitbasket_authority : AccountInfo<'info>,
+    pub dex_program : AccountInfo<'info>,
+    pub rent : AccountInfo<'info>,
+}
+
 /// Add basket -> To create a new basket.
-/// Need to pass all token mints and amounts by instruction
+/// Need to pass number of components and token mints and amounts by instruction
+/// Remaining components can be added with AddBasketComponents if they do not fit in a single transaction
+/// Components should be distinct listed tokens, pass the token description of each component in remaining accounts
 /// This will create a basket key and a basket mint key
 /// Basket mint are special mint for each basket that will be minted when you buy a basket
 #[derive(Accounts)]
-#[instruction(basket_number : u64, basket_bump : u8, basket_mint_bump : u8)]
+#[instruction(basket_number : u64, basket_bump : u8, basket_mint_bump : u8, basket_name : String, basket_desc : String, number_of_components : u8)]
 pub struct AddBasket<'info> {
     #[account(mut, signer)]
     pub client : AccountInfo<'info>,
@@ -77,7 +223,7 @@ pub struct AddBasket<'info> {
                seeds = [FRUIT_BASKET, &basket_number.to_le_bytes()],
                bump = basket_bump,
                payer = client,
-               space = 8 + size_of::<Basket>())]
+               space = 8 + Basket::space(number_of_components as usize))]
     pub basket : Box<Account<'info, Basket>>,
 
     #[account(init,
@@ -93,12 +239,135 @@ pub struct AddBasket<'info> {
     pub rent : Sysvar<'info, Rent>,
 }
 
+/// Pause basket -> to halt new contexts, processing or finalization for a single basket.
+/// This should be done only by owner or pauser of the group
+#[derive(Accounts)]
+pub struct PauseBasket<'info> {
+    #[account(signer)]
+    pub pauser : AccountInfo<'info>,
+
+    pub group : AccountLoader<'info, FruitBasketGroup>,
+
+    #[account(mut, constraint = basket.group == group.key())]
+    pub basket : Box<Account<'info, Basket>>,
+}
+
+/// Create creator fee vault -> token account owned by fruitbasket authority where creator fees of a basket are accrued.
+/// There is a creator fee vault for each basket and quote token, anyone can create it
+#[derive(Accounts)]
+#[instruction(bump : u8)]
+pub struct CreateCreatorFeeVault<'info> {
+    #[account(mut, signer)]
+    pub payer : AccountInfo<'info>,
+
+    pub basket : Box<Account<'info, Basket>>,
+
+    pub quote_token_mint : Account<'info, Mint>,
+
+    #[account(init,
+              seeds = [FRUIT_BASKET_CREATOR_FEE_VAULT, &basket.key().to_bytes(), &quote_token_mint.key().to_bytes()],
+              bump = bump,
+              payer = payer,
+              token::mint = quote_token_mint,
+              token::authority = fruit_basket_authority)]
+    pub creator_fee_vault : Account<'info, TokenAccount>,
+
+    pub fruit_basket_authority : AccountInfo<'info>,
+    pub system_program : Program<'info, System>,
+    pub token_program : Program<'info, anchor_spl::token::Token>,
+    pub rent : Sysvar<'info, Rent>,
+}
+
+/// Claim creator fees -> transfer all the fees accrued for a basket to the creator.
+/// Only the creator of the basket can claim the fees
+#[derive(Accounts)]
+pub struct ClaimCreatorFees<'info> {
+    #[account(signer)]
+    pub creator : AccountInfo<'info>,
+
+    #[account(constraint = basket.creator == creator.key())]
+    pub basket : Box<Account<'info, Basket>>,
+
+    #[account(mut)]
+    pub creator_fee_vault : Account<'info, TokenAccount>,
+
+    #[account(mut, constraint = destination.mint == creator_fee_vault.mint)]
+    pub destination : Account<'info, TokenAccount>,
+
+    pub fruit_basket_authority : AccountInfo<'info>,
+    pub token_program : AccountInfo<'info>,
+}
+
+/// Add basket components -> To add components to a basket which is not complete.
+/// Only the creator of the basket can add components
+/// Components should be distinct listed tokens, pass the token description of each component in remaining accounts
+#[derive(Accounts)]
+pub struct AddBasketComponents<'info> {
+    #[account(signer)]
+    pub client : AccountInfo<'info>,
+
+    #[account(mut, constraint = basket.creator == client.key())]
+    pub basket : Box<Account<'info, Basket>>,
+}
+
+/// Create basket vault -> token account owned by fruitbasket authority where the tokens backing a basket are kept.
+/// There is a vault for each basket and token, anyone can create it
+#[derive(Accounts)]
+#[instruction(bump : u8)]
+pub struct CreateBasketVault<'info> {
+    #[account(mut, signer)]
+    pub payer : AccountInfo<'info>,
+
+    pub basket : Box<Account<'info, Basket>>,
+
+    pub token_mint : Account<'info, Mint>,
+
+    #[account(init,
+              seeds = [FRUIT_BASKET_VAULT, &basket.key().to_bytes(), &token_mint.key().to_bytes()],
+              bump = bump,
+              payer = payer,
+              token::mint = token_mint,
+              token::authority = fruit_basket_authority)]
+    pub basket_vault : Account<'info, TokenAccount>,
+
+    pub fruit_basket_authority : AccountInfo<'info>,
+    pub system_program : Program<'info, System>,
+    pub token_program : Program<'info, anchor_spl::token::Token>,
+    pub rent : Sysvar<'info, Rent>,
+}
+
+/// View basket backing -> emits the tokens held by the basket vaults against the tokens required by the basket supply.
+/// For each component from first_component, in the order of the basket, pass in remaining accounts :
+/// token description and basket vault of the component.
+#[derive(Accounts)]
+pub struct ViewBasketBacking<'info> {
+    pub basket : Box<Account<'info, Basket>>,
+
+    #[account(constraint = basket.basket_mint == basket_token_mint.key())]
+    pub basket_token_mint : Account<'info, Mint>,
+}
+
+/// Audit basket -> records on the basket how much of its supply is backed by its vaults.
+/// Permissionless, pass the same remaining accounts as ViewBasketBacking.
+/// Components can be audited over several transactions while the supply is unchanged,
+/// the solvency is recorded once the last component is audited.
+#[derive(Accounts)]
+pub struct AuditBasket<'info> {
+    pub group : AccountLoader<'info, FruitBasketGroup>,
+
+    #[account(mut, constraint = basket.group == group.key())]
+    pub basket : Box<Account<'info, Basket>>,
+
+    #[account(constraint = basket.basket_mint == basket_token_mint.key())]
+    pub basket_token_mint : Account<'info, Mint>,
+}
+
 // Permissionless instruction which should be called to update price in cache
 // This will called after a fixed period by offchain program
 #[derive(Accounts)]
 pub struct UpdatePrice<'info> {
     pub group : AccountLoader<'info, FruitBasketGroup>,
-    #[account(mut)]
+    #[account(mut, constraint = token_desc.group == group.key())]
     pub token_desc : Box<Account<'info, TokenDescription>>,
     #[account(constraint = token_desc.price_oracle == oracle_ai.key())]
     pub oracle_ai : AccountInfo<'info>,
@@ -109,8 +378,58 @@ pub struct UpdatePrice<'info> {
 // called by offchain program at a fixed interval
 #[derive(Accounts)]
 pub struct UpdateBasketPrice<'info> {
+    pub group : AccountLoader<'info, FruitBasketGroup>,
+    #[account(mut, constraint = basket.group == group.key())]
+    pub basket : Box<Account<'info, Basket>>,
+    // pyth compatible price account of the basket, only updated once created
+    #[account(mut)]
+    pub basket_nav : AccountInfo<'info>,
+}
+
+/// Create basket nav -> price account laid out like a pyth price account,
+/// so that any pyth client can read the price of a basket.
+/// The account is not an anchor account, there is no discriminator. Anyone can create it.
+#[derive(Accounts)]
+pub struct CreateBasketNav<'info> {
+    #[account(mut, signer)]
+    pub payer : AccountInfo<'info>,
+
+    pub basket : Box<Account<'info, Basket>>,
+
     #[account(mut)]
+    pub basket_nav : AccountInfo<'info>,
+
+    pub system_program : Program<'info, System>,
+}
+
+/// Create basket open orders -> serum open orders of a basket on a market.
+/// Each basket trades its legs with its own open orders so that settled funds always go to its own vaults.
+/// The account is owned by the dex program. Anyone can create it.
+#[derive(Accounts)]
+pub struct CreateBasketOpenOrders<'info> {
+    #[account(mut, signer)]
+    pub payer : AccountInfo<'info>,
+
+    pub basket : Box<Account<'info, Basket>>,
+    pub market : AccountInfo<'info>,
+
+    #[account(mut)]
+    pub open_orders : AccountInfo<'info>,
+    pub fruitbasket_authority : AccountInfo<'info>,
+    pub dex_program : AccountInfo<'info>,
+    pub system_program : Program<'info, System>,
+    pub rent : AccountInfo<'info>,
+}
+
+// permissionless instruction which should be called to price a basket size from the serum orderbooks
+// for each component from first_component pass token description, market, bids and asks as remaining accounts
+// components can be walked over several transactions, prices are updated once the last component is walked
+#[derive(Accounts)]
+pub struct UpdateBasketBookPrice<'info> {
+    pub group : AccountLoader<'info, FruitBasketGroup>,
+    #[account(mut, constraint = basket.group == group.key())]
     pub basket : Box<Account<'info, Basket>>,
+    pub dex_program : AccountInfo<'info>,
 }
 
 /// Creates a context for a basket trade {buying, selling}
@@ -128,6 +447,7 @@ pub struct InitTradeContext<'info> {
     #[account(signer, mut)]
     pub user : AccountInfo<'info>,
 
+    #[account(mut, constraint = basket.group == group.key())]
     pub basket : Box<Account<'info, Basket>>,
     
     // user quote token account i.e usdc account
@@ -154,9 +474,63 @@ pub struct InitTradeContext<'info> {
                 seeds = [FRUIT_BASKET_CONTEXT, &user.key.to_bytes(), &[order_id]],
                 bump = context_bump,
                 payer = user,
-                space = 8 + size_of::<BasketTradeContext>(),
+                space = 8 + BasketTradeContext::space(basket.components.len()),
+            )]
+    pub trade_context : Box<Account<'info, BasketTradeContext>>,
+
+    #[account(mut)]
+    pub quote_token_transaction_pool : Account<'info, TokenAccount>,
+    pub fruit_basket_authority : AccountInfo<'info>,
+
+    pub token_program : AccountInfo<'info>,
+    pub system_program : Program<'info, System>,
+}
+
+/// Creates a context to swap a basket for another basket.
+/// Components shared by both baskets are netted, only the difference is traded on serum.
+/// Source basket tokens are burned here and destination basket tokens are minted by FinalizeSwapContext.
+/// The user can add quote tokens if the destination basket is worth more than the source basket.
+/// Fees of the traded legs are reserved at their worst price and deposited on top of the added quote tokens.
+#[derive(Accounts)]
+#[instruction( order_id: u8, context_bump : u8,)]
+pub struct InitSwapContext<'info> {
+    pub group : AccountLoader<'info, FruitBasketGroup>,
+
+    #[account(signer, mut)]
+    pub user : AccountInfo<'info>,
+
+    #[account(mut)]
+    pub source_basket : Box<Account<'info, Basket>>,
+
+    #[account(mut)]
+    pub destination_basket : Box<Account<'info, Basket>>,
+
+    #[account(mut,
+                constraint = quote_token_account.owner == *user.key,
+                constraint = quote_token_account.mint == *quote_token_mint.to_account_info().key)]
+    pub quote_token_account : Account<'info, TokenAccount>,
+
+    #[account(mut,
+              constraint = source_basket_token_account.owner == *user.key,
+              constraint = source_basket_token_account.mint == source_basket.basket_mint, )]
+    pub source_basket_token_account : Account<'info, TokenAccount>,
+
+    #[account(constraint = destination_basket_token_account.owner == *user.key,
+              constraint = destination_basket_token_account.mint == destination_basket.basket_mint, )]
+    pub destination_basket_token_account : Account<'info, TokenAccount>,
+
+    pub quote_token_mint : Account<'info, Mint>,
+
+    #[account(mut, constraint = source_basket.basket_mint == source_basket_token_mint.key(),)]
+    pub source_basket_token_mint : Account<'info, Mint>,
+
+    #[account(init,
+                seeds = [FRUIT_BASKET_CONTEXT, &user.key.to_bytes(), &[order_id]],
+                bump = context_bump,
+                payer = user,
+                space = 8 + BasketTradeContext::space(source_basket.components.len() + destination_basket.components.len()),
             )]
-    pub trade_context : AccountLoader<'info, BasketTradeContext>,
+    pub trade_context : Box<Account<'info, BasketTradeContext>>,
 
     #[account(mut)]
     pub quote_token_transaction_pool : Account<'info, TokenAccount>,
@@ -172,8 +546,9 @@ pub struct InitTradeContext<'info> {
 /// This method should be always permissionless as it will be called by an offchain program
 #[derive(Accounts)]
 pub struct ProcessTokenOnContext<'info> {
+    pub group : AccountLoader<'info, FruitBasketGroup>,
     #[account(mut)]
-    pub trade_context : AccountLoader<'info, BasketTradeContext>,
+    pub trade_context : Box<Account<'info, BasketTradeContext>>,
     #[account(constraint = token_desc.token_mint == token_mint.key())]
     pub token_desc : Box<Account<'info, TokenDescription>>,
 
@@ -181,11 +556,13 @@ pub struct ProcessTokenOnContext<'info> {
 
     pub quote_token_mint : Account<'info, Mint>,
 
+    #[account(constraint = fruitbasket.group == group.key())]
     pub fruitbasket : Box<Account<'info, Basket>>,
     // accounts related to market and serum
-    #[account(mut, constraint = market.key() == token_desc.market)]
+    // market of the token for the quote currency of the context, open orders of the basket backed by the leg on that market
+    #[account(mut)]
     pub market: AccountInfo<'info>,
-    #[account(mut, constraint = open_orders.key() == token_desc.token_open_orders)]
+    #[account(mut)]
     pub open_orders: AccountInfo<'info>,
     #[account(mut)]
     pub request_queue: AccountInfo<'info>,
@@ -200,7 +577,7 @@ pub struct ProcessTokenOnContext<'info> {
     #[account(mut)]
     pub quote_token_vault: AccountInfo<'info>,
     pub vault_signer: AccountInfo<'info>,
-    // pool where all tokens are kept
+    // vault of the basket backed by the leg, the destination basket for the buy legs of a swap
     #[account(mut)]
     pub token_pool : AccountInfo<'info>,
     // pool where all usdc in transaction are kept belonging baskets
@@ -222,9 +599,12 @@ pub struct ProcessTokenOnContext<'info> {
 /// permissionless as it is called by offchain program
 #[derive(Accounts)]
 pub struct FinalizeContext <'info> {
+    pub fruitbasket_group : AccountLoader<'info, FruitBasketGroup>,
+
     #[account(mut, close = user)]
-    pub trade_context : AccountLoader<'info, BasketTradeContext>,
+    pub trade_context : Box<Account<'info, BasketTradeContext>>,
 
+    #[account(mut)]
     pub fruitbasket : Box<Account<'info, Basket>>,
 
     #[account(mut,
@@ -240,6 +620,15 @@ pub struct FinalizeContext <'info> {
     #[account(mut)]
     pub quote_token_transaction_pool : Account<'info, TokenAccount>,
 
+    // protocol fees are transfered to the fee vault of the quote token
+    #[account(mut)]
+    pub fee_vault : Account<'info, TokenAccount>,
+
+    // creator fees are accrued in the creator fee vault of the basket
+    // the vault is only checked when fees are taken, it may not exist to finalize a reverted context
+    #[account(mut)]
+    pub creator_fee_vault : AccountInfo<'info>,
+
     pub fruit_basket_authority : AccountInfo<'info>,
 
     pub quote_token_mint : Account<'info, Mint>,
@@ -251,14 +640,253 @@ pub struct FinalizeContext <'info> {
     pub system_program : Program<'info, System>,
 }
 
+/// Finalize and close a swap context
+/// Mints the destination basket tokens and gives back the quote tokens left to the user.
+/// A reverted swap gives back the source basket tokens and the quote tokens added by the user.
+/// For each leg shared by both baskets, pass in remaining accounts :
+/// token description, source basket vault and destination basket vault of the token.
+/// permissionless as it is called by offchain program
+#[derive(Accounts)]
+pub struct FinalizeSwapContext <'info> {
+    pub fruitbasket_group : AccountLoader<'info, FruitBasketGroup>,
+
+    #[account(mut, close = user)]
+    pub trade_context : Box<Account<'info, BasketTradeContext>>,
+
+    #[account(mut)]
+    pub source_basket : Box<Account<'info, Basket>>,
+
+    #[account(mut)]
+    pub destination_basket : Box<Account<'info, Basket>>,
+
+    #[account(mut,
+        constraint = quote_token_account.owner == user.key(),
+        constraint = quote_token_account.mint == quote_token_mint.key())]
+    pub quote_token_account : Account<'info, TokenAccount>,
+
+    #[account(mut,
+        constraint = source_basket_token_account.owner == user.key(),
+        constraint = source_basket_token_account.mint == source_basket.basket_mint)]
+    pub source_basket_token_account : Account<'info, TokenAccount>,
+
+    #[account(mut,
+        constraint = destination_basket_token_account.owner == user.key(),
+        constraint = destination_basket_token_account.mint == destination_basket.basket_mint)]
+    pub destination_basket_token_account : Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub quote_token_transaction_pool : Account<'info, TokenAccount>,
+
+    // fee vaults are only checked when fees are taken, they may not exist to finalize a reverted swap
+    #[account(mut)]
+    pub fee_vault : AccountInfo<'info>,
+
+    // creator of the source basket is rewarded on quote received by sell legs
+    #[account(mut)]
+    pub source_creator_fee_vault : AccountInfo<'info>,
+
+    // creator of the destination basket is rewarded on quote spent by buy legs
+    #[account(mut)]
+    pub destination_creator_fee_vault : AccountInfo<'info>,
+
+    pub fruit_basket_authority : AccountInfo<'info>,
+
+    pub quote_token_mint : Account<'info, Mint>,
+    #[account(mut, constraint = source_basket.basket_mint == source_basket_token_mint.key())]
+    pub source_basket_token_mint : Account<'info, Mint>,
+    #[account(mut, constraint = destination_basket.basket_mint == destination_basket_token_mint.key())]
+    pub destination_basket_token_mint : Account<'info, Mint>,
+    #[account(mut)]
+    pub user : AccountInfo<'info>,
+    pub token_program: AccountInfo<'info>,
+    pub system_program : Program<'info, System>,
+}
+
+/// Creates a context to rebalance a basket to new components.
+/// The difference between the tokens held by the basket vaults and the new backing of the basket supply
+/// is traded on serum against the base mint, components which are reduced are sold before the increased components are bought.
+/// Only the creator of the basket or the basket curator of the group can rebalance it, once the basket has no open context.
+/// For each component, in the order of the basket, pass in remaining accounts :
+/// token description and basket vault of the component.
+#[derive(Accounts)]
+#[instruction( order_id: u8, context_bump : u8, new_components : Vec<BasketComponentDescription>)]
+pub struct InitRebalanceContext<'info> {
+    pub group : AccountLoader<'info, FruitBasketGroup>,
+
+    #[account(signer, mut)]
+    pub curator : AccountInfo<'info>,
+
+    #[account(mut, constraint = basket.group == group.key())]
+    pub basket : Box<Account<'info, Basket>>,
+
+    #[account(constraint = basket.basket_mint == basket_token_mint.key())]
+    pub basket_token_mint : Account<'info, Mint>,
+
+    #[account(init,
+                seeds = [FRUIT_BASKET_CONTEXT, &curator.key.to_bytes(), &[order_id]],
+                bump = context_bump,
+                payer = curator,
+                space = 8 + BasketTradeContext::space(basket.components.len() + new_components.len()),
+            )]
+    pub trade_context : Box<Account<'info, BasketTradeContext>>,
+
+    pub system_program : Program<'info, System>,
+}
+
+/// Finalize and close a rebalance context
+/// Swaps in the pending components of the basket, the quote left by the legs is kept in the basket vault of the base mint.
+/// A reverted rebalance keeps the current components.
+/// permissionless as it is called by offchain program
+#[derive(Accounts)]
+pub struct FinalizeRebalanceContext <'info> {
+    pub group : AccountLoader<'info, FruitBasketGroup>,
+
+    #[account(mut, close = curator)]
+    pub trade_context : Box<Account<'info, BasketTradeContext>>,
+
+    #[account(mut, constraint = basket.group == group.key())]
+    pub basket : Box<Account<'info, Basket>>,
+
+    #[account(mut)]
+    pub quote_token_transaction_pool : Account<'info, TokenAccount>,
+
+    // the vault is only checked when quote is left, it may not exist if the legs spent all the quote
+    #[account(mut)]
+    pub basket_quote_vault : AccountInfo<'info>,
+
+    pub fruit_basket_authority : AccountInfo<'info>,
+
+    #[account(mut, constraint = trade_context.user == curator.key())]
+    pub curator : AccountInfo<'info>,
+    pub token_program: AccountInfo<'info>,
+}
+
 /// This instruction will be used to revert the trade context if there is a failure during trade of the basket.
 /// permissionless as it wil be used by offchain program
 /// off chain program will revert all context if they are not treated before n seconds
 #[derive(Accounts)]
 pub struct RevertTradeContext<'info> {
     #[account(mut)]
-    pub trade_context : AccountLoader<'info, BasketTradeContext>,
+    pub trade_context : Box<Account<'info, BasketTradeContext>>,
+
+    pub fruitbasket : Box<Account<'info, Basket>>,
+    pub quote_token_transaction_pool : AccountInfo<'info>,
+}
+
+/// Cancel a limit trade context.
+/// Limit contexts stay open until all the legs are filled, so only the user who created it can cancel it.
+/// The creator of the basket or the basket curator of the group can cancel it too, as a rebalance waits for the basket to have no open context.
+/// Resting orders are cancelled and filled legs are reverted while processing the context.
+#[derive(Accounts)]
+pub struct CancelTradeContext<'info> {
+    pub group : AccountLoader<'info, FruitBasketGroup>,
+
+    #[account(signer)]
+    pub authority : AccountInfo<'info>,
+
+    #[account(mut)]
+    pub trade_context : Box<Account<'info, BasketTradeContext>>,
 
+    #[account(constraint = fruitbasket.group == group.key())]
     pub fruitbasket : Box<Account<'info, Basket>>,
     pub quote_token_transaction_pool : AccountInfo<'info>,
 }
+
+/// Creates a context to create or redeem basket tokens in kind.
+/// The user gives (or gets back) the exact amount of every component instead of trading them on serum.
+/// Basket tokens are burned here for a redeem, the components are then moved with ProcessInKind over several transactions
+/// and basket tokens are minted by FinalizeInKindContext for a creation.
+/// The context counts as an open context of the basket so that its components cannot be rebalanced meanwhile.
+#[derive(Accounts)]
+#[instruction(context_bump : u8)]
+pub struct InitInKindContext<'info> {
+    #[account(signer, mut)]
+    pub user : AccountInfo<'info>,
+
+    pub group : AccountLoader<'info, FruitBasketGroup>,
+
+    #[account(mut, constraint = fruitbasket.group == group.key())]
+    pub fruitbasket : Box<Account<'info, Basket>>,
+
+    #[account(mut,
+        constraint = basket_token_account.owner == user.key(),
+        constraint = basket_token_account.mint == basket_token_mint.key())]
+    pub basket_token_account : Account<'info, TokenAccount>,
+
+    #[account(mut, constraint = fruitbasket.basket_mint == basket_token_mint.key())]
+    pub basket_token_mint : Account<'info, Mint>,
+
+    // a user has a single in kind context per basket
+    #[account(init,
+                seeds = [FRUIT_BASKET_IN_KIND, &user.key.to_bytes(), &fruitbasket.key().to_bytes()],
+                bump = context_bump,
+                payer = user,
+                space = 8 + size_of::<InKindContext>(),
+            )]
+    pub in_kind_context : Box<Account<'info, InKindContext>>,
+
+    pub token_program : AccountInfo<'info>,
+    pub system_program : Program<'info, System>,
+}
+
+/// Moves the components of an in kind context from its cursor.
+/// For each component from first_component, in the order of the basket, pass in remaining accounts :
+/// token description, token account of the user and basket vault of the component.
+/// Components are taken from the user only if the user signs, anyone can give them back to the user.
+#[derive(Accounts)]
+pub struct ProcessInKind<'info> {
+    pub user : AccountInfo<'info>,
+
+    #[account(mut,
+        constraint = in_kind_context.user == user.key(),
+        constraint = in_kind_context.basket == fruitbasket.key())]
+    pub in_kind_context : Box<Account<'info, InKindContext>>,
+
+    pub fruitbasket : Box<Account<'info, Basket>>,
+
+    pub fruit_basket_authority : AccountInfo<'info>,
+    pub token_program : AccountInfo<'info>,
+}
+
+/// Finalize and close an in kind context once its components are moved.
+/// Mints the basket tokens of a creation, a cancelled creation mints nothing.
+/// context rent returned to the user
+/// permissionless as it is called by offchain program
+#[derive(Accounts)]
+pub struct FinalizeInKindContext<'info> {
+    #[account(mut, close = user,
+        constraint = in_kind_context.user == user.key(),
+        constraint = in_kind_context.basket == fruitbasket.key(),
+        constraint = in_kind_context.basket_token_account == basket_token_account.key())]
+    pub in_kind_context : Box<Account<'info, InKindContext>>,
+
+    #[account(mut)]
+    pub fruitbasket : Box<Account<'info, Basket>>,
+
+    #[account(mut)]
+    pub basket_token_account : Account<'info, TokenAccount>,
+
+    #[account(mut, constraint = fruitbasket.basket_mint == basket_token_mint.key())]
+    pub basket_token_mint : Account<'info, Mint>,
+
+    #[account(mut)]
+    pub user : AccountInfo<'info>,
+    pub fruit_basket_authority : AccountInfo<'info>,
+    pub token_program : AccountInfo<'info>,
+}
+
+/// Cancel the creation of basket tokens in kind, the components deposited so far are given back with ProcessInKind.
+/// The creator of the basket or the basket curator of the group can cancel it too, as a rebalance waits for the basket to have no open context.
+#[derive(Accounts)]
+pub struct CancelInKindContext<'info> {
+    pub group : AccountLoader<'info, FruitBasketGroup>,
+
+    #[account(signer)]
+    pub authority : AccountInfo<'info>,
+
+    #[account(mut, constraint = in_kind_context.basket == fruitbasket.key())]
+    pub in_kind_context : Box<Account<'info, InKindContext>>,
+
+    #[account(constraint = fruitbasket.group == group.key())]
+    pub fruitbasket : Box<Account<'info, Basket>>,
+}
@@ -1,27 +1,53 @@
 use anchor_lang::prelude::*;
 use std::{mem::size_of};
+use solana_program::entrypoint::MAX_PERMITTED_DATA_INCREASE;
 use anchor_spl::token::{self, SetAuthority, TokenAccount, Mint, InitializeMint};
 use spl_token::instruction::{AuthorityType};
-use pyth_client::Price;
+use pyth_client::{Price, PriceStatus, PriceType, AccountType, CorpAction};
 use anchor_spl::dex;
 
 mod instructions;
 use instructions::*;
-mod states;
+pub mod states;
 use states::*;
 mod processor;
-mod errors;
+pub mod errors;
 use errors::*;
+pub mod events;
+use events::*;
 
 
 declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
-const MAX_NB_COMPONENTS: usize = 10;
-const FRUIT_BASKET_GROUP : &[u8] = b"fruitbasket_group";
-const FRUIT_BASKET_AUTHORITY : &[u8] = b"fruitbasket_auth";
-const FRUIT_BASKET : &[u8] = b"fruitbasket";
-const FRUIT_BASKET_MINT : &[u8] = b"fruitbasket_mint";
-const FRUIT_BASKET_CONTEXT : &[u8] = b"fruitbasket_context";
-const FRUIT_BASKET_TOKEN : &[u8] = b"fruitbasket_token";
+pub const FRUIT_BASKET_GROUP : &[u8] = b"fruitbasket_group";
+pub const FRUIT_BASKET_AUTHORITY : &[u8] = b"fruitbasket_auth";
+pub const FRUIT_BASKET : &[u8] = b"fruitbasket";
+pub const FRUIT_BASKET_MINT : &[u8] = b"fruitbasket_mint";
+pub const FRUIT_BASKET_CONTEXT : &[u8] = b"fruitbasket_context";
+pub const FRUIT_BASKET_TOKEN : &[u8] = b"fruitbasket_token";
+pub const FRUIT_BASKET_FEE_VAULT : &[u8] = b"fruitbasket_fee_vault";
+pub const FRUIT_BASKET_CREATOR_FEE_VAULT : &[u8] = b"fruitbasket_creator_fee_vault";
+pub const FRUIT_BASKET_NAV : &[u8] = b"fruitbasket_nav";
+pub const FRUIT_BASKET_VAULT : &[u8] = b"fruitbasket_vault";
+pub const FRUIT_BASKET_OPEN_ORDERS : &[u8] = b"fruitbasket_open_orders";
+pub const FRUIT_BASKET_IN_KIND : &[u8] = b"fruitbasket_in_kind";
+const DEFAULT_LEG_SLIPPAGE_BPS : u64 = 100;
+const MAX_LEG_SLIPPAGE_BPS : u64 = 1000;
+const MAX_FEE_BPS : u64 = 1000;
+const DEFAULT_MAX_CREATOR_FEE_BPS : u64 = 100;
+const MAX_QUOTE_CURRENCIES : usize = 4;
+const DEFAULT_MAX_PRICE_AGE_SLOTS : u64 = 50;
+const DEFAULT_MAX_CONFIDENCE_BPS : u64 = 1000;
+const MAX_CONFIDENCE_BPS : u64 = 2000;
+const MAX_DEVIATION_BPS : u64 = 5000;
+const DEFAULT_EMA_WINDOW_SLOTS : u64 = 150;
+// pause flags of groups and baskets, reverting contexts are never paused
+const PAUSE_NEW_CONTEXTS : u8 = 1;
+const PAUSE_PROCESSING : u8 = 2;
+const PAUSE_FINALIZE : u8 = 4;
+// solvency of a basket whose vaults hold exactly what its supply requires
+const FULLY_BACKED_BPS : u64 = 10000;
+// slots over which the chunks of an audit are accepted, vaults are not priced so the price age of the group does not apply
+const MAX_AUDIT_WINDOW_SLOTS : u64 = 150;
 mod empty {
     use super::*;
     declare_id!("HJt8Tjdsc9ms9i4WCZEzhzr4oyf3ANcdzXrNdLPFqm3M");
@@ -37,10 +63,78 @@ pub mod fruitbasket {
         processor::initialize_group(ctx, base_mint_name)
     }
 
+    pub fn set_leg_slippage(ctx: Context<ConfigureGroup>, slippage_bps : u64) -> ProgramResult {
+        processor::set_leg_slippage(ctx, slippage_bps)
+    }
+
+    pub fn set_fees(ctx: Context<ConfigureGroup>, mint_fee_bps : u64, redeem_fee_bps : u64) -> ProgramResult {
+        processor::set_fees(ctx, mint_fee_bps, redeem_fee_bps)
+    }
+
+    pub fn propose_owner(ctx: Context<ConfigureGroup>, new_owner : Pubkey) -> ProgramResult {
+        processor::propose_owner(ctx, new_owner)
+    }
+
+    pub fn accept_ownership(ctx: Context<AcceptOwnership>) -> ProgramResult {
+        processor::accept_ownership(ctx)
+    }
+
+    pub fn set_role(ctx: Context<ConfigureGroup>, role : GroupRole, member : Pubkey) -> ProgramResult {
+        processor::set_role(ctx, role, member)
+    }
+
+    pub fn set_group_pause(ctx: Context<ConfigureGroup>, pause_flags : u8) -> ProgramResult {
+        processor::set_group_pause(ctx, pause_flags)
+    }
+
+    pub fn set_basket_pause(ctx: Context<PauseBasket>, pause_flags : u8) -> ProgramResult {
+        processor::set_basket_pause(ctx, pause_flags)
+    }
+
+    pub fn set_token_pause(ctx: Context<PauseToken>, paused : bool) -> ProgramResult {
+        processor::set_token_pause(ctx, paused)
+    }
+
+    pub fn set_max_price_age(ctx: Context<ConfigureGroup>, max_price_age_slots : u64) -> ProgramResult {
+        processor::set_max_price_age(ctx, max_price_age_slots)
+    }
+
+    pub fn set_ema_config(ctx: Context<ConfigureGroup>, ema_window_slots : u64, ema_bound : bool) -> ProgramResult {
+        processor::set_ema_config(ctx, ema_window_slots, ema_bound)
+    }
+
+    pub fn set_max_creator_fee(ctx: Context<ConfigureGroup>, max_creator_fee_bps : u64) -> ProgramResult {
+        processor::set_max_creator_fee(ctx, max_creator_fee_bps)
+    }
+
+    pub fn create_fee_vault(ctx: Context<CreateFeeVault>, _bump : u8) -> ProgramResult {
+        processor::create_fee_vault(ctx)
+    }
+
+    pub fn withdraw_fees(ctx: Context<WithdrawFees>, amount : u64) -> ProgramResult {
+        processor::withdraw_fees(ctx, amount)
+    }
+
+    pub fn add_quote_currency(ctx: Context<AddQuoteCurrency>) -> ProgramResult {
+        processor::add_quote_currency(ctx)
+    }
+
+    pub fn update_quote_price(ctx: Context<UpdateQuotePrice>) -> ProgramResult {
+        processor::update_quote_price(ctx)
+    }
+
     pub fn add_token(ctx: Context<AddToken>, _bump : u8, name: String) -> ProgramResult {
         processor::add_token(ctx, name)
     }
 
+    pub fn configure_token(ctx: Context<ConfigureToken>, max_confidence_bps : u64, max_deviation_bps : u64) -> ProgramResult {
+        processor::configure_token(ctx, max_confidence_bps, max_deviation_bps)
+    }
+
+    pub fn add_token_market(ctx: Context<AddTokenMarket>) -> ProgramResult {
+        processor::add_token_market(ctx)
+    }
+
     // add basket
     pub fn add_basket(ctx : Context<AddBasket>, 
         basket_number : u64, 
@@ -48,8 +142,39 @@ pub mod fruitbasket {
         _basket_mint_bump : u8,
         basket_name : String, 
         basket_desc : String,
+        number_of_components : u8,
+        basket_components : Vec<BasketComponentDescription>,
+        creator_fee_bps : u64) -> ProgramResult {
+        processor::add_basket(ctx, basket_number, basket_name, basket_desc, number_of_components, basket_components, creator_fee_bps)
+    }
+
+    pub fn create_creator_fee_vault(ctx : Context<CreateCreatorFeeVault>, _bump : u8) -> ProgramResult {
+        processor::create_creator_fee_vault(ctx)
+    }
+
+    pub fn claim_creator_fees(ctx : Context<ClaimCreatorFees>) -> ProgramResult {
+        processor::claim_creator_fees(ctx)
+    }
+
+    pub fn add_basket_components(ctx : Context<AddBasketComponents>,
         basket_components : Vec<BasketComponentDescription>) -> ProgramResult {
-        processor::add_basket(ctx, basket_number, basket_name, basket_desc, basket_components)
+        processor::add_basket_components(ctx, basket_components)
+    }
+
+    pub fn create_basket_vault(ctx : Context<CreateBasketVault>, _bump : u8) -> ProgramResult {
+        processor::create_basket_vault(ctx)
+    }
+
+    pub fn view_basket_backing(ctx : Context<ViewBasketBacking>, first_component : u8) -> ProgramResult {
+        processor::view_basket_backing(ctx, first_component)
+    }
+
+    pub fn audit_basket(ctx : Context<AuditBasket>, first_component : u8) -> ProgramResult {
+        processor::audit_basket(ctx, first_component)
+    }
+
+    pub fn set_audit_auto_pause(ctx: Context<ConfigureGroup>, audit_auto_pause : bool) -> ProgramResult {
+        processor::set_audit_auto_pause(ctx, audit_auto_pause)
     }
 
     pub fn update_price(ctx : Context<UpdatePrice>) -> ProgramResult {
@@ -60,6 +185,18 @@ pub mod fruitbasket {
         processor::update_basket_price(ctx)
     }
 
+    pub fn create_basket_nav(ctx : Context<CreateBasketNav>, bump : u8) -> ProgramResult {
+        processor::create_basket_nav(ctx, bump)
+    }
+
+    pub fn create_basket_open_orders(ctx : Context<CreateBasketOpenOrders>, bump : u8) -> ProgramResult {
+        processor::create_basket_open_orders(ctx, bump)
+    }
+
+    pub fn update_basket_book_price(ctx : Context<UpdateBasketBookPrice>, amount : u64, first_component : u8) -> ProgramResult {
+        processor::update_basket_book_price(ctx, amount, first_component)
+    }
+
     pub fn init_trade_context(
         ctx: Context<InitTradeContext>,
         _order_id: u8, 
@@ -67,8 +204,9 @@ pub mod fruitbasket {
         side : ContextSide,
         amount : u64,
         max_buy_or_min_sell_price : u64,
+        order_type : ContextOrderType,
     ) -> ProgramResult {
-        processor::init_trade_context(ctx, side, amount, max_buy_or_min_sell_price)
+        processor::init_trade_context(ctx, side, amount, max_buy_or_min_sell_price, order_type)
     }
 
     pub fn process_token_for_context(ctx : Context<ProcessTokenOnContext>) -> ProgramResult {
@@ -81,7 +219,64 @@ pub mod fruitbasket {
         processor::finalize_context(ctx)
     }
 
+    pub fn init_swap_context(
+        ctx: Context<InitSwapContext>,
+        _order_id: u8,
+        _context_bump : u8,
+        amount : u64,
+        destination_amount : u64,
+        max_quote_in : u64,
+    ) -> ProgramResult {
+        processor::init_swap_context(ctx, amount, destination_amount, max_quote_in)
+    }
+
+    pub fn finalize_swap_context<'info>(
+        ctx : Context<'_, '_, '_, 'info, FinalizeSwapContext<'info>>
+    ) -> ProgramResult{
+        processor::finalize_swap_context(ctx)
+    }
+
+    pub fn init_rebalance_context(
+        ctx: Context<InitRebalanceContext>,
+        _order_id: u8,
+        _context_bump : u8,
+        new_components : Vec<BasketComponentDescription>,
+    ) -> ProgramResult {
+        processor::init_rebalance_context(ctx, new_components)
+    }
+
+    pub fn finalize_rebalance_context(
+        ctx : Context<FinalizeRebalanceContext>
+    ) -> ProgramResult{
+        processor::finalize_rebalance_context(ctx)
+    }
+
     pub fn revert_trade_context( ctx: Context<RevertTradeContext> ) -> ProgramResult {
         processor::revert_trade_context(ctx)
     }
+
+    pub fn cancel_trade_context( ctx: Context<CancelTradeContext> ) -> ProgramResult {
+        processor::cancel_trade_context(ctx)
+    }
+
+    pub fn init_in_kind_context(
+        ctx : Context<InitInKindContext>,
+        _context_bump : u8,
+        amount : u64,
+        redeem : bool,
+    ) -> ProgramResult {
+        processor::init_in_kind_context(ctx, amount, redeem)
+    }
+
+    pub fn process_in_kind<'info>(ctx : Context<'_, '_, '_, 'info, ProcessInKind<'info>>, first_component : u8) -> ProgramResult {
+        processor::process_in_kind(ctx, first_component)
+    }
+
+    pub fn finalize_in_kind_context(ctx : Context<FinalizeInKindContext>) -> ProgramResult {
+        processor::finalize_in_kind_context(ctx)
+    }
+
+    pub fn cancel_in_kind_context(ctx : Context<CancelInKindContext>) -> ProgramResult {
+        processor::cancel_in_kind_context(ctx)
+    }
 }
@@ -1,10 +1,10 @@
 use crate::*;
 use anchor_spl::dex::serum_dex::matching::{OrderType, Side};
-use std::{num::NonZeroU64};
+use std::{num::NonZeroU64, collections::BinaryHeap};
 use anchor_spl::dex::serum_dex::instruction::SelfTradeBehavior;
-use anchor_spl::dex::serum_dex::state::{ MarketState };
+use anchor_spl::dex::serum_dex::state::{ Market, MarketState, OpenOrders };
+use anchor_spl::dex::serum_dex::critbit::SlabView;
 use solana_program::sysvar::clock::Clock;
-use core::cell::RefMut;
 use fixed::types::I80F48;
 
 pub fn initialize_group(
@@ -26,6 +26,23 @@ pub fn initialize_group(
     group.number_of_baskets = 0;
     group.nb_users = 0;
     group.quote_token_transaction_pool = ctx.accounts.quote_token_transaction_pool.key();
+    group.leg_slippage_bps = DEFAULT_LEG_SLIPPAGE_BPS;
+    group.mint_fee_bps = 0;
+    group.redeem_fee_bps = 0;
+    group.max_creator_fee_bps = DEFAULT_MAX_CREATOR_FEE_BPS;
+    group.max_price_age_slots = DEFAULT_MAX_PRICE_AGE_SLOTS;
+    group.ema_window_slots = DEFAULT_EMA_WINDOW_SLOTS;
+    group.ema_bound = 0;
+    // base mint is the first quote currency, its price is one whole token
+    let base_decimal = ctx.accounts.quote_token_mint.decimals;
+    group.nb_quote_currencies = 1;
+    group.quote_currencies[0] = QuoteCurrency {
+        mint : group.base_mint,
+        transaction_pool : group.quote_token_transaction_pool,
+        price_oracle : Pubkey::default(),
+        price : 10u64.pow(base_decimal.into()),
+        decimal : base_decimal,
+    };
 
     //pre allocate programming addresses
     Pubkey::find_program_address(&[FRUIT_BASKET.as_ref(), &[0]], ctx.program_id);
@@ -39,6 +56,268 @@ pub fn initialize_group(
                     &ctx.accounts.owner, 
                     authority, &ctx.accounts.token_program, 
                     None)?;
+
+    emit!(GroupInitialized {
+        group : ctx.accounts.fruit_basket_grp.key(),
+        owner : group.owner,
+        base_mint : group.base_mint,
+    });
+    Ok(())
+}
+
+pub fn set_leg_slippage(ctx: Context<ConfigureGroup>, slippage_bps : u64) -> ProgramResult {
+    let mut group = ctx.accounts.group.load_mut()?;
+    if group.owner != ctx.accounts.owner.key() {
+        return Err(FruitBasketError::Unauthorized.into());
+    }
+    if slippage_bps > MAX_LEG_SLIPPAGE_BPS {
+        return Err(FruitBasketError::SlippageTooHigh.into());
+    }
+    group.leg_slippage_bps = slippage_bps;
+    Ok(())
+}
+
+pub fn set_fees(ctx: Context<ConfigureGroup>, mint_fee_bps : u64, redeem_fee_bps : u64) -> ProgramResult {
+    let mut group = ctx.accounts.group.load_mut()?;
+    if !group.has_role(&ctx.accounts.owner.key(), GroupRole::FeeManager) {
+        return Err(FruitBasketError::Unauthorized.into());
+    }
+    if mint_fee_bps > MAX_FEE_BPS || redeem_fee_bps > MAX_FEE_BPS {
+        return Err(FruitBasketError::FeeTooHigh.into());
+    }
+    group.mint_fee_bps = mint_fee_bps;
+    group.redeem_fee_bps = redeem_fee_bps;
+    Ok(())
+}
+
+pub fn propose_owner(ctx: Context<ConfigureGroup>, new_owner : Pubkey) -> ProgramResult {
+    let mut group = ctx.accounts.group.load_mut()?;
+    if group.owner != ctx.accounts.owner.key() {
+        return Err(FruitBasketError::Unauthorized.into());
+    }
+    if group.owner == new_owner {
+        return Err(FruitBasketError::AlreadyOwner.into());
+    }
+    // proposing the default key cancels the transfer
+    group.pending_owner = new_owner;
+
+    emit!(OwnershipProposed {
+        group : ctx.accounts.group.key(),
+        owner : group.owner,
+        pending_owner : new_owner,
+    });
+    Ok(())
+}
+
+pub fn accept_ownership(ctx: Context<AcceptOwnership>) -> ProgramResult {
+    let mut group = ctx.accounts.group.load_mut()?;
+    if group.pending_owner == Pubkey::default() || group.pending_owner != ctx.accounts.new_owner.key() {
+        return Err(FruitBasketError::Unauthorized.into());
+    }
+    let previous_owner = group.owner;
+    group.owner = group.pending_owner;
+    group.pending_owner = Pubkey::default();
+
+    emit!(OwnershipTransferred {
+        group : ctx.accounts.group.key(),
+        previous_owner,
+        owner : group.owner,
+    });
+    Ok(())
+}
+
+pub fn set_role(ctx: Context<ConfigureGroup>, role : GroupRole, member : Pubkey) -> ProgramResult {
+    let mut group = ctx.accounts.group.load_mut()?;
+    if group.owner != ctx.accounts.owner.key() {
+        return Err(FruitBasketError::Unauthorized.into());
+    }
+    // setting the default key gives back the role to the owner
+    match role {
+        GroupRole::TokenLister => group.token_lister = member,
+        GroupRole::BasketCurator => group.basket_curator = member,
+        GroupRole::Pauser => group.pauser = member,
+        GroupRole::FeeManager => group.fee_manager = member,
+    }
+
+    emit!(RoleSet {
+        group : ctx.accounts.group.key(),
+        role,
+        member,
+    });
+    Ok(())
+}
+
+pub fn set_group_pause(ctx: Context<ConfigureGroup>, pause_flags : u8) -> ProgramResult {
+    let mut group = ctx.accounts.group.load_mut()?;
+    if !group.has_role(&ctx.accounts.owner.key(), GroupRole::Pauser) {
+        return Err(FruitBasketError::Unauthorized.into());
+    }
+    group.pause_flags = pause_flags;
+
+    emit!(PauseSet {
+        group : ctx.accounts.group.key(),
+        target : ctx.accounts.group.key(),
+        pause_flags,
+    });
+    Ok(())
+}
+
+pub fn set_basket_pause(ctx: Context<PauseBasket>, pause_flags : u8) -> ProgramResult {
+    let group = ctx.accounts.group.load()?;
+    if !group.has_role(&ctx.accounts.pauser.key(), GroupRole::Pauser) {
+        return Err(FruitBasketError::Unauthorized.into());
+    }
+    ctx.accounts.basket.pause_flags = pause_flags;
+
+    emit!(PauseSet {
+        group : ctx.accounts.group.key(),
+        target : ctx.accounts.basket.key(),
+        pause_flags,
+    });
+    Ok(())
+}
+
+pub fn set_token_pause(ctx: Context<PauseToken>, paused : bool) -> ProgramResult {
+    let group = ctx.accounts.group.load()?;
+    if !group.has_role(&ctx.accounts.pauser.key(), GroupRole::Pauser) {
+        return Err(FruitBasketError::Unauthorized.into());
+    }
+    ctx.accounts.token_desc.paused = paused as u8;
+
+    emit!(PauseSet {
+        group : ctx.accounts.group.key(),
+        target : ctx.accounts.token_desc.key(),
+        pause_flags : paused as u8,
+    });
+    Ok(())
+}
+
+pub fn set_max_price_age(ctx: Context<ConfigureGroup>, max_price_age_slots : u64) -> ProgramResult {
+    let mut group = ctx.accounts.group.load_mut()?;
+    if group.owner != ctx.accounts.owner.key() {
+        return Err(FruitBasketError::Unauthorized.into());
+    }
+    group.max_price_age_slots = max_price_age_slots;
+    Ok(())
+}
+
+pub fn set_ema_config(ctx: Context<ConfigureGroup>, ema_window_slots : u64, ema_bound : bool) -> ProgramResult {
+    let mut group = ctx.accounts.group.load_mut()?;
+    if group.owner != ctx.accounts.owner.key() {
+        return Err(FruitBasketError::Unauthorized.into());
+    }
+    if ema_window_slots == 0 {
+        return Err(FruitBasketError::InvalidEmaWindow.into());
+    }
+    group.ema_window_slots = ema_window_slots;
+    group.ema_bound = ema_bound as u8;
+    Ok(())
+}
+
+pub fn set_max_creator_fee(ctx: Context<ConfigureGroup>, max_creator_fee_bps : u64) -> ProgramResult {
+    let mut group = ctx.accounts.group.load_mut()?;
+    if !group.has_role(&ctx.accounts.owner.key(), GroupRole::FeeManager) {
+        return Err(FruitBasketError::Unauthorized.into());
+    }
+    if max_creator_fee_bps > MAX_FEE_BPS {
+        return Err(FruitBasketError::FeeTooHigh.into());
+    }
+    group.max_creator_fee_bps = max_creator_fee_bps;
+    Ok(())
+}
+
+pub fn create_fee_vault(ctx: Context<CreateFeeVault>) -> ProgramResult {
+    let group = ctx.accounts.group.load()?;
+    if !group.has_role(&ctx.accounts.owner.key(), GroupRole::FeeManager) {
+        return Err(FruitBasketError::Unauthorized.into());
+    }
+    let (authority, _bump) = Pubkey::find_program_address(&[FRUIT_BASKET_AUTHORITY], ctx.program_id);
+    if authority != ctx.accounts.fruit_basket_authority.key() {
+        return Err(FruitBasketError::UnknownAuthority.into());
+    }
+    Ok(())
+}
+
+pub fn withdraw_fees(ctx: Context<WithdrawFees>, amount : u64) -> ProgramResult {
+    let group = ctx.accounts.group.load()?;
+    if !group.has_role(&ctx.accounts.owner.key(), GroupRole::FeeManager) {
+        return Err(FruitBasketError::Unauthorized.into());
+    }
+    let quote_token_mint = ctx.accounts.fee_vault.mint;
+    if fee_vault_address(&ctx.accounts.group.key(), &quote_token_mint, ctx.program_id) != ctx.accounts.fee_vault.key() {
+        return Err(FruitBasketError::UnknownFeeVault.into());
+    }
+    let (authority, bump) = Pubkey::find_program_address(&[FRUIT_BASKET_AUTHORITY], ctx.program_id);
+    if authority != ctx.accounts.fruit_basket_authority.key() {
+        return Err(FruitBasketError::UnknownAuthority.into());
+    }
+    let seeds = [&FRUIT_BASKET_AUTHORITY[..], &[bump]];
+    let signer = &[&seeds[..]];
+    let accounts = token::Transfer {
+        from: ctx.accounts.fee_vault.to_account_info(),
+        to: ctx.accounts.destination.to_account_info(),
+        authority: ctx.accounts.fruit_basket_authority.clone(),
+    };
+    let transfer_ctx = CpiContext::new_with_signer(ctx.accounts.token_program.clone(), accounts, signer);
+    token::transfer( transfer_ctx, amount)?;
+
+    emit!(FeesWithdrawn {
+        group : ctx.accounts.group.key(),
+        quote_token_mint,
+        destination : ctx.accounts.destination.key(),
+        amount,
+    });
+    Ok(())
+}
+
+pub fn add_quote_currency(ctx: Context<AddQuoteCurrency>) -> ProgramResult {
+    let mut group = ctx.accounts.group.load_mut()?;
+    if group.owner != ctx.accounts.owner.key() {
+        return Err(FruitBasketError::Unauthorized.into());
+    }
+    let quote_token_mint = ctx.accounts.quote_token_mint.key();
+    if group.quote_currency(&quote_token_mint).is_ok() {
+        return Err(FruitBasketError::QuoteCurrencyAlreadyRegistered.into());
+    }
+    let nb_quote_currencies = group.nb_quote_currencies as usize;
+    if nb_quote_currencies >= MAX_QUOTE_CURRENCIES {
+        return Err(FruitBasketError::QuoteCurrencyLimitReached.into());
+    }
+    // quote currency is not priced until update_quote_price is called
+    group.quote_currencies[nb_quote_currencies] = QuoteCurrency {
+        mint : quote_token_mint,
+        transaction_pool : ctx.accounts.quote_token_transaction_pool.key(),
+        price_oracle : ctx.accounts.price_oracle.key(),
+        price : 0,
+        decimal : ctx.accounts.quote_token_mint.decimals,
+    };
+    group.nb_quote_currencies += 1;
+
+    let (authority, _bump) = Pubkey::find_program_address(&[FRUIT_BASKET_AUTHORITY], ctx.program_id);
+    // change authority of the pool fruitbasket authority
+    change_authority(&ctx.accounts.quote_token_transaction_pool.to_account_info(), 
+                    &ctx.accounts.owner, 
+                    authority, &ctx.accounts.token_program, 
+                    None)?;
+    Ok(())
+}
+
+pub fn update_quote_price(ctx: Context<UpdateQuotePrice>) -> ProgramResult {
+    let mut group = ctx.accounts.group.load_mut()?;
+    let oracle_key = ctx.accounts.oracle_ai.key();
+    let nb_quote_currencies = group.nb_quote_currencies as usize;
+    // base mint has no oracle
+    let position = group.quote_currencies[1..nb_quote_currencies].iter()
+                        .position(|x| x.price_oracle == oracle_key)
+                        .ok_or(FruitBasketError::UnknownQuoteCurrency)?;
+    let cache = read_oracle(&ctx.accounts.oracle_ai, group.max_price_age_slots, DEFAULT_MAX_CONFIDENCE_BPS)?;
+    group.quote_currencies[position + 1].price = cache.price_in_quote_native();
+
+    emit!(QuotePriceUpdated {
+        group : ctx.accounts.group.key(),
+        quote_token_mint : group.quote_currencies[position + 1].mint,
+        price : group.quote_currencies[position + 1].price,
+    });
     Ok(())
 }
 
@@ -47,9 +326,13 @@ pub fn add_token(ctx: Context<AddToken>, name: String) -> ProgramResult {
         return Err(FruitBasketError::NameBufferOverflow.into());
     }
     let mut group = ctx.accounts.fruit_basket_grp.load_mut()?;
+    if !group.has_role(&ctx.accounts.owner.key(), GroupRole::TokenLister) {
+        return Err(FruitBasketError::Unauthorized.into());
+    }
     let token_description = &mut ctx.accounts.token_desc;
     token_description.magic = TOKEN_DESC_MAGIC;
     token_description.id = group.token_count;
+    token_description.group = ctx.accounts.fruit_basket_grp.key();
 
     token_description.token_mint = *ctx.accounts.mint.to_account_info().key;
     token_description.price_oracle = *ctx.accounts.price_oracle.key;
@@ -72,8 +355,18 @@ pub fn add_token(ctx: Context<AddToken>, name: String) -> ProgramResult {
     }
     token_description.token_pool = *ctx.accounts.token_pool.to_account_info().key;
     token_description.token_decimal = ctx.accounts.mint.decimals;
+    token_description.max_confidence_bps = DEFAULT_MAX_CONFIDENCE_BPS;
+    token_description.max_deviation_bps = 0;
     
     group.token_count += 1;
+
+    emit!(TokenAdded {
+        group : ctx.accounts.fruit_basket_grp.key(),
+        token_desc : token_description.key(),
+        token_mint : token_description.token_mint,
+        price_oracle : token_description.price_oracle,
+        market : ctx.accounts.market.key(),
+    });
     if ctx.accounts.market.key() == empty::ID {
         return Ok(());
     }
@@ -94,35 +387,123 @@ pub fn add_token(ctx: Context<AddToken>, name: String) -> ProgramResult {
     Ok(())
 }
 
+pub fn configure_token(ctx: Context<ConfigureToken>, max_confidence_bps : u64, max_deviation_bps : u64) -> ProgramResult {
+    let group = ctx.accounts.group.load()?;
+    if !group.has_role(&ctx.accounts.owner.key(), GroupRole::TokenLister) {
+        return Err(FruitBasketError::Unauthorized.into());
+    }
+    // a confidence threshold of 0 rejects every price, a deviation threshold of 0 disables the check
+    if max_confidence_bps == 0 || max_confidence_bps > MAX_CONFIDENCE_BPS {
+        return Err(FruitBasketError::ConfidenceThresholdOutOfRange.into());
+    }
+    if max_deviation_bps > MAX_DEVIATION_BPS {
+        return Err(FruitBasketError::DeviationTooHigh.into());
+    }
+    let token_description = &mut ctx.accounts.token_desc;
+    token_description.max_confidence_bps = max_confidence_bps;
+    token_description.max_deviation_bps = max_deviation_bps;
+    Ok(())
+}
+
+pub fn add_token_market(ctx: Context<AddTokenMarket>) -> ProgramResult {
+    let group = ctx.accounts.group.load()?;
+    if !group.has_role(&ctx.accounts.owner.key(), GroupRole::TokenLister) {
+        return Err(FruitBasketError::Unauthorized.into());
+    }
+    let quote_token_mint = ctx.accounts.quote_token_mint.key();
+    group.quote_currency(&quote_token_mint)?;
+    let token_description = &mut ctx.accounts.token_desc;
+    // base mint market is added with the token
+    if token_description.market_for(&quote_token_mint, &group.base_mint).is_some() {
+        return Err(FruitBasketError::QuoteCurrencyAlreadyRegistered.into());
+    }
+    let position = token_description.quote_markets.iter()
+                        .position(|x| x.quote_mint == Pubkey::default())
+                        .ok_or(FruitBasketError::QuoteCurrencyLimitReached)?;
+    // legs are routed to this market for the quote currency, it should trade the token for it
+    {
+        let market_state = MarketState::load(&ctx.accounts.market, ctx.accounts.dex_program.key)?;
+        if market_mint(market_state.coin_mint) != token_description.token_mint
+            || market_mint(market_state.pc_mint) != quote_token_mint {
+            return Err(FruitBasketError::MarketMintMismatch.into());
+        }
+    }
+
+    let (authority, bump) = Pubkey::find_program_address(&[FRUIT_BASKET_AUTHORITY], ctx.program_id);
+    if authority != ctx.accounts.fruitbasket_authority.key() {
+        return Err(FruitBasketError::UnknownAuthority.into());
+    }
+    let seeds = &[&FRUIT_BASKET_AUTHORITY[..], &[bump]];
+    //create and assign open order
+    let open_order_instruction = dex::InitOpenOrders {
+        open_orders: ctx.accounts.open_orders_account.to_account_info().clone(),
+        authority: ctx.accounts.fruitbasket_authority.clone(),
+        market: ctx.accounts.market.clone(),
+        rent: ctx.accounts.rent.clone(),
+    };
+    let oo_ctx = CpiContext::new(ctx.accounts.dex_program.clone(), open_order_instruction);
+    dex::init_open_orders(oo_ctx.with_signer(&[seeds]))?;
+
+    token_description.quote_markets[position] = QuoteMarket {
+        quote_mint : quote_token_mint,
+        market : ctx.accounts.market.key(),
+        open_orders : ctx.accounts.open_orders_account.key(),
+    };
+    Ok(())
+}
+
+// serum keeps the mints of a market as little endian words
+fn market_mint(words : [u64; 4]) -> Pubkey {
+    let mut bytes = [0u8; 32];
+    for (chunk, word) in bytes.chunks_mut(8).zip(words.iter()) {
+        chunk.copy_from_slice(&word.to_le_bytes());
+    }
+    Pubkey::new_from_array(bytes)
+}
+
 pub fn add_basket(
     ctx: Context<AddBasket>,
     basket_number: u64,
     basket_name: String,
     basket_desc: String,
+    number_of_components: u8,
     basket_components: Vec<BasketComponentDescription>,
+    creator_fee_bps: u64,
 ) -> ProgramResult {
-    if basket_components.len() >= MAX_NB_COMPONENTS {
+    // basket and its trade contexts are created by the program so they are limited in size
+    let nb_components = number_of_components as usize;
+    if 8 + Basket::space(nb_components) > MAX_PERMITTED_DATA_INCREASE 
+        || 8 + BasketTradeContext::space(nb_components) > MAX_PERMITTED_DATA_INCREASE
+        || basket_components.len() > nb_components {
         return Err(FruitBasketError::ComponentCountOverflow.into());
     }
-    if basket_components.len() < 2 {
+    if number_of_components < 2 {
         return Err(FruitBasketError::ComponentCountUnderflow.into());
     }
     let mut group = ctx.accounts.group.load_mut()?;
+    // baskets can be created by anyone until a curator is set
+    if group.basket_curator != Pubkey::default() && !group.has_role(&ctx.accounts.client.key(), GroupRole::BasketCurator) {
+        return Err(FruitBasketError::Unauthorized.into());
+    }
     if group.number_of_baskets != (basket_number as u64) {
         return Err(FruitBasketError::BasketNbMismatch.into());
     }
+    if creator_fee_bps > group.max_creator_fee_bps {
+        return Err(FruitBasketError::CreatorFeeTooHigh.into());
+    }
+    check_new_components(&[], &basket_components, &ctx.accounts.group.key(), ctx.remaining_accounts, ctx.program_id)?;
 
     let basket = &mut ctx.accounts.basket;
     basket.magic = BASKET_DESC_MAGIC;
     basket.basket_name[..basket_name.len()].copy_from_slice(basket_name[..].as_bytes());
     basket.desc[..basket_desc.len()].copy_from_slice(basket_desc[..].as_bytes());
-    basket.number_of_components = basket_components.len() as u8;
+    basket.number_of_components = number_of_components;
+    basket.creator = ctx.accounts.client.key();
+    basket.group = ctx.accounts.group.key();
+    basket.creator_fee_bps = creator_fee_bps;
     basket.basket_mint = *ctx.accounts.basket_mint.to_account_info().key;
-
-    for i in 0..basket_components.len() {
-        let component = basket_components[i];
-        basket.components[i] = component;
-    }
+    basket.component_prices = vec![ComponentPrice::default(); basket_components.len()];
+    basket.components = basket_components;
 
     let (authority, _bump) =
         Pubkey::find_program_address(&[FRUIT_BASKET_AUTHORITY], ctx.program_id);
@@ -139,50 +520,545 @@ pub fn add_basket(
     }
 
     group.number_of_baskets += 1;
+
+    emit!(BasketCreated {
+        group : basket.group,
+        basket : basket.key(),
+        basket_mint : basket.basket_mint,
+        creator : basket.creator,
+        number_of_components,
+        components : basket.components.clone(),
+        creator_fee_bps,
+    });
+    Ok(())
+}
+
+pub fn add_basket_components(
+    ctx: Context<AddBasketComponents>,
+    basket_components: Vec<BasketComponentDescription>,
+) -> ProgramResult {
+    let basket = &mut ctx.accounts.basket;
+    if basket.components.len() + basket_components.len() > basket.number_of_components as usize {
+        return Err(FruitBasketError::ComponentCountOverflow.into());
+    }
+    check_new_components(&basket.components, &basket_components, &basket.group, ctx.remaining_accounts, ctx.program_id)?;
+    for component in basket_components.iter() {
+        basket.components.push(*component);
+        basket.component_prices.push(ComponentPrice::default());
+    }
+
+    emit!(BasketComponentsAdded {
+        basket : basket.key(),
+        components : basket_components,
+    });
+    Ok(())
+}
+
+// components are tokens listed in the group of the basket, and a token backs a single component as legs are matched by mint
+// and components share the vault of their token, token descriptions of the new components are given in the same order
+fn check_new_components(components : &[BasketComponentDescription], new_components : &[BasketComponentDescription], group : &Pubkey,
+                        token_desc_accounts : &[AccountInfo], program_id : &Pubkey) -> ProgramResult {
+    if token_desc_accounts.len() != new_components.len() {
+        return Err(FruitBasketError::AccountsMismatch.into());
+    }
+    for (index, (component, token_desc_ai)) in new_components.iter().zip(token_desc_accounts.iter()).enumerate() {
+        if components.iter().chain(new_components[..index].iter()).any(|x| x.token_mint == component.token_mint) {
+            return Err(FruitBasketError::DuplicateComponent.into());
+        }
+        // token descriptions are trusted only if they are owned by the program
+        if token_desc_ai.owner != program_id || token_desc_address(&component.token_mint, program_id) != token_desc_ai.key() {
+            return Err(FruitBasketError::ComponentNotListed.into());
+        }
+        let token_desc = deserialize_token_descs(std::slice::from_ref(token_desc_ai))?;
+        if token_desc[0].group != *group {
+            return Err(FruitBasketError::ComponentNotListed.into());
+        }
+    }
+    Ok(())
+}
+
+pub fn create_creator_fee_vault(ctx: Context<CreateCreatorFeeVault>) -> ProgramResult {
+    let (authority, _bump) = Pubkey::find_program_address(&[FRUIT_BASKET_AUTHORITY], ctx.program_id);
+    if authority != ctx.accounts.fruit_basket_authority.key() {
+        return Err(FruitBasketError::UnknownAuthority.into());
+    }
+    Ok(())
+}
+
+pub fn claim_creator_fees(ctx: Context<ClaimCreatorFees>) -> ProgramResult {
+    let quote_token_mint = ctx.accounts.creator_fee_vault.mint;
+    if creator_fee_vault_address(&ctx.accounts.basket.key(), &quote_token_mint, ctx.program_id) != ctx.accounts.creator_fee_vault.key() {
+        return Err(FruitBasketError::UnknownFeeVault.into());
+    }
+    let (authority, bump) = Pubkey::find_program_address(&[FRUIT_BASKET_AUTHORITY], ctx.program_id);
+    if authority != ctx.accounts.fruit_basket_authority.key() {
+        return Err(FruitBasketError::UnknownAuthority.into());
+    }
+    let amount = ctx.accounts.creator_fee_vault.amount;
+    if amount == 0 {
+        return Ok(());
+    }
+    let seeds = [&FRUIT_BASKET_AUTHORITY[..], &[bump]];
+    let signer = &[&seeds[..]];
+    let accounts = token::Transfer {
+        from: ctx.accounts.creator_fee_vault.to_account_info(),
+        to: ctx.accounts.destination.to_account_info(),
+        authority: ctx.accounts.fruit_basket_authority.clone(),
+    };
+    let transfer_ctx = CpiContext::new_with_signer(ctx.accounts.token_program.clone(), accounts, signer);
+    token::transfer( transfer_ctx, amount)?;
+
+    emit!(CreatorFeesClaimed {
+        basket : ctx.accounts.basket.key(),
+        creator : ctx.accounts.creator.key(),
+        quote_token_mint,
+        destination : ctx.accounts.destination.key(),
+        amount,
+    });
+    Ok(())
+}
+
+pub fn create_basket_vault(ctx: Context<CreateBasketVault>) -> ProgramResult {
+    let (authority, _bump) = Pubkey::find_program_address(&[FRUIT_BASKET_AUTHORITY], ctx.program_id);
+    if authority != ctx.accounts.fruit_basket_authority.key() {
+        return Err(FruitBasketError::UnknownAuthority.into());
+    }
+    Ok(())
+}
+
+pub fn view_basket_backing(ctx: Context<ViewBasketBacking>, first_component : u8) -> ProgramResult {
+    let basket = &ctx.accounts.basket;
+    let supply = ctx.accounts.basket_token_mint.supply;
+    let first_component = first_component as usize;
+    let (vault_amounts, required_amounts, _) = basket_backing(basket, first_component, supply, ctx.remaining_accounts, ctx.program_id)?;
+    emit!(BasketBacking {
+        basket : basket.key(),
+        supply,
+        token_mints : basket.components[first_component..first_component + vault_amounts.len()].iter().map(|x| x.token_mint).collect(),
+        vault_amounts,
+        required_amounts,
+    });
+    Ok(())
+}
+
+pub fn audit_basket(ctx: Context<AuditBasket>, first_component : u8) -> ProgramResult {
+    let group = ctx.accounts.group.load()?;
+    let basket = &mut ctx.accounts.basket;
+    let supply = ctx.accounts.basket_token_mint.supply;
+    let first_component = first_component as usize;
+    let (vault_amounts, required_amounts, _) = basket_backing(basket, first_component, supply, ctx.remaining_accounts, ctx.program_id)?;
+    let clock = Clock::get()?;
+    if first_component == 0 {
+        // an audit from the first component drops the pending audit
+        basket.audit_pending_supply = supply;
+        basket.audit_pending_slot = clock.slot;
+        basket.audit_pending_solvency_bps = u64::MAX;
+    } else if first_component != basket.audit_cursor as usize
+                || supply != basket.audit_pending_supply
+                || clock.slot.saturating_sub(basket.audit_pending_slot) > MAX_AUDIT_WINDOW_SLOTS {
+        // the pending audit is continued while the supply is unchanged and its first components were audited recently
+        return Err(FruitBasketError::AuditMismatch.into());
+    }
+    // a basket is as solvent as its least backed component
+    let solvency_bps = vault_amounts.iter().zip(required_amounts.iter())
+                        .filter(|(_, required)| **required > 0)
+                        .map(|(vault, required)| (*vault as u128)
+                                .checked_mul(FULLY_BACKED_BPS.into()).unwrap()
+                                .checked_div(*required as u128).unwrap()
+                                .min(u64::MAX.into()) as u64)
+                        .min()
+                        .unwrap_or(u64::MAX);
+    basket.audit_pending_solvency_bps = basket.audit_pending_solvency_bps.min(solvency_bps);
+    basket.audit_cursor = (first_component + vault_amounts.len()) as u8;
+    if basket.audit_cursor as usize != basket.components.len() {
+        return Ok(());
+    }
+
+    // no component requires any backing when the supply is 0
+    let solvency_bps = if basket.audit_pending_solvency_bps == u64::MAX { FULLY_BACKED_BPS } else { basket.audit_pending_solvency_bps };
+    basket.solvency_bps = solvency_bps;
+    basket.audit_timestamp = clock.unix_timestamp;
+    basket.audit_cursor = 0;
+    // no more basket tokens can be created until the backing is restored, holders can still exit
+    let paused = group.audit_auto_pause == 1 && solvency_bps < FULLY_BACKED_BPS;
+    if paused {
+        basket.pause_flags |= PAUSE_NEW_CONTEXTS;
+    }
+    emit!(BasketAudited {
+        basket : basket.key(),
+        supply,
+        solvency_bps,
+        timestamp : clock.unix_timestamp,
+        paused,
+    });
+    Ok(())
+}
+
+pub fn set_audit_auto_pause(ctx: Context<ConfigureGroup>, audit_auto_pause : bool) -> ProgramResult {
+    let mut group = ctx.accounts.group.load_mut()?;
+    if !group.has_role(&ctx.accounts.owner.key(), GroupRole::Pauser) {
+        return Err(FruitBasketError::Unauthorized.into());
+    }
+    group.audit_auto_pause = audit_auto_pause as u8;
     Ok(())
 }
 
+// balance of the vault, backing required by the supply and token decimals for components of a basket
+// remaining accounts are (token description, basket vault) for each component from first_component in basket order
+fn basket_backing(basket : &Account<Basket>, first_component : usize, supply : u64, remaining_accounts : &[AccountInfo], program_id : &Pubkey) 
+    -> std::result::Result<(Vec<u64>, Vec<u64>, Vec<u8>), ProgramError> {
+    let nb_components = remaining_accounts.len() / 2;
+    if nb_components == 0 || remaining_accounts.len() % 2 != 0 || first_component + nb_components > basket.components.len() {
+        return Err(FruitBasketError::AccountsMismatch.into());
+    }
+    let mut vault_amounts = Vec::with_capacity(nb_components);
+    let mut required_amounts = Vec::with_capacity(nb_components);
+    let mut token_decimals = Vec::with_capacity(nb_components);
+    for (component, accounts) in basket.components[first_component..].iter().zip(remaining_accounts.chunks(2)) {
+        let token_desc_ai = &accounts[0];
+        let basket_vault = &accounts[1];
+        // token descriptions are trusted only if they are owned by the program
+        if token_desc_ai.owner != program_id {
+            return Err(FruitBasketError::ErrorDeserializeTokeDesc.into());
+        }
+        let token_desc = deserialize_token_descs(std::slice::from_ref(token_desc_ai))?.remove(0);
+        if token_desc.token_mint != component.token_mint
+            || basket_vault_address(&basket.key(), &component.token_mint, program_id) != basket_vault.key() {
+            return Err(FruitBasketError::UnknownBasketVault.into());
+        }
+        vault_amounts.push(token::accessor::amount(basket_vault)?);
+        required_amounts.push(component_native_amount(supply, component, token_desc.token_decimal, true));
+        token_decimals.push(token_desc.token_decimal);
+    }
+    Ok((vault_amounts, required_amounts, token_decimals))
+}
+
 pub fn update_price(ctx: Context<UpdatePrice>) -> ProgramResult {
-    let oracle_data = ctx.accounts.oracle_ai.try_borrow_data()?;
+    let group = ctx.accounts.group.load()?;
+    let token_desc = &mut ctx.accounts.token_desc;
+    let mut cache = read_oracle(&ctx.accounts.oracle_ai, group.max_price_age_slots, token_desc.max_confidence_bps)?;
+    // price should not jump too far from the cached price
+    let previous_price = token_desc.cache.price_in_quote_native();
+    if token_desc.max_deviation_bps > 0 && previous_price > 0 {
+        let price = cache.price_in_quote_native();
+        let deviation = if price > previous_price { price - previous_price } else { previous_price - price };
+        if deviation > bps_of(previous_price, token_desc.max_deviation_bps) {
+            return Err(FruitBasketError::PriceDeviationTooHigh.into());
+        }
+    }
+    let elapsed_slots = cache.last_update_slot.saturating_sub(token_desc.cache.last_update_slot);
+    cache.ema_price = ema_step(token_desc.cache.ema_price, cache.price_in_quote_native(), elapsed_slots, group.ema_window_slots);
+    token_desc.cache = cache;
+
+    emit!(PriceUpdated {
+        token_desc : token_desc.key(),
+        token_mint : token_desc.token_mint,
+        price : cache.last_price,
+        confidence : cache.last_confidence,
+        exp : cache.last_exp,
+        publish_slot : cache.publish_slot,
+        ema_price : cache.ema_price,
+    });
+    Ok(())
+}
+
+// read the price of a pyth oracle, halted and stale prices are rejected
+fn read_oracle(oracle_ai : &AccountInfo, max_price_age_slots : u64, max_confidence_bps : u64) -> std::result::Result<Cache, ProgramError> {
+    let oracle_data = oracle_ai.try_borrow_data()?;
     let oracle = pyth_client::cast::<Price>(&oracle_data);
+    if !matches!(oracle.agg.status, PriceStatus::Trading) {
+        return Err(FruitBasketError::OracleNotTrading.into());
+    }
+    let clock = Clock::get()?;
+    if clock.slot.saturating_sub(oracle.agg.pub_slot) > max_price_age_slots {
+        return Err(FruitBasketError::StalePrice.into());
+    }
     if oracle.agg.price <= 0 {
         return Err(FruitBasketError::PriceEqualOrLessThanZero.into());
     }
     
-    let threshold : u64 = oracle.agg.price.checked_div(10).unwrap() as u64; // confidence should be within 10%
+    let threshold : u64 = bps_of(oracle.agg.price as u64, max_confidence_bps);
     if oracle.agg.conf > threshold {
         return Err(FruitBasketError::LowConfidenceInOracle.into());
     } 
-    ctx.accounts.token_desc.cache.last_price = oracle.agg.price as u64;
-    ctx.accounts.token_desc.cache.last_confidence = oracle.agg.conf;
-    ctx.accounts.token_desc.cache.last_exp = oracle.expo;
-    Ok(())
+    Ok(Cache {
+        last_price : oracle.agg.price as u64,
+        last_confidence : oracle.agg.conf,
+        last_exp : oracle.expo,
+        publish_slot : oracle.agg.pub_slot,
+        last_update_slot : clock.slot,
+        last_update_timestamp : clock.unix_timestamp,
+        ema_price : 0,
+    })
 }
 
 pub fn update_basket_price(ctx : Context<UpdateBasketPrice>) -> ProgramResult{
     let basket = &mut ctx.accounts.basket;
-    // deserialize remaining accounts for the basket tokens
-    let token_descs_deserailized = ctx.remaining_accounts.iter().map( |x| {
-        let account_data = &x.try_borrow_data()?;
-        let mut account_data_slice: &[u8] = &account_data;
-        TokenDescription::try_deserialize_unchecked(&mut account_data_slice)
-
-    } ).collect::<Vec<_>>();
-
-    let res_error = token_descs_deserailized.iter().any(|x| x.is_err());
-    if res_error {
+    // token descriptions are trusted only if they are owned by the program
+    if ctx.remaining_accounts.iter().any(|x| x.owner != ctx.program_id) {
         return Err(FruitBasketError::ErrorDeserializeTokeDesc.into());
     }
-    let token_descs = token_descs_deserailized.iter().map(|x| x.as_ref().ok().unwrap()).collect::<Vec<_>>();
-    
-    let wrong_magic = token_descs.iter().any(|x| x.magic != TOKEN_DESC_MAGIC);
-    if wrong_magic {
-        return Err(FruitBasketError::ErrorDeserializeTokeDesc.into());
+    // deserialize remaining accounts for the basket tokens
+    let token_descs_deserailized = deserialize_token_descs(ctx.remaining_accounts)?;
+    // each description should be the one listed for its mint
+    let wrong_desc = ctx.remaining_accounts.iter().zip(token_descs_deserailized.iter())
+                        .any(|(token_desc_ai, token_desc)| token_desc_address(&token_desc.token_mint, ctx.program_id) != token_desc_ai.key());
+    if wrong_desc {
+        return Err(FruitBasketError::AccountsMismatch.into());
     }
+    let token_descs = token_descs_deserailized.iter().collect::<Vec<_>>();
     msg!("deserialization done");
-    basket.update_price(&token_descs)?;
-    Ok(())
-}
+    let group = ctx.accounts.group.load()?;
+    let clock = Clock::get()?;
+    basket.update_price(&token_descs, clock.slot, group.max_price_age_slots)?;
+    let is_priced = basket.component_prices.iter().all(|x| x.price > 0);
+    if is_priced {
+        basket.ema_price = ema_step(basket.ema_price, basket.last_price, clock.slot.saturating_sub(basket.ema_slot), group.ema_window_slots);
+        basket.ema_slot = clock.slot;
+    }
+
+    // publish the basket price once all the components are priced
+    let basket_nav = &ctx.accounts.basket_nav;
+    if basket_nav_address(&basket.key(), ctx.program_id).0 != basket_nav.key() {
+        return Err(FruitBasketError::AccountsMismatch.into());
+    }
+    if basket_nav.owner == ctx.program_id && is_priced {
+        let mut nav_data = basket_nav.try_borrow_mut_data()?;
+        let nav = nav_price_mut(&mut nav_data)?;
+        nav.prev_slot = nav.valid_slot;
+        nav.prev_price = nav.agg.price;
+        nav.prev_conf = nav.agg.conf;
+        nav.expo = -(basket.decimal as i32);
+        nav.last_slot = clock.slot;
+        nav.valid_slot = clock.slot;
+        nav.agg.price = basket.last_price as i64;
+        nav.agg.conf = basket.confidence;
+        nav.agg.status = PriceStatus::Trading;
+        // the basket price is as old as its oldest component price
+        nav.agg.pub_slot = basket.price_slot;
+    }
+
+    emit!(BasketPriceUpdated {
+        basket : basket.key(),
+        price : basket.last_price,
+        confidence : basket.confidence,
+        price_slot : basket.price_slot,
+        ema_price : basket.ema_price,
+        is_priced,
+    });
+    Ok(())
+}
+
+pub fn create_basket_nav(ctx : Context<CreateBasketNav>, bump : u8) -> ProgramResult {
+    let basket_key = ctx.accounts.basket.key();
+    let basket_nav = &ctx.accounts.basket_nav;
+    if basket_nav_address(&basket_key, ctx.program_id) != (basket_nav.key(), bump) {
+        return Err(FruitBasketError::AccountsMismatch.into());
+    }
+    let space = size_of::<Price>();
+    let lamports = Rent::get()?.minimum_balance(space);
+    let basket_bytes = basket_key.to_bytes();
+    let seeds = [FRUIT_BASKET_NAV, &basket_bytes[..], &[bump]];
+    solana_program::program::invoke_signed(
+        &solana_program::system_instruction::create_account(ctx.accounts.payer.key, basket_nav.key, lamports, space as u64, ctx.program_id),
+        &[ctx.accounts.payer.clone(), basket_nav.clone(), ctx.accounts.system_program.to_account_info()],
+        &[&seeds[..]],
+    )?;
+
+    // header of a pyth price account, price is not valid until the basket is priced
+    let mut nav_data = basket_nav.try_borrow_mut_data()?;
+    let nav = nav_price_mut(&mut nav_data)?;
+    nav.magic = pyth_client::MAGIC;
+    nav.ver = pyth_client::VERSION_2;
+    nav.atype = AccountType::Price as u32;
+    nav.size = space as u32;
+    nav.ptype = PriceType::Price;
+    nav.expo = -6;
+    nav.agg.status = PriceStatus::Unknown;
+    nav.agg.corp_act = CorpAction::NoCorpAct;
+    Ok(())
+}
+
+pub fn create_basket_open_orders(ctx : Context<CreateBasketOpenOrders>, bump : u8) -> ProgramResult {
+    let basket_key = ctx.accounts.basket.key();
+    let market_key = ctx.accounts.market.key();
+    let open_orders = &ctx.accounts.open_orders;
+    if basket_open_orders_address(&basket_key, &market_key, ctx.program_id) != (open_orders.key(), bump) {
+        return Err(FruitBasketError::UnknownOpenOrders.into());
+    }
+    // the address can only be created once, it should not be owned by another program
+    if ctx.accounts.dex_program.key() != dex::ID {
+        return Err(FruitBasketError::AccountsMismatch.into());
+    }
+    let (authority, authority_bump) = Pubkey::find_program_address(&[FRUIT_BASKET_AUTHORITY], ctx.program_id);
+    if authority != ctx.accounts.fruitbasket_authority.key() {
+        return Err(FruitBasketError::UnknownAuthority.into());
+    }
+    // serum pads its accounts with 5 bytes before and 7 bytes after the open orders
+    let space = size_of::<OpenOrders>() + 12;
+    let lamports = Rent::get()?.minimum_balance(space);
+    let basket_bytes = basket_key.to_bytes();
+    let market_bytes = market_key.to_bytes();
+    let seeds = [FRUIT_BASKET_OPEN_ORDERS, &basket_bytes[..], &market_bytes[..], &[bump]];
+    solana_program::program::invoke_signed(
+        &solana_program::system_instruction::create_account(ctx.accounts.payer.key, open_orders.key, lamports, space as u64, &dex::ID),
+        &[ctx.accounts.payer.clone(), open_orders.clone(), ctx.accounts.system_program.to_account_info()],
+        &[&seeds[..]],
+    )?;
+
+    let authority_seeds = &[&FRUIT_BASKET_AUTHORITY[..], &[authority_bump]];
+    let open_order_instruction = dex::InitOpenOrders {
+        open_orders: open_orders.clone(),
+        authority: ctx.accounts.fruitbasket_authority.clone(),
+        market: ctx.accounts.market.clone(),
+        rent: ctx.accounts.rent.clone(),
+    };
+    let oo_ctx = CpiContext::new(ctx.accounts.dex_program.clone(), open_order_instruction);
+    dex::init_open_orders(oo_ctx.with_signer(&[authority_seeds]))
+}
+
+pub fn update_basket_book_price(ctx : Context<UpdateBasketBookPrice>, amount : u64, first_component : u8) -> ProgramResult {
+    let group = ctx.accounts.group.load()?;
+    let basket = &mut ctx.accounts.basket;
+    if amount == 0 {
+        return Err(FruitBasketError::InvalidAmount.into());
+    }
+    if !basket.is_complete() {
+        return Err(FruitBasketError::BasketIncomplete.into());
+    }
+    let remaining_accounts = ctx.remaining_accounts;
+    let first_component = first_component as usize;
+    let nb_walked = remaining_accounts.len() / 4;
+    if nb_walked == 0 || remaining_accounts.len() % 4 != 0 || first_component + nb_walked > basket.components.len() {
+        return Err(FruitBasketError::AccountsMismatch.into());
+    }
+    let slot = Clock::get()?.slot;
+    if first_component == 0 {
+        // a walk from the first component drops the pending walk
+        basket.book_pending_amount = amount;
+        basket.book_pending_slot = slot;
+        basket.book_pending_buy_cost = 0;
+        basket.book_pending_sell_proceeds = 0;
+    } else if first_component != basket.book_cursor as usize
+                || amount != basket.book_pending_amount
+                || slot.saturating_sub(basket.book_pending_slot) > group.max_price_age_slots {
+        // the pending walk is continued for the same amount while its first components are fresh
+        return Err(FruitBasketError::BookWalkMismatch.into());
+    }
+    let dex_program_id = ctx.accounts.dex_program.key;
+    for (offset, accounts) in remaining_accounts.chunks(4).enumerate() {
+        let component = basket.components[first_component + offset];
+        let token_desc_ai = &accounts[0];
+        let market = &accounts[1];
+        let bids = &accounts[2];
+        let asks = &accounts[3];
+        if token_desc_ai.owner != ctx.program_id {
+            return Err(FruitBasketError::ErrorDeserializeTokeDesc.into());
+        }
+        let token_desc = deserialize_token_descs(std::slice::from_ref(token_desc_ai))?.remove(0);
+        if token_desc.token_mint != component.token_mint {
+            return Err(FruitBasketError::AccountsMismatch.into());
+        }
+        if token_desc.market != market.key() {
+            return Err(FruitBasketError::UnknownMarket.into());
+        }
+        // buying rounds the component up so that a component smaller than a lot is never priced at zero
+        let buy_amount = component_native_amount(amount, &component, token_desc.token_decimal, true);
+        let sell_amount = component_native_amount(amount, &component, token_desc.token_decimal, false);
+        let buy_cost = book_quote_amount(market, bids, asks, dex_program_id, Side::Ask, buy_amount)?;
+        let sell_proceeds = book_quote_amount(market, bids, asks, dex_program_id, Side::Bid, sell_amount)?;
+        basket.book_pending_buy_cost = basket.book_pending_buy_cost.checked_add(buy_cost).unwrap();
+        basket.book_pending_sell_proceeds = basket.book_pending_sell_proceeds.checked_add(sell_proceeds).unwrap();
+    }
+    basket.book_cursor = (first_component + nb_walked) as u8;
+    if basket.book_cursor as usize != basket.components.len() {
+        return Ok(());
+    }
+
+    // prices are given for a whole basket, buy price is rounded up and sell price rounded down
+    let unit = 10u128.pow(basket.decimal.into());
+    let buy_cost = basket.book_pending_buy_cost as u128;
+    let sell_proceeds = basket.book_pending_sell_proceeds as u128;
+    basket.book_buy_price = buy_cost.checked_mul(unit).unwrap().checked_add(amount as u128 - 1).unwrap().checked_div(amount.into()).unwrap() as u64;
+    basket.book_sell_price = sell_proceeds.checked_mul(unit).unwrap().checked_div(amount.into()).unwrap() as u64;
+    basket.book_price_amount = amount;
+    // prices are as old as the first walked component
+    basket.book_price_slot = basket.book_pending_slot;
+    basket.book_cursor = 0;
+
+    emit!(BasketBookPriceUpdated {
+        basket : basket.key(),
+        buy_price : basket.book_buy_price,
+        sell_price : basket.book_sell_price,
+        amount,
+    });
+    Ok(())
+}
+
+// quote native amount to take a token amount from one side of the orderbook
+// asks are walked from the lowest price to buy, bids from the highest price to sell
+// serum fees are not taken into account
+fn book_quote_amount(market : &AccountInfo, 
+                     bids : &AccountInfo, 
+                     asks : &AccountInfo, 
+                     dex_program_id : &Pubkey, 
+                     side : Side, 
+                     token_amount : u64) -> std::result::Result<u64, ProgramError> {
+    let market_state = MarketState::load(market, dex_program_id)?;
+    let coin_lot_size = market_state.coin_lot_size;
+    let pc_lot_size = market_state.pc_lot_size;
+    let slab = if side == Side::Bid { market_state.load_bids_mut(bids)? } else { market_state.load_asks_mut(asks)? };
+    // orders are kept in a heap with the best price on top, only the levels taken are popped
+    let mut orders = (0..slab.capacity() as u32)
+                        .filter_map(|handle| slab.get(handle).and_then(|node| node.as_leaf()))
+                        .map(|leaf| {
+                            let price = leaf.price().get();
+                            let rank = if side == Side::Bid { price } else { u64::MAX - price };
+                            (rank, price, leaf.quantity())
+                        })
+                        .collect::<BinaryHeap<_>>();
+    // legs are traded in whole coin lots, a part of a lot is bought as a whole lot and not sold
+    let mut lots_left = if side == Side::Ask {
+        token_amount.checked_add(coin_lot_size - 1).unwrap().checked_div(coin_lot_size).unwrap()
+    } else {
+        token_amount.checked_div(coin_lot_size).unwrap()
+    };
+    let mut pc_lots : u128 = 0;
+    while lots_left > 0 {
+        let (_, price, quantity) = match orders.pop() {
+            Some(order) => order,
+            None => break,
+        };
+        let lots = lots_left.min(quantity);
+        pc_lots += (lots as u128).checked_mul(price.into()).unwrap();
+        lots_left -= lots;
+    }
+    if lots_left > 0 {
+        return Err(FruitBasketError::InsufficientBookDepth.into());
+    }
+    Ok(pc_lots.checked_mul(pc_lot_size.into()).unwrap() as u64)
+}
+
+fn deserialize_token_descs(accounts : &[AccountInfo]) -> std::result::Result<Vec<TokenDescription>, ProgramError> {
+    let token_descs_deserailized = accounts.iter().map( |x| {
+        let account_data = &x.try_borrow_data()?;
+        let mut account_data_slice: &[u8] = &account_data;
+        TokenDescription::try_deserialize_unchecked(&mut account_data_slice)
+
+    } ).collect::<Vec<_>>();
+
+    let res_error = token_descs_deserailized.iter().any(|x| x.is_err());
+    if res_error {
+        return Err(FruitBasketError::ErrorDeserializeTokeDesc.into());
+    }
+    let token_descs = token_descs_deserailized.into_iter().map(|x| x.ok().unwrap()).collect::<Vec<_>>();
+    
+    let wrong_magic = token_descs.iter().any(|x| x.magic != TOKEN_DESC_MAGIC);
+    if wrong_magic {
+        return Err(FruitBasketError::ErrorDeserializeTokeDesc.into());
+    }
+    Ok(token_descs)
+}
 
 
 pub fn init_trade_context(
@@ -190,40 +1066,105 @@ pub fn init_trade_context(
     side: ContextSide,
     amount : u64,
     max_buy_or_min_sell_price : u64,
+    order_type : ContextOrderType,
 ) -> ProgramResult {
     
     let group = ctx.accounts.group.load()?;
-    let basket = &ctx.accounts.basket;
-    let mut trade_context = ctx.accounts.trade_context.load_init()?;
+    let basket = &mut ctx.accounts.basket;
+    let trade_context = &mut ctx.accounts.trade_context;
     let is_buy_side = side == ContextSide::Buy;
+    let is_limit_order = order_type == ContextOrderType::Limit;
+    // swap and rebalance contexts are created with their own instructions
+    if side == ContextSide::Swap || side == ContextSide::Rebalance {
+        return Err(FruitBasketError::InvalidContextSide.into());
+    }
+    check_not_paused(&group, basket, PAUSE_NEW_CONTEXTS)?;
+    if !basket.is_complete() {
+        return Err(FruitBasketError::BasketIncomplete.into());
+    }
+    if basket.is_rebalancing() {
+        return Err(FruitBasketError::BasketRebalancing.into());
+    }
+    // a basket is priced once all its components have a cached price
+    if basket.last_price == 0 {
+        return Err(FruitBasketError::BasketNotPriced.into());
+    }
+    // basket price and component prices should be recent
+    let clock = Clock::get()?;
+    if clock.slot.saturating_sub(basket.price_slot) > group.max_price_age_slots {
+        return Err(FruitBasketError::StalePrice.into());
+    }
+    // prices given by the user are in the quote currency of their choice
+    let quote = group.quote_currency(&ctx.accounts.quote_token_mint.key())?;
+    if ctx.accounts.quote_token_transaction_pool.key() != quote.transaction_pool {
+        return Err(FruitBasketError::AccountsMismatch.into());
+    }
+    let basket_price = quote.in_quote_native(basket.last_price)?;
+    let basket_confidence = quote.in_quote_native(basket.confidence)?;
+    // market contexts are sized from the orderbooks when they were recently walked for at least this amount
+    let book_is_fresh = !is_limit_order 
+                            && basket.book_price_amount >= amount
+                            && clock.slot.saturating_sub(basket.book_price_slot) <= group.max_price_age_slots;
+    let book_price = if book_is_fresh {
+        Some(quote.in_quote_native(if is_buy_side { basket.book_buy_price } else { basket.book_sell_price })?)
+    } else {
+        None
+    };
 
-    // price after taking into account the confidence
-    let possible_last_basket_price : u64 = 
-        if is_buy_side { 
-            basket.last_price.checked_add( basket.confidence ).unwrap()
+    // price after taking into account the confidence, or executable price on the orderbooks
+    let mut possible_last_basket_price : u64 = match book_price {
+        Some(price) => price,
+        None if is_buy_side => basket_price.checked_add( basket_confidence ).unwrap(),
+        None => basket_price.checked_sub( basket_confidence ).unwrap(),
+    };
+    // a single price update cannot move the bound past the moving average
+    if group.ema_bound == 1 && basket.ema_price > 0 {
+        let ema_price = quote.in_quote_native(basket.ema_price)?;
+        possible_last_basket_price = if is_buy_side {
+            possible_last_basket_price.max(ema_price) 
         } else {
-            basket.last_price.checked_sub( basket.confidence ).unwrap()
+            possible_last_basket_price.min(ema_price) 
         };
+    }
     // we assume that the max worst case price is 10 percent of the actual price
+    // an executable price only needs the slippage band of the legs
+    let padding = match book_price {
+        Some(price) => bps_of(price, group.leg_slippage_bps),
+        None => possible_last_basket_price.checked_div(10).unwrap(),
+    };
+    // prices are given for a whole basket
     let mut worst_case_price = 
         if is_buy_side {
-            possible_last_basket_price.checked_add(possible_last_basket_price.checked_div(10).unwrap()).unwrap()
+            possible_last_basket_price.checked_add(padding).unwrap()
         } else {
-            possible_last_basket_price.checked_sub(possible_last_basket_price.checked_div(10).unwrap()).unwrap()
+            possible_last_basket_price.checked_sub(padding).unwrap()
         };
+    if is_limit_order {
+        // limit orders rest on the orderbook so the limit price is not bounded by the current basket price
+        if max_buy_or_min_sell_price == 0 || basket_price == 0 {
+            return Err(FruitBasketError::InvalidLimitPrice.into());
+        }
+        worst_case_price = max_buy_or_min_sell_price;
+    }
+    let mut worst_case_quote : u64 = 0;
+    let mut fee_reserve : u64 = 0;
     // maximum allowed price should be greater than current basket price plus the confidence of the price.
     // TODO update this check by taking into account spread in orderbook so there are far less transactions to be reverted.
     if is_buy_side {
-        if max_buy_or_min_sell_price < possible_last_basket_price {
-            return Err(FruitBasketError::TooLowMaximumBuyPrice.into());
+        if !is_limit_order {
+            if max_buy_or_min_sell_price < possible_last_basket_price {
+                return Err(FruitBasketError::TooLowMaximumBuyPrice.into());
+            }
+            // largest maximum price allowed is 10% of possible_last_basket_price
+            worst_case_price = if max_buy_or_min_sell_price > worst_case_price { worst_case_price } else { max_buy_or_min_sell_price };
         }
-        // largest maximum price allowed is 10% of possible_last_basket_price
-        worst_case_price = if max_buy_or_min_sell_price > worst_case_price { worst_case_price } else { max_buy_or_min_sell_price };
+        // quote deposited for the amount of basket tokens bought at the worst case price
+        worst_case_quote = (worst_case_price as u128)
+                                .checked_mul(amount.into()).unwrap()
+                                .checked_div(10u128.pow(basket.decimal.into())).unwrap() as u64;
+        // fees are taken on the quote spent, at most the worst case quote, and are deposited on top of it
+        fee_reserve = bps_of(worst_case_quote, group.mint_fee_bps.checked_add(basket.creator_fee_bps).unwrap());
 
-        if ctx.accounts.quote_token_transaction_pool.key() != group.quote_token_transaction_pool {
-            return Err(FruitBasketError::AccountsMismatch.into());
-        }
-        
 
         // transfer usdc from client to pool account
         let accounts = token::Transfer {
@@ -232,11 +1173,11 @@ pub fn init_trade_context(
             authority: ctx.accounts.user.to_account_info(),
         };
         let transfer_ctx = CpiContext::new(ctx.accounts.token_program.clone(), accounts);
-        token::transfer( transfer_ctx, worst_case_price)?;
+        token::transfer( transfer_ctx, worst_case_quote.checked_add(fee_reserve).unwrap())?;
     }
     else {
         // burn the tokens which user wants to sell.
-        if max_buy_or_min_sell_price > possible_last_basket_price {
+        if !is_limit_order && max_buy_or_min_sell_price > possible_last_basket_price {
             return Err(FruitBasketError::TooHighMinimumSellPrice.into());
         }
         let (authority, bump) = Pubkey::find_program_address(&[FRUIT_BASKET_AUTHORITY], ctx.program_id);
@@ -261,15 +1202,38 @@ pub fn init_trade_context(
     trade_context.side = side;
     trade_context.basket = basket.key();
     trade_context.reverting = 0;
-    trade_context.usdc_amount_left = if is_buy_side { worst_case_price } else { 0 };
+    trade_context.usdc_amount_left = if is_buy_side { worst_case_quote.checked_add(fee_reserve).unwrap() } else { 0 };
     trade_context.amount = amount;
     trade_context.quote_token_account = ctx.accounts.quote_token_account.key();
     trade_context.basket_token_account = ctx.accounts.basket_token_account.key();
+    trade_context.user = ctx.accounts.user.key();
+    trade_context.quote_token_mint = quote.mint;
     trade_context.initial_usdc_transfer_amount = trade_context.usdc_amount_left;
-    trade_context.tokens_treated = [0; MAX_NB_COMPONENTS];
+    trade_context.fee_reserve = fee_reserve;
+    trade_context.order_type = order_type;
+    trade_context.limit_price = if is_limit_order { max_buy_or_min_sell_price } else { 0 };
+
+    let number_of_components = basket.components.len();
+    trade_context.token_mints = vec![Pubkey::default(); number_of_components];
+    trade_context.token_amounts = vec![0; number_of_components];
+    trade_context.initial_token_amounts = vec![0; number_of_components];
+    trade_context.tokens_treated = vec![0; number_of_components];
+    trade_context.leg_limit_prices = vec![0; number_of_components];
+    trade_context.leg_order_quantities = vec![0; number_of_components];
+    trade_context.leg_filled_quantities = vec![0; number_of_components];
+    trade_context.leg_sides = vec![side; number_of_components];
+    trade_context.leg_transfer_amounts = vec![0; number_of_components];
+    trade_context.quote_spent = 0;
+    trade_context.quote_received = 0;
+    trade_context.destination_basket = Pubkey::default();
+    trade_context.destination_basket_token_account = Pubkey::default();
+    trade_context.destination_amount = 0;
+    trade_context.mint_fee_bps = group.mint_fee_bps;
+    trade_context.redeem_fee_bps = group.redeem_fee_bps;
+    trade_context.creator_fee_bps = basket.creator_fee_bps;
+    trade_context.destination_creator_fee_bps = 0;
 
-    for component_index in 0..basket.number_of_components {
-        let position = component_index as usize;
+    for position in 0..number_of_components {
         let component : &BasketComponentDescription = &basket.components[position]; 
         trade_context.token_mints[position] = component.token_mint;
         // check if we found the token mint in our token list
@@ -282,36 +1246,126 @@ pub fn init_trade_context(
         
         trade_context.token_amounts[position] = amount_of_tokens as u64;   
         trade_context.initial_token_amounts[position] = trade_context.token_amounts[position];
+
+        if is_limit_order {
+            // every leg is priced at the cached component price scaled by the ratio between limit and basket price
+            let component_price = quote.in_quote_native(basket.component_prices[position].price)?;
+            trade_context.leg_limit_prices[position] = (component_price as u128)
+                                            .checked_mul(max_buy_or_min_sell_price.into()).unwrap()
+                                            .checked_div(basket_price.into()).unwrap() as u64;
+        }
     }
     // set a timestamp on the context.
-    let clock = Clock::get()?;
     trade_context.created_on = clock.unix_timestamp as u64;
+    // the basket cannot be rebalanced until its contexts are finalized
+    basket.open_contexts = basket.open_contexts.checked_add(1).unwrap();
+
+    emit_context_created(trade_context);
     Ok(())
 }
 
 pub fn process_token_for_context(ctx : Context<ProcessTokenOnContext>) -> ProgramResult {
+    // the leg is processed on a copy of the context as the serum helpers borrow all the accounts
+    let mut trade_context = (**ctx.accounts.trade_context).clone();
+    let token_mint = ctx.accounts.token_mint.key();
+    let pending_leg = trade_context.token_mints.iter().position(|x| *x == token_mint)
+                        .filter(|token_index| trade_context.tokens_treated[*token_index] == 0);
+    let token_index = match pending_leg {
+        Some(token_index) => token_index,
+        None => return Ok(()),
+    };
+    let quote_token_transaction_pool = ctx.accounts.quote_token_transaction_pool.to_account_info();
+    let tokens_before = token::accessor::amount(&ctx.accounts.token_pool)?;
+    let quote_before = token::accessor::amount(&quote_token_transaction_pool)?;
+    process_token(&ctx, &mut trade_context)?;
+    let tokens_after = token::accessor::amount(&ctx.accounts.token_pool)?;
+    let quote_after = token::accessor::amount(&quote_token_transaction_pool)?;
+
+    emit!(LegProcessed {
+        trade_context : ctx.accounts.trade_context.key(),
+        token_mint,
+        is_buy : tokens_after > tokens_before,
+        reverting : trade_context.reverting == 1,
+        quantity : if tokens_after > tokens_before { tokens_after - tokens_before } else { tokens_before - tokens_after },
+        quote_amount : if quote_after > quote_before { quote_after - quote_before } else { quote_before - quote_after },
+        remaining : trade_context.token_amounts[token_index],
+        treated : trade_context.tokens_treated[token_index] == 1,
+    });
+    **ctx.accounts.trade_context = trade_context;
+    Ok(())
+}
+
+fn process_token(ctx : &Context<ProcessTokenOnContext>, trade_context : &mut BasketTradeContext) -> ProgramResult {
     let fruitbasket = &ctx.accounts.fruitbasket;
-    let _component_in_basket = fruitbasket.components.iter().position(|x| x.token_mint == ctx.accounts.token_mint.key());
-    // check if token is component of the basket
+    // legs of the context are the components of the basket, or of both baskets for a swap
+    let _component_in_basket = trade_context.token_mints.iter().position(|x| *x == ctx.accounts.token_mint.key());
+    // check if token is a leg of the context
     if _component_in_basket == None 
     {
         return Ok(());
     }
     let token_index = _component_in_basket.unwrap();
     let token_desc = &ctx.accounts.token_desc;
-    let mut trade_context = ctx.accounts.trade_context.load_mut()?;
 
     // check if token is already treated
     if trade_context.tokens_treated[token_index] == 1 {
         return Ok(());
     }
-    let is_buy_side = ( trade_context.side == ContextSide::Buy && trade_context.reverting == 0) // check if buy while not reverting
-                            || ( trade_context.side == ContextSide::Sell && trade_context.reverting == 1); // check if sell if reverting
+    let leg_side = trade_context.leg_sides[token_index];
+    let is_buy_side = ( leg_side == ContextSide::Buy && trade_context.reverting == 0) // check if buy while not reverting
+                            || ( leg_side == ContextSide::Sell && trade_context.reverting == 1); // check if sell if reverting
+
+    // buy legs of a swap or a rebalance are paid with the quote received by its sell legs
+    let is_netted = trade_context.side == ContextSide::Swap || trade_context.side == ContextSide::Rebalance;
+    if is_netted && trade_context.reverting == 0 && is_buy_side {
+        let sell_leg_pending = trade_context.leg_sides.iter().zip(trade_context.tokens_treated.iter())
+                                    .any(|(side, treated)| *side == ContextSide::Sell && *treated == 0);
+        if sell_leg_pending {
+            return Err(FruitBasketError::SwapSellLegsPending.into());
+        }
+    }
 
     // checks to verify if we are in right context
     if fruitbasket.key() != trade_context.basket {
         return Err( FruitBasketError::UnknownBasket.into() );
     }
+    // a rebalancing basket only trades the legs of its rebalance context
+    if fruitbasket.is_rebalancing() && fruitbasket.rebalance_context != ctx.accounts.trade_context.key() {
+        return Err( FruitBasketError::BasketRebalancing.into() );
+    }
+    // legs are traded on the markets of the quote currency of the context
+    let group = ctx.accounts.group.load()?;
+    if ctx.accounts.quote_token_mint.key() != trade_context.quote_token_mint {
+        return Err( FruitBasketError::AccountsMismatch.into() );
+    }
+    let quote = group.quote_currency(&trade_context.quote_token_mint)?;
+    if ctx.accounts.quote_token_transaction_pool.key() != quote.transaction_pool {
+        return Err( FruitBasketError::AccountsMismatch.into() );
+    }
+    // reverting contexts are always processed so that users get their funds back
+    if trade_context.reverting == 0 {
+        check_not_paused(&group, fruitbasket, PAUSE_PROCESSING)?;
+        if token_desc.paused == 1 {
+            return Err( FruitBasketError::Paused.into() );
+        }
+    }
+    let (market, _) = token_desc.market_for(&quote.mint, &group.base_mint).ok_or(FruitBasketError::UnknownMarket)?;
+    if ctx.accounts.market.key() != market {
+        return Err( FruitBasketError::UnknownMarket.into() );
+    }
+    // tokens of a leg are kept in the vault of the basket they back
+    let leg_basket = if trade_context.side == ContextSide::Swap && leg_side == ContextSide::Buy {
+        trade_context.destination_basket
+    } else {
+        trade_context.basket
+    };
+    if ctx.accounts.token_pool.key() != basket_vault_address(&leg_basket, &token_desc.token_mint, ctx.program_id) {
+        return Err( FruitBasketError::UnknownBasketVault.into() );
+    }
+    // each basket trades with its own open orders, settled tokens can only go to its vault
+    if ctx.accounts.open_orders.key() != basket_open_orders_address(&leg_basket, &market, ctx.program_id).0 {
+        return Err( FruitBasketError::UnknownOpenOrders.into() );
+    }
 
     // get authority bump and verify authority
     let (pda, bump) =
@@ -321,6 +1375,24 @@ pub fn process_token_for_context(ctx : Context<ProcessTokenOnContext>) -> Progra
     }
 
     let seeds = &[&FRUIT_BASKET_AUTHORITY[..], &[bump]];
+    let (lot_size, pc_lot_size) = {
+        let market_state = MarketState::load(&ctx.accounts.market, ctx.accounts.dex_program.key)?;
+        (market_state.coin_lot_size, market_state.pc_lot_size)
+    };
+
+    if trade_context.order_type == ContextOrderType::Limit {
+        if trade_context.reverting == 0 {
+            return ctx.accounts.process_limit_leg(trade_context, token_index, lot_size, pc_lot_size, &[seeds]);
+        }
+        if trade_context.leg_order_quantities[token_index] > 0 {
+            // cancel the resting order, the filled part is reverted below with a market order
+            ctx.accounts.cancel_limit_leg(trade_context, token_index, lot_size, pc_lot_size, &[seeds])?;
+            if native_token_amount(trade_context.token_amounts[token_index], token_desc.token_decimal) < lot_size {
+                trade_context.tokens_treated[token_index] = 1;
+                return Ok(());
+            }
+        }
+    }
     // set side
     let side : Side = if is_buy_side { Side::Bid } else { Side::Ask };
     // create new order
@@ -328,59 +1400,79 @@ pub fn process_token_for_context(ctx : Context<ProcessTokenOnContext>) -> Progra
     let token_pool = &ctx.accounts.token_pool.to_account_info();
 
     // recalculate amount by taking token decimals under consideration
-    let token_amount = if token_desc.token_decimal != 6 { 
-                                trade_context.token_amounts[token_index]
-                                    .checked_mul(10u64.pow(token_desc.token_decimal.into())).unwrap()
-                                    .checked_div(10u64.pow(6)).unwrap() 
-                            } else {
-                                trade_context.token_amounts[token_index]
-                            };
-    let (lot_size ,max_coin_qty ) = {
-        let market_state = MarketState::load(&ctx.accounts.market, ctx.accounts.dex_program.key)?;
-        (market_state.coin_lot_size, token_amount.checked_div(market_state.coin_lot_size).unwrap())
+    let token_amount = native_token_amount(trade_context.token_amounts[token_index], token_desc.token_decimal);
+    let max_coin_qty = token_amount.checked_div(lot_size).unwrap();
+    // a leg smaller than one lot cannot be traded on serum
+    if max_coin_qty == 0 {
+        trade_context.tokens_treated[token_index] = 1;
+        return Ok(());
+    }
+    // the fee reserve is kept for finalize
+    let max_native_token = if is_buy_side {trade_context.usdc_amount_left.saturating_sub(trade_context.fee_reserve)} else {u64::MAX};
+    // legs are bounded by the oracle price and the slippage band of the group
+    // reverts are not bounded so that user funds can always exit
+    let is_bounded = trade_context.reverting == 0;
+    let limit_price = if is_bounded {
+        let leg_price = quote.in_quote_native(token_desc.cache.price_with_slippage(is_buy_side, group.leg_slippage_bps))?;
+        leg_price_in_lots(leg_price, token_desc.token_decimal, lot_size, pc_lot_size, is_buy_side).max(1)
+    } else if is_buy_side {
+        u64::MAX 
+    } else {
+        1 
     };
-    let max_native_token = if is_buy_side {trade_context.usdc_amount_left} else {u64::MAX};
 
+    // free funds left in the open orders by the resting orders of the basket are not part of this leg
+    ctx.accounts.settle_accounts(&[seeds])?;
     // get value before transaction
     let value_before_transaction = token::accessor::amount(quote_token_transaction_pool)?;
     let tokens_before_transaction = token::accessor::amount(token_pool)?;
     // Create a new order on serum
-    ctx.accounts.create_new_order(side, max_coin_qty, max_native_token, &[seeds])?;
+    ctx.accounts.create_new_order(side, limit_price, max_coin_qty, max_native_token, &[seeds])?;
     // settle order on serum
     ctx.accounts.settle_accounts(&[seeds])?;
 
     let value_after_transaction = token::accessor::amount(quote_token_transaction_pool)?;
     let tokens_after_transaction = token::accessor::amount(token_pool)?;
     // check how many tokens were really transfered. If all tokens were not transfered we have to redo the process
+    let tokens_transfered = if is_buy_side {
+        tokens_after_transaction.checked_sub(tokens_before_transaction)
+    } else {
+        tokens_before_transaction.checked_sub(tokens_after_transaction)
+    }.ok_or(FruitBasketError::InvalidLegAmount)?;
+    let tokens_left = token_amount.checked_sub(tokens_transfered).ok_or(FruitBasketError::InvalidLegAmount)?;
+    trade_context.token_amounts[token_index] = context_token_amount(tokens_left, token_desc.token_decimal);
+    if tokens_left < lot_size {
+        trade_context.tokens_treated[token_index] = 1;
+    }
+    // a leg which could not be completely filled within the band fails instead of overpaying
+    if is_bounded && trade_context.tokens_treated[token_index] == 0 {
+        return Err(FruitBasketError::LegPriceOutOfBand.into());
+    }
     if is_buy_side {
-        let tokens_transfered = tokens_after_transaction.checked_sub(tokens_before_transaction).unwrap();
-        trade_context.token_amounts[token_index] = token_amount.checked_sub(tokens_transfered).unwrap();
-        if trade_context.token_amounts[token_index] < lot_size {
-            trade_context.tokens_treated[token_index] = 1;
+        let quote_spent = value_before_transaction.checked_sub(value_after_transaction).ok_or(FruitBasketError::InvalidLegAmount)?;
+        trade_context.usdc_amount_left = trade_context.usdc_amount_left.checked_sub(quote_spent).ok_or(FruitBasketError::InvalidLegAmount)?;
+        if trade_context.reverting == 0 {
+            trade_context.quote_spent = trade_context.quote_spent.checked_add(quote_spent).unwrap();
         }
-    }
-    else {
-        let tokens_transfered = tokens_before_transaction.checked_sub(tokens_after_transaction).unwrap();
-        trade_context.token_amounts[token_index] = token_amount.checked_sub(tokens_transfered).unwrap();
-        if trade_context.token_amounts[token_index] < lot_size {
-            trade_context.tokens_treated[token_index] = 1;
+    } else {
+        let quote_received = value_after_transaction.checked_sub(value_before_transaction).ok_or(FruitBasketError::InvalidLegAmount)?;
+        trade_context.usdc_amount_left = trade_context.usdc_amount_left.checked_add(quote_received).unwrap();
+        if trade_context.reverting == 0 {
+            trade_context.quote_received = trade_context.quote_received.checked_add(quote_received).unwrap();
         }
     }
-    trade_context.usdc_amount_left = if is_buy_side {
-        trade_context.usdc_amount_left.checked_sub(value_before_transaction.checked_sub(value_after_transaction).unwrap()).unwrap()
-    } else {
-        trade_context.usdc_amount_left.checked_add(value_after_transaction.checked_sub(value_before_transaction).unwrap()).unwrap()
-    };
     Ok(())
 }
 
 pub fn finalize_context(ctx : Context<FinalizeContext>) -> ProgramResult {
-    let trade_context = ctx.accounts.trade_context.load_mut()?;
+    let trade_context = &ctx.accounts.trade_context;
+    // swap and rebalance contexts are finalized with their own instructions
+    if trade_context.side == ContextSide::Swap || trade_context.side == ContextSide::Rebalance {
+        return Err(FruitBasketError::InvalidContextSide.into());
+    }
     // check if all tokens are treated
-    for i in 0..ctx.accounts.fruitbasket.number_of_components {
-        if trade_context.tokens_treated[i as usize] != 1 {
-            return Err(FruitBasketError::NotAllTokensTreatedBeforeFinalize.into());
-        }
+    if trade_context.tokens_treated.iter().any(|x| *x != 1) {
+        return Err(FruitBasketError::NotAllTokensTreatedBeforeFinalize.into());
     }
     // some more checks
     if trade_context.basket != ctx.accounts.fruitbasket.key() {
@@ -396,10 +1488,41 @@ pub fn finalize_context(ctx : Context<FinalizeContext>) -> ProgramResult {
     }
     let seeds = [&FRUIT_BASKET_AUTHORITY[..], &[bump]];
     let signer = &[&seeds[..]];
+    let group = ctx.accounts.fruitbasket_group.load()?;
+    if ctx.accounts.fruitbasket.group != ctx.accounts.fruitbasket_group.key() 
+        || ctx.accounts.quote_token_mint.key() != trade_context.quote_token_mint
+        || ctx.accounts.quote_token_transaction_pool.key() != group.quote_currency(&trade_context.quote_token_mint)?.transaction_pool {
+        return Err(FruitBasketError::AccountsMismatch.into());
+    }
+    if ctx.accounts.fruitbasket.is_rebalancing() {
+        return Err(FruitBasketError::BasketRebalancing.into());
+    }
+    ctx.accounts.fruitbasket.open_contexts = ctx.accounts.fruitbasket.open_contexts.saturating_sub(1);
 
     if trade_context.reverting == 1 {
         return finalize_for_revert_context(&ctx, trade_context, signer);
     }
+    check_not_paused(&group, &ctx.accounts.fruitbasket, PAUSE_FINALIZE)?;
+
+    // protocol fee is taken on quote spent for buy and on quote received for sell
+    if fee_vault_address(&ctx.accounts.fruitbasket_group.key(), &ctx.accounts.quote_token_mint.key(), ctx.program_id) != ctx.accounts.fee_vault.key() {
+        return Err(FruitBasketError::UnknownFeeVault.into());
+    }
+    // fees are those the user agreed to when the context was created
+    let (fee_base, fee_bps) = if trade_context.side == ContextSide::Buy {
+        (trade_context.initial_usdc_transfer_amount.checked_sub(trade_context.usdc_amount_left).unwrap(), trade_context.mint_fee_bps)
+    } else {
+        (trade_context.usdc_amount_left, trade_context.redeem_fee_bps)
+    };
+    let fee = (fee_base as u128).checked_mul(fee_bps.into()).unwrap().checked_div(10000).unwrap() as u64;
+    // creator of the basket is rewarded on the same base as the protocol
+    if creator_fee_vault_address(&ctx.accounts.fruitbasket.key(), &ctx.accounts.quote_token_mint.key(), ctx.program_id) != ctx.accounts.creator_fee_vault.key() {
+        return Err(FruitBasketError::UnknownFeeVault.into());
+    }
+    let creator_fee = (fee_base as u128).checked_mul(trade_context.creator_fee_bps.into()).unwrap().checked_div(10000).unwrap() as u64;
+    if fee.checked_add(creator_fee).unwrap() > trade_context.usdc_amount_left {
+        return Err(FruitBasketError::InsufficientQuoteForFee.into());
+    }
 
     if trade_context.side == ContextSide::Buy {
         // buy side
@@ -412,22 +1535,69 @@ pub fn finalize_context(ctx : Context<FinalizeContext>) -> ProgramResult {
         let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
         token::mint_to(cpi_ctx, trade_context.amount)?;
     }
+    if fee > 0 {
+        let accounts = token::Transfer {
+            from: ctx.accounts.quote_token_transaction_pool.to_account_info().clone(),
+            to: ctx.accounts.fee_vault.to_account_info().clone(),
+            authority:  ctx.accounts.fruit_basket_authority.clone(),
+        };
+        let transfer_ctx = CpiContext::new_with_signer(ctx.accounts.token_program.clone(), accounts, signer);
+        token::transfer( transfer_ctx, fee)?;
+
+        emit!(FeeTaken {
+            group : ctx.accounts.fruitbasket_group.key(),
+            basket : trade_context.basket,
+            trade_context : trade_context.key(),
+            user : trade_context.user,
+            side : trade_context.side,
+            quote_token_mint : ctx.accounts.quote_token_mint.key(),
+            amount : fee,
+        });
+    }
+    if creator_fee > 0 {
+        let accounts = token::Transfer {
+            from: ctx.accounts.quote_token_transaction_pool.to_account_info().clone(),
+            to: ctx.accounts.creator_fee_vault.to_account_info().clone(),
+            authority:  ctx.accounts.fruit_basket_authority.clone(),
+        };
+        let transfer_ctx = CpiContext::new_with_signer(ctx.accounts.token_program.clone(), accounts, signer);
+        token::transfer( transfer_ctx, creator_fee)?;
+
+        emit!(CreatorFeeAccrued {
+            basket : trade_context.basket,
+            trade_context : trade_context.key(),
+            creator : ctx.accounts.fruitbasket.creator,
+            quote_token_mint : ctx.accounts.quote_token_mint.key(),
+            amount : creator_fee,
+        });
+    }
     // transfer remaining usdc back to client for buy context
     // transfer result usdc back to client for sell context
-    if trade_context.usdc_amount_left > 0 { 
+    let usdc_amount_left = trade_context.usdc_amount_left.checked_sub(fee).unwrap().checked_sub(creator_fee).unwrap();
+    if usdc_amount_left > 0 { 
         let accounts = token::Transfer {
             from: ctx.accounts.quote_token_transaction_pool.to_account_info().clone(),
             to: ctx.accounts.quote_token_account.to_account_info().clone(),
             authority:  ctx.accounts.fruit_basket_authority.clone(),
         };
         let transfer_ctx = CpiContext::new_with_signer(ctx.accounts.token_program.clone(), accounts, signer);
-        token::transfer( transfer_ctx, trade_context.usdc_amount_left)?;
+        token::transfer( transfer_ctx, usdc_amount_left)?;
     }
+
+    emit!(TradeContextFinalized {
+        trade_context : trade_context.key(),
+        basket : trade_context.basket,
+        user : trade_context.user,
+        side : trade_context.side,
+        reverted : false,
+        basket_minted : if trade_context.side == ContextSide::Buy { trade_context.amount } else { 0 },
+        quote_returned : usdc_amount_left,
+    });
     Ok(())
 }
 
 pub fn finalize_for_revert_context<'info>(ctx : &Context<FinalizeContext>,
-                                            trade_context : RefMut<BasketTradeContext>,
+                                            trade_context : &BasketTradeContext,
                                             signer : &[&[&[u8]]]) -> ProgramResult {
     if trade_context.side == ContextSide::Buy {
         // user was trying to buy the context and the transaction was reverted mostly due to failure.
@@ -452,106 +1622,1185 @@ pub fn finalize_for_revert_context<'info>(ctx : &Context<FinalizeContext>,
         let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
         token::mint_to(cpi_ctx, trade_context.amount)?;
     }
-    
+
+    let is_buy_side = trade_context.side == ContextSide::Buy;
+    emit!(TradeContextFinalized {
+        trade_context : ctx.accounts.trade_context.key(),
+        basket : trade_context.basket,
+        user : trade_context.user,
+        side : trade_context.side,
+        reverted : true,
+        basket_minted : if is_buy_side { 0 } else { trade_context.amount },
+        quote_returned : if is_buy_side { trade_context.initial_usdc_transfer_amount } else { 0 },
+    });
     Ok(())
 }
 
-pub fn revert_trade_context( ctx: Context<RevertTradeContext> ) -> ProgramResult {
-    let mut trade_context = ctx.accounts.trade_context.load_mut()?;
-    // check if trade is already reverting
-    if trade_context.reverting == 1 {
-        return Ok(())
+pub fn init_swap_context(
+    ctx: Context<InitSwapContext>,
+    amount : u64,
+    destination_amount : u64,
+    max_quote_in : u64,
+) -> ProgramResult {
+    let group = ctx.accounts.group.load()?;
+    let source_basket = &mut ctx.accounts.source_basket;
+    let destination_basket = &mut ctx.accounts.destination_basket;
+    let trade_context = &mut ctx.accounts.trade_context;
+
+    if amount == 0 || destination_amount == 0 {
+        return Err(FruitBasketError::InvalidAmount.into());
     }
-    let basket = &ctx.accounts.fruitbasket;
-    if basket.key() != trade_context.basket {
-        return Err( FruitBasketError::UnknownBasket.into() );
+    if source_basket.key() == destination_basket.key() {
+        return Err(FruitBasketError::SwapSameBasket.into());
+    }
+    check_not_paused(&group, source_basket, PAUSE_NEW_CONTEXTS)?;
+    check_not_paused(&group, destination_basket, PAUSE_NEW_CONTEXTS)?;
+    if !source_basket.is_complete() || !destination_basket.is_complete() {
+        return Err(FruitBasketError::BasketIncomplete.into());
+    }
+    if source_basket.is_rebalancing() || destination_basket.is_rebalancing() {
+        return Err(FruitBasketError::BasketRebalancing.into());
+    }
+    let quote = group.quote_currency(&ctx.accounts.quote_token_mint.key())?;
+    if source_basket.group != ctx.accounts.group.key() 
+        || destination_basket.group != ctx.accounts.group.key()
+        || ctx.accounts.quote_token_transaction_pool.key() != quote.transaction_pool {
+        return Err(FruitBasketError::AccountsMismatch.into());
+    }
+    let (authority, _bump) = Pubkey::find_program_address(&[FRUIT_BASKET_AUTHORITY], ctx.program_id);
+    if authority != ctx.accounts.fruit_basket_authority.key() {
+        return Err(FruitBasketError::UnknownAuthority.into());
     }
 
-    trade_context.reverting = 1;
-    for index in 0..basket.number_of_components {
-        let token_index = index as usize;
-        if trade_context.tokens_treated[token_index] == 1 {
-            trade_context.tokens_treated[token_index] = 0;
-            trade_context.token_amounts[token_index] = trade_context.initial_token_amounts[token_index];
+    // net the components of both baskets, negative amounts are sold and positive amounts are bought
+    let (token_mints, net_amounts, shared_amounts) = net_leg_amounts(&component_leg_amounts(&source_basket.components, amount),
+                                                                     &component_leg_amounts(&destination_basket.components, destination_amount));
+    // fees are taken on the quote of the traded legs, they are reserved at the worst price of each leg like for a buy
+    let mut fee_reserve : u64 = 0;
+    for (token_mint, net_amount) in token_mints.iter().zip(net_amounts.iter()).filter(|(_, x)| **x != 0) {
+        let (components, component_prices, fee_bps) = if *net_amount > 0 {
+            (&destination_basket.components, &destination_basket.component_prices,
+             group.mint_fee_bps.checked_add(destination_basket.creator_fee_bps).unwrap())
         } else {
-            // token has not been processed yet.
-            if trade_context.token_amounts[token_index] == trade_context.initial_token_amounts[token_index] {
-                trade_context.tokens_treated[token_index] = 1;
-            }
-            else {
-                // update the token count that should be processed
-                trade_context.token_amounts[token_index] = trade_context.initial_token_amounts[token_index].checked_sub(trade_context.token_amounts[token_index]).unwrap();
-            }
+            (&source_basket.components, &source_basket.component_prices,
+             group.redeem_fee_bps.checked_add(source_basket.creator_fee_bps).unwrap())
+        };
+        let position = components.iter().position(|x| x.token_mint == *token_mint).unwrap();
+        let component_price = &component_prices[position];
+        if component_price.price == 0 {
+            return Err(FruitBasketError::BasketNotPriced.into());
         }
+        let worst_price = quote.in_quote_native(component_price.price.checked_add(component_price.confidence).unwrap())?;
+        // leg amounts have 6 decimals
+        let leg_quote = (net_amount.unsigned_abs())
+                            .checked_mul(worst_price.into()).unwrap()
+                            .checked_div(1_000_000).unwrap() as u64;
+        let worst_leg_quote = leg_quote.checked_add(bps_of(leg_quote, group.leg_slippage_bps)).unwrap();
+        fee_reserve = fee_reserve.checked_add(bps_of(worst_leg_quote, fee_bps)).unwrap();
     }
-    // update usdc amount left
-    // TODO smarter way to decide these token amounts
-    if trade_context.side == ContextSide::Buy {
-        trade_context.usdc_amount_left = 0;
-    } else  {
-        // TODO ASAP smarter way to calculate the usdc limit to buy back the tokens in case of revert.
-        // Multiple strategies available.
-        trade_context.usdc_amount_left = token::accessor::amount(&ctx.accounts.quote_token_transaction_pool)?;
+
+    // quote added by the user to pay for the difference between both baskets, with the fees on top
+    let quote_deposited = max_quote_in.checked_add(fee_reserve).unwrap();
+    if quote_deposited > 0 {
+        let accounts = token::Transfer {
+            from: ctx.accounts.quote_token_account.to_account_info().clone(),
+            to: ctx.accounts.quote_token_transaction_pool.to_account_info().clone(),
+            authority: ctx.accounts.user.to_account_info(),
+        };
+        let transfer_ctx = CpiContext::new(ctx.accounts.token_program.clone(), accounts);
+        token::transfer( transfer_ctx, quote_deposited)?;
     }
+    // burn the source basket tokens
+    let cpi_accounts = token::Burn {
+        mint: ctx.accounts.source_basket_token_mint.to_account_info(),
+        to: ctx.accounts.source_basket_token_account.to_account_info(),
+        authority: ctx.accounts.user.to_account_info(),
+    };
+    let cpi_ctx = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts);
+    token::burn(cpi_ctx, amount)?;
+
+    trade_context.magic = BASKET_TRADE_CONTEXT_MAGIC;
+    trade_context.side = ContextSide::Swap;
+    trade_context.basket = source_basket.key();
+    trade_context.reverting = 0;
+    trade_context.amount = amount;
+    trade_context.usdc_amount_left = quote_deposited;
+    trade_context.quote_token_account = ctx.accounts.quote_token_account.key();
+    trade_context.basket_token_account = ctx.accounts.source_basket_token_account.key();
+    trade_context.user = ctx.accounts.user.key();
+    trade_context.quote_token_mint = quote.mint;
+    trade_context.initial_usdc_transfer_amount = quote_deposited;
+    trade_context.fee_reserve = fee_reserve;
+    trade_context.order_type = ContextOrderType::Market;
+    trade_context.limit_price = 0;
+    trade_context.quote_spent = 0;
+    trade_context.quote_received = 0;
+    trade_context.destination_basket = destination_basket.key();
+    trade_context.destination_basket_token_account = ctx.accounts.destination_basket_token_account.key();
+    trade_context.destination_amount = destination_amount;
+    trade_context.mint_fee_bps = group.mint_fee_bps;
+    trade_context.redeem_fee_bps = group.redeem_fee_bps;
+    trade_context.creator_fee_bps = source_basket.creator_fee_bps;
+    trade_context.destination_creator_fee_bps = destination_basket.creator_fee_bps;
+
+    set_netted_legs(trade_context, token_mints, net_amounts);
+    // tokens kept by both baskets are not traded but moved between their vaults
+    trade_context.leg_transfer_amounts = shared_amounts;
+
+    let clock = Clock::get()?;
+    trade_context.created_on = clock.unix_timestamp as u64;
+    // neither basket can be rebalanced until the swap is finalized
+    source_basket.open_contexts = source_basket.open_contexts.checked_add(1).unwrap();
+    destination_basket.open_contexts = destination_basket.open_contexts.checked_add(1).unwrap();
+
+    emit_context_created(trade_context);
     Ok(())
 }
 
-fn change_authority<'info>(acc : &AccountInfo<'info>, 
-                          from : &AccountInfo<'info>, 
-                          to: Pubkey, 
-                          token_program: &AccountInfo<'info>, 
-                          seeds : Option<&[&[&[u8]]]>) -> ProgramResult{
+// amount of each component for an amount of basket tokens, with 6 decimals
+fn component_leg_amounts(components : &[BasketComponentDescription], basket_amount : u64) -> Vec<(Pubkey, u64)> {
+    components.iter()
+        .map(|component| (component.token_mint, (basket_amount as u128)
+                                                    .checked_mul(component.amount.into()).unwrap()
+                                                    .checked_div(10u128.pow(component.decimal.into())).unwrap() as u64))
+        .collect()
+}
 
-    let cpi_acc = SetAuthority {
-        account_or_mint: acc.clone(),
-        current_authority: from.clone(),
-    };
-    let mut cpi = CpiContext::new(token_program.clone(), cpi_acc);
-    if let Some(signer_seeds) = seeds {
-        cpi = cpi.with_signer(signer_seeds);
+// net two lists of token amounts, negative amounts are sold and positive amounts are bought
+// also gives the amount of each token which is both sold and bought
+fn net_leg_amounts(sold_legs : &[(Pubkey, u64)], bought_legs : &[(Pubkey, u64)]) -> (Vec<Pubkey>, Vec<i128>, Vec<u64>) {
+    let mut token_mints : Vec<Pubkey> = Vec::new();
+    let mut sold_amounts : Vec<i128> = Vec::new();
+    let mut bought_amounts : Vec<i128> = Vec::new();
+    let legs = sold_legs.iter().map(|x| (x, true))
+                .chain(bought_legs.iter().map(|x| (x, false)));
+    for ((token_mint, amount), is_sold) in legs {
+        let amount_of_tokens = *amount as i128;
+        let position = match token_mints.iter().position(|x| x == token_mint) {
+            Some(position) => position,
+            None => {
+                token_mints.push(*token_mint);
+                sold_amounts.push(0);
+                bought_amounts.push(0);
+                token_mints.len() - 1
+            }
+        };
+        if is_sold {
+            sold_amounts[position] += amount_of_tokens;
+        } else {
+            bought_amounts[position] += amount_of_tokens;
+        }
     }
-    token::set_authority( cpi,  AuthorityType::AccountOwner, Some(to))
+    let net_amounts = bought_amounts.iter().zip(sold_amounts.iter()).map(|(bought, sold)| bought - sold).collect();
+    let shared_amounts = bought_amounts.iter().zip(sold_amounts.iter()).map(|(bought, sold)| *bought.min(sold) as u64).collect();
+    (token_mints, net_amounts, shared_amounts)
 }
 
-impl<'info> ProcessTokenOnContext<'info>{
-    fn create_new_order(&self, 
-                        side:Side, 
-                        max_coin_qty: u64, 
-                        max_native_token : u64,
-                        seeds:&[&[&[u8]]]) -> ProgramResult {
-        let is_buy_side = side == Side::Bid;
-        let client_order_id = 0;
-        let limit = 65535;
-        let new_orders = dex::NewOrderV3 {
-            market: self.market.clone(),
-            open_orders: self.open_orders.clone(),
-            request_queue: self.request_queue.clone(),
-            event_queue: self.event_queue.clone(),
-            market_bids: self.bids.clone(),
-            market_asks: self.asks.clone(),
-            order_payer_token_account: if is_buy_side { self.quote_token_transaction_pool.to_account_info().clone() } else { self.token_pool.clone() },
-            open_orders_authority: self.fruit_basket_authority.clone(),
-            coin_vault: self.token_vault.clone(),
-            pc_vault: self.quote_token_vault.clone(),
-            token_program: self.token_program.clone(),
-            rent: self.rent.clone(),
-        };
-        let ctx_orders = CpiContext::new(self.dex_program.clone(), new_orders);
-        // TODO Decide limit price and native token price more approriately.
-        let limit_price = if is_buy_side { u64::MAX } else { 1 };
-        dex::new_order_v3(
-            ctx_orders.with_signer(seeds),
-            side,
-            NonZeroU64::new(limit_price).unwrap(),
-            NonZeroU64::new(max_coin_qty).unwrap(),
-            NonZeroU64::new(max_native_token).unwrap(),
-            SelfTradeBehavior::DecrementTake,
-            OrderType::ImmediateOrCancel,
+// legs of a swap or rebalance context from netted amounts
+fn set_netted_legs(trade_context : &mut BasketTradeContext, token_mints : Vec<Pubkey>, net_amounts : Vec<i128>) {
+    let number_of_legs = token_mints.len();
+    trade_context.token_amounts = net_amounts.iter().map(|x| x.unsigned_abs() as u64).collect();
+    trade_context.initial_token_amounts = trade_context.token_amounts.clone();
+    // netted out legs have nothing to trade
+    trade_context.tokens_treated = net_amounts.iter().map(|x| if *x == 0 { 1 } else { 0 }).collect();
+    trade_context.leg_sides = net_amounts.iter().map(|x| if *x > 0 { ContextSide::Buy } else { ContextSide::Sell }).collect();
+    trade_context.token_mints = token_mints;
+    trade_context.leg_limit_prices = vec![0; number_of_legs];
+    trade_context.leg_order_quantities = vec![0; number_of_legs];
+    trade_context.leg_filled_quantities = vec![0; number_of_legs];
+    trade_context.leg_transfer_amounts = vec![0; number_of_legs];
+}
+
+pub fn init_rebalance_context(
+    ctx: Context<InitRebalanceContext>,
+    new_components : Vec<BasketComponentDescription>,
+) -> ProgramResult {
+    let group = ctx.accounts.group.load()?;
+    let basket = &mut ctx.accounts.basket;
+    let trade_context = &mut ctx.accounts.trade_context;
+
+    let curator = ctx.accounts.curator.key();
+    if basket.creator != curator && !group.has_role(&curator, GroupRole::BasketCurator) {
+        return Err(FruitBasketError::Unauthorized.into());
+    }
+    if !basket.is_complete() {
+        return Err(FruitBasketError::BasketIncomplete.into());
+    }
+    if basket.is_rebalancing() {
+        return Err(FruitBasketError::BasketRebalancing.into());
+    }
+    check_not_paused(&group, basket, PAUSE_NEW_CONTEXTS)?;
+    // legs are netted against the vaults, which contexts move until they are finalized
+    if basket.open_contexts > 0 {
+        return Err(FruitBasketError::BasketHasOpenContexts.into());
+    }
+    // new components have to fit in the space allocated for the basket
+    let number_of_legs = basket.components.len() + new_components.len();
+    if new_components.len() > basket.number_of_components as usize
+        || 8 + BasketTradeContext::space(number_of_legs) > MAX_PERMITTED_DATA_INCREASE {
+        return Err(FruitBasketError::ComponentCountOverflow.into());
+    }
+    if new_components.len() < 2 {
+        return Err(FruitBasketError::ComponentCountUnderflow.into());
+    }
+    if new_components.iter().enumerate().any(|(index, x)| new_components[..index].iter().any(|y| y.token_mint == x.token_mint)) {
+        return Err(FruitBasketError::DuplicateComponent.into());
+    }
+
+    // tokens held by the vaults are traded for the backing of the whole supply in the new components
+    let supply = ctx.accounts.basket_token_mint.supply;
+    let (vault_amounts, _, token_decimals) = basket_backing(basket, 0, supply, ctx.remaining_accounts, ctx.program_id)?;
+    if vault_amounts.len() != basket.components.len() {
+        return Err(FruitBasketError::AccountsMismatch.into());
+    }
+    let held_amounts = basket.components.iter().zip(vault_amounts.iter().zip(token_decimals.iter()))
+                        .map(|(component, (vault_amount, token_decimal))| (component.token_mint, context_token_amount(*vault_amount, *token_decimal)))
+                        .collect::<Vec<_>>();
+    let (token_mints, net_amounts, _) = net_leg_amounts(&held_amounts, &component_leg_amounts(&new_components, supply));
+
+    trade_context.magic = BASKET_TRADE_CONTEXT_MAGIC;
+    trade_context.side = ContextSide::Rebalance;
+    trade_context.basket = basket.key();
+    trade_context.reverting = 0;
+    trade_context.amount = supply;
+    trade_context.usdc_amount_left = 0;
+    trade_context.quote_token_account = Pubkey::default();
+    trade_context.basket_token_account = Pubkey::default();
+    trade_context.user = curator;
+    trade_context.quote_token_mint = group.base_mint;
+    trade_context.initial_usdc_transfer_amount = 0;
+    trade_context.fee_reserve = 0;
+    trade_context.order_type = ContextOrderType::Market;
+    trade_context.limit_price = 0;
+    trade_context.quote_spent = 0;
+    trade_context.quote_received = 0;
+    trade_context.destination_basket = Pubkey::default();
+    trade_context.destination_basket_token_account = Pubkey::default();
+    trade_context.destination_amount = 0;
+    // a rebalance is paid by the basket, no fee is taken
+    trade_context.mint_fee_bps = 0;
+    trade_context.redeem_fee_bps = 0;
+    trade_context.creator_fee_bps = 0;
+    trade_context.destination_creator_fee_bps = 0;
+    set_netted_legs(trade_context, token_mints, net_amounts);
+
+    let clock = Clock::get()?;
+    trade_context.created_on = clock.unix_timestamp as u64;
+
+    // basket cannot be traded until the rebalance is finalized
+    basket.pending_components = new_components;
+    basket.rebalance_context = trade_context.key();
+
+    emit_context_created(trade_context);
+    Ok(())
+}
+
+fn emit_context_created(trade_context : &Account<BasketTradeContext>) {
+    emit!(TradeContextCreated {
+        trade_context : trade_context.key(),
+        basket : trade_context.basket,
+        user : trade_context.user,
+        side : trade_context.side,
+        order_type : trade_context.order_type,
+        amount : trade_context.amount,
+        quote_token_mint : trade_context.quote_token_mint,
+        quote_deposited : trade_context.initial_usdc_transfer_amount,
+        destination_basket : trade_context.destination_basket,
+        destination_amount : trade_context.destination_amount,
+    });
+}
+
+pub fn finalize_rebalance_context(ctx : Context<FinalizeRebalanceContext>) -> ProgramResult {
+    let trade_context = &ctx.accounts.trade_context;
+    let basket = &mut ctx.accounts.basket;
+    if trade_context.side != ContextSide::Rebalance {
+        return Err(FruitBasketError::InvalidContextSide.into());
+    }
+    if trade_context.tokens_treated.iter().any(|x| *x != 1) {
+        return Err(FruitBasketError::NotAllTokensTreatedBeforeFinalize.into());
+    }
+    if trade_context.basket != basket.key() || basket.rebalance_context != trade_context.key() {
+        return Err(FruitBasketError::UnknownBasket.into());
+    }
+    let group = ctx.accounts.group.load()?;
+    if ctx.accounts.quote_token_transaction_pool.key() != group.quote_token_transaction_pool {
+        return Err(FruitBasketError::AccountsMismatch.into());
+    }
+    if trade_context.reverting == 0 {
+        check_not_paused(&group, basket, PAUSE_FINALIZE)?;
+    }
+    let (authority, bump) = Pubkey::find_program_address(&[FRUIT_BASKET_AUTHORITY], ctx.program_id);
+    if authority != ctx.accounts.fruit_basket_authority.key() {
+        return Err(FruitBasketError::UnknownAuthority.into());
+    }
+    let seeds = [&FRUIT_BASKET_AUTHORITY[..], &[bump]];
+    let signer = &[&seeds[..]];
+
+    // quote left between sell and buy legs belongs to the basket, it is kept in its vault of the base mint
+    if trade_context.usdc_amount_left > 0 {
+        if basket_vault_address(&basket.key(), &group.base_mint, ctx.program_id) != ctx.accounts.basket_quote_vault.key() {
+            return Err(FruitBasketError::UnknownBasketVault.into());
+        }
+        transfer_from_pool(&ctx.accounts.token_program, &ctx.accounts.quote_token_transaction_pool.to_account_info(), 
+                            &ctx.accounts.basket_quote_vault, &ctx.accounts.fruit_basket_authority, signer, trade_context.usdc_amount_left)?;
+    }
+    let pending_components = std::mem::take(&mut basket.pending_components);
+    if trade_context.reverting == 0 {
+        // component prices have to be updated before the basket can be traded again
+        // the basket account keeps its space if it has less components after the rebalance
+        basket.number_of_components = pending_components.len() as u8;
+        basket.component_prices = vec![ComponentPrice::default(); pending_components.len()];
+        basket.components = pending_components;
+        basket.price_slot = 0;
+        basket.book_price_amount = 0;
+
+        emit!(BasketRebalanced {
+            basket : basket.key(),
+            trade_context : trade_context.key(),
+            components : basket.components.clone(),
+        });
+    }
+    basket.rebalance_context = Pubkey::default();
+
+    emit!(TradeContextFinalized {
+        trade_context : trade_context.key(),
+        basket : trade_context.basket,
+        user : trade_context.user,
+        side : trade_context.side,
+        reverted : trade_context.reverting == 1,
+        basket_minted : 0,
+        quote_returned : 0,
+    });
+    Ok(())
+}
+
+pub fn finalize_swap_context<'info>(ctx : Context<'_, '_, '_, 'info, FinalizeSwapContext<'info>>) -> ProgramResult {
+    let trade_context = &ctx.accounts.trade_context;
+    if trade_context.side != ContextSide::Swap {
+        return Err(FruitBasketError::InvalidContextSide.into());
+    }
+    if trade_context.tokens_treated.iter().any(|x| *x != 1) {
+        return Err(FruitBasketError::NotAllTokensTreatedBeforeFinalize.into());
+    }
+    if trade_context.basket != ctx.accounts.source_basket.key() 
+        || trade_context.destination_basket != ctx.accounts.destination_basket.key() {
+        return Err(FruitBasketError::UnknownBasket.into());
+    }
+    if trade_context.quote_token_account != ctx.accounts.quote_token_account.key() 
+        || trade_context.basket_token_account != ctx.accounts.source_basket_token_account.key()
+        || trade_context.destination_basket_token_account != ctx.accounts.destination_basket_token_account.key() {
+        return Err(FruitBasketError::AccountsMismatch.into());
+    }
+    let (authority, bump) = Pubkey::find_program_address(&[FRUIT_BASKET_AUTHORITY], ctx.program_id);
+    if authority != ctx.accounts.fruit_basket_authority.key() {
+        return Err(FruitBasketError::UnknownAuthority.into());
+    }
+    let seeds = [&FRUIT_BASKET_AUTHORITY[..], &[bump]];
+    let signer = &[&seeds[..]];
+    let quote_token_transaction_pool = ctx.accounts.quote_token_transaction_pool.to_account_info();
+    let group = ctx.accounts.fruitbasket_group.load()?;
+    if ctx.accounts.source_basket.group != ctx.accounts.fruitbasket_group.key()
+        || ctx.accounts.quote_token_mint.key() != trade_context.quote_token_mint
+        || quote_token_transaction_pool.key() != group.quote_currency(&trade_context.quote_token_mint)?.transaction_pool {
+        return Err(FruitBasketError::AccountsMismatch.into());
+    }
+    if ctx.accounts.source_basket.is_rebalancing() || ctx.accounts.destination_basket.is_rebalancing() {
+        return Err(FruitBasketError::BasketRebalancing.into());
+    }
+    ctx.accounts.source_basket.open_contexts = ctx.accounts.source_basket.open_contexts.saturating_sub(1);
+    ctx.accounts.destination_basket.open_contexts = ctx.accounts.destination_basket.open_contexts.saturating_sub(1);
+
+    if trade_context.reverting == 1 {
+        // give back the quote added by the user and the source basket tokens
+        if trade_context.initial_usdc_transfer_amount > 0 {
+            transfer_from_pool(&ctx.accounts.token_program, &quote_token_transaction_pool, &ctx.accounts.quote_token_account.to_account_info(),
+                                &ctx.accounts.fruit_basket_authority, signer, trade_context.initial_usdc_transfer_amount)?;
+        }
+        let cpi_accounts = token::MintTo {
+            mint: ctx.accounts.source_basket_token_mint.to_account_info(),
+            to: ctx.accounts.source_basket_token_account.to_account_info(),
+            authority: ctx.accounts.fruit_basket_authority.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(ctx.accounts.token_program.to_account_info(), cpi_accounts, signer);
+        token::mint_to(cpi_ctx, trade_context.amount)?;
+
+        emit!(TradeContextFinalized {
+            trade_context : trade_context.key(),
+            basket : trade_context.basket,
+            user : trade_context.user,
+            side : trade_context.side,
+            reverted : true,
+            basket_minted : trade_context.amount,
+            quote_returned : trade_context.initial_usdc_transfer_amount,
+        });
+        return Ok(());
+    }
+    check_not_paused(&group, &ctx.accounts.source_basket, PAUSE_FINALIZE)?;
+    check_not_paused(&group, &ctx.accounts.destination_basket, PAUSE_FINALIZE)?;
+    transfer_shared_legs(&ctx, trade_context, signer)?;
+
+    // protocol fee is taken on quote spent by buy legs and on quote received by sell legs
+    let group_key = ctx.accounts.fruitbasket_group.key();
+    let quote_token_mint = ctx.accounts.quote_token_mint.key();
+    if fee_vault_address(&group_key, &quote_token_mint, ctx.program_id) != ctx.accounts.fee_vault.key() 
+        || creator_fee_vault_address(&trade_context.basket, &quote_token_mint, ctx.program_id) != ctx.accounts.source_creator_fee_vault.key()
+        || creator_fee_vault_address(&trade_context.destination_basket, &quote_token_mint, ctx.program_id) != ctx.accounts.destination_creator_fee_vault.key() {
+        return Err(FruitBasketError::UnknownFeeVault.into());
+    }
+    let fee = bps_of(trade_context.quote_spent, trade_context.mint_fee_bps)
+                .checked_add(bps_of(trade_context.quote_received, trade_context.redeem_fee_bps)).unwrap();
+    let source_creator_fee = bps_of(trade_context.quote_received, trade_context.creator_fee_bps);
+    let destination_creator_fee = bps_of(trade_context.quote_spent, trade_context.destination_creator_fee_bps);
+    let usdc_amount_left = trade_context.usdc_amount_left
+                            .checked_sub(fee)
+                            .and_then(|x| x.checked_sub(source_creator_fee))
+                            .and_then(|x| x.checked_sub(destination_creator_fee))
+                            .ok_or(FruitBasketError::InsufficientQuoteForFee)?;
+
+    let cpi_accounts = token::MintTo {
+        mint: ctx.accounts.destination_basket_token_mint.to_account_info(),
+        to: ctx.accounts.destination_basket_token_account.to_account_info(),
+        authority: ctx.accounts.fruit_basket_authority.to_account_info(),
+    };
+    let cpi_ctx = CpiContext::new_with_signer(ctx.accounts.token_program.to_account_info(), cpi_accounts, signer);
+    token::mint_to(cpi_ctx, trade_context.destination_amount)?;
+
+    if fee > 0 {
+        transfer_from_pool(&ctx.accounts.token_program, &quote_token_transaction_pool, &ctx.accounts.fee_vault.to_account_info(),
+                            &ctx.accounts.fruit_basket_authority, signer, fee)?;
+        emit!(FeeTaken {
+            group : group_key,
+            basket : trade_context.destination_basket,
+            trade_context : trade_context.key(),
+            user : trade_context.user,
+            side : trade_context.side,
+            quote_token_mint,
+            amount : fee,
+        });
+    }
+    let creator_fees = [
+        (source_creator_fee, &ctx.accounts.source_basket, &ctx.accounts.source_creator_fee_vault),
+        (destination_creator_fee, &ctx.accounts.destination_basket, &ctx.accounts.destination_creator_fee_vault),
+    ];
+    for (creator_fee, basket, creator_fee_vault) in creator_fees {
+        if creator_fee == 0 {
+            continue;
+        }
+        transfer_from_pool(&ctx.accounts.token_program, &quote_token_transaction_pool, &creator_fee_vault.to_account_info(),
+                            &ctx.accounts.fruit_basket_authority, signer, creator_fee)?;
+        emit!(CreatorFeeAccrued {
+            basket : basket.key(),
+            trade_context : trade_context.key(),
+            creator : basket.creator,
+            quote_token_mint,
+            amount : creator_fee,
+        });
+    }
+    if usdc_amount_left > 0 {
+        transfer_from_pool(&ctx.accounts.token_program, &quote_token_transaction_pool, &ctx.accounts.quote_token_account.to_account_info(),
+                            &ctx.accounts.fruit_basket_authority, signer, usdc_amount_left)?;
+    }
+
+    emit!(TradeContextFinalized {
+        trade_context : trade_context.key(),
+        basket : trade_context.destination_basket,
+        user : trade_context.user,
+        side : trade_context.side,
+        reverted : false,
+        basket_minted : trade_context.destination_amount,
+        quote_returned : usdc_amount_left,
+    });
+    Ok(())
+}
+
+pub fn revert_trade_context( ctx: Context<RevertTradeContext> ) -> ProgramResult {
+    let trade_context = &mut ctx.accounts.trade_context;
+    // limit contexts are expected to stay open for long, only the user can cancel them
+    if trade_context.order_type == ContextOrderType::Limit {
+        return Err(FruitBasketError::LimitContextRevertRequiresUser.into());
+    }
+    if trade_context.side == ContextSide::Rebalance {
+        return Err(FruitBasketError::RebalanceRevertRequiresCurator.into());
+    }
+    start_revert(trade_context, &ctx.accounts.fruitbasket, &ctx.accounts.quote_token_transaction_pool)?;
+
+    emit!(TradeContextReverted {
+        trade_context : trade_context.key(),
+        basket : trade_context.basket,
+        user : trade_context.user,
+        cancelled : false,
+    });
+    Ok(())
+}
+
+pub fn cancel_trade_context( ctx: Context<CancelTradeContext> ) -> ProgramResult {
+    let trade_context = &mut ctx.accounts.trade_context;
+    // rebalance contexts are cancelled by their curator like limit contexts
+    if trade_context.order_type != ContextOrderType::Limit && trade_context.side != ContextSide::Rebalance {
+        return Err(FruitBasketError::NotALimitContext.into());
+    }
+    let authority = ctx.accounts.authority.key();
+    if trade_context.user != authority {
+        // the curator of the basket cancels the limit contexts which keep it from being rebalanced
+        let group = ctx.accounts.group.load()?;
+        let basket = &ctx.accounts.fruitbasket;
+        let is_curator = basket.creator == authority || group.has_role(&authority, GroupRole::BasketCurator);
+        if trade_context.order_type != ContextOrderType::Limit || !is_curator {
+            return Err(FruitBasketError::AccountsMismatch.into());
+        }
+    }
+    start_revert(trade_context, &ctx.accounts.fruitbasket, &ctx.accounts.quote_token_transaction_pool)?;
+
+    emit!(TradeContextReverted {
+        trade_context : trade_context.key(),
+        basket : trade_context.basket,
+        user : trade_context.user,
+        cancelled : true,
+    });
+    Ok(())
+}
+
+fn start_revert( trade_context : &mut BasketTradeContext,
+                 basket : &Account<Basket>,
+                 quote_token_transaction_pool : &AccountInfo ) -> ProgramResult {
+    // check if trade is already reverting
+    if trade_context.reverting == 1 {
+        return Ok(())
+    }
+    if basket.key() != trade_context.basket {
+        return Err( FruitBasketError::UnknownBasket.into() );
+    }
+
+    trade_context.reverting = 1;
+    for token_index in 0..trade_context.tokens_treated.len() {
+        // legs netted out by a swap have nothing to revert
+        if trade_context.initial_token_amounts[token_index] == 0 {
+            continue;
+        }
+        if trade_context.tokens_treated[token_index] == 1 {
+            trade_context.tokens_treated[token_index] = 0;
+            trade_context.token_amounts[token_index] = trade_context.initial_token_amounts[token_index];
+        } else {
+            // token has not been processed yet.
+            // a resting limit order has to be cancelled before deciding the amount to revert
+            if trade_context.token_amounts[token_index] == trade_context.initial_token_amounts[token_index]
+                && trade_context.leg_order_quantities[token_index] == 0 {
+                trade_context.tokens_treated[token_index] = 1;
+            }
+            else {
+                // update the token count that should be processed
+                trade_context.token_amounts[token_index] = trade_context.initial_token_amounts[token_index]
+                                                            .checked_sub(trade_context.token_amounts[token_index])
+                                                            .ok_or(FruitBasketError::InvalidLegAmount)?;
+            }
+        }
+    }
+    // update usdc amount left, a rebalance buys back its legs with the quote it received only
+    // TODO smarter way to decide these token amounts
+    if trade_context.side == ContextSide::Buy {
+        // a limit buy keeps its budget, fills not accounted yet are taken from it when its resting orders are cancelled
+        if trade_context.order_type == ContextOrderType::Market {
+            trade_context.usdc_amount_left = 0;
+        }
+    } else if trade_context.side == ContextSide::Swap {
+        // the pool is shared by every context, a swap buys back its sold legs with the quote it holds only
+        let quote_held = trade_context.initial_usdc_transfer_amount
+                            .checked_add(trade_context.quote_received).unwrap()
+                            .checked_sub(trade_context.quote_spent).unwrap();
+        trade_context.usdc_amount_left = quote_held.min(token::accessor::amount(quote_token_transaction_pool)?);
+    } else if trade_context.side != ContextSide::Rebalance {
+        // TODO ASAP smarter way to calculate the usdc limit to buy back the tokens in case of revert.
+        // Multiple strategies available.
+        trade_context.usdc_amount_left = token::accessor::amount(quote_token_transaction_pool)?;
+    }
+    Ok(())
+}
+
+pub fn init_in_kind_context(ctx : Context<InitInKindContext>, amount : u64, redeem : bool) -> ProgramResult {
+    if amount == 0 {
+        return Err(FruitBasketError::InvalidAmount.into());
+    }
+    let basket = &mut ctx.accounts.fruitbasket;
+    // holders can always redeem their basket tokens
+    if !redeem {
+        check_not_paused(&*ctx.accounts.group.load()?, basket, PAUSE_NEW_CONTEXTS)?;
+    }
+    if !basket.is_complete() {
+        return Err(FruitBasketError::BasketIncomplete.into());
+    }
+    if basket.is_rebalancing() {
+        return Err(FruitBasketError::BasketRebalancing.into());
+    }
+    if redeem {
+        let cpi_accounts = token::Burn {
+            mint: ctx.accounts.basket_token_mint.to_account_info(),
+            to: ctx.accounts.basket_token_account.to_account_info(),
+            authority: ctx.accounts.user.clone(),
+        };
+        let cpi_ctx = CpiContext::new(ctx.accounts.token_program.clone(), cpi_accounts);
+        token::burn(cpi_ctx, amount)?;
+    }
+
+    let in_kind_context = &mut ctx.accounts.in_kind_context;
+    in_kind_context.magic = IN_KIND_CONTEXT_MAGIC;
+    in_kind_context.basket = basket.key();
+    in_kind_context.user = ctx.accounts.user.key();
+    in_kind_context.basket_token_account = ctx.accounts.basket_token_account.key();
+    in_kind_context.amount = amount;
+    in_kind_context.redeem = redeem as u8;
+    in_kind_context.cursor = 0;
+    in_kind_context.reverting = 0;
+    in_kind_context.deposited = 0;
+    // the basket cannot be rebalanced until the components are moved
+    basket.open_contexts = basket.open_contexts.checked_add(1).unwrap();
+    Ok(())
+}
+
+pub fn process_in_kind<'info>(ctx : Context<'_, '_, '_, 'info, ProcessInKind<'info>>, first_component : u8) -> ProgramResult {
+    let in_kind_context = &ctx.accounts.in_kind_context;
+    let first_component = first_component as usize;
+    let count = ctx.remaining_accounts.len() / 3;
+    // components are moved in basket order from the cursor of the context
+    if first_component != in_kind_context.cursor as usize
+        || count == 0
+        || ctx.remaining_accounts.len() != count * 3
+        || first_component + count > in_kind_context.components_to_move(ctx.accounts.fruitbasket.components.len()) {
+        return Err(FruitBasketError::InKindMismatch.into());
+    }
+    // components are taken from the user for a creation, and given back for a redeem or a cancelled creation
+    let from_user = in_kind_context.redeem == 0 && in_kind_context.reverting == 0;
+    if from_user && !ctx.accounts.user.is_signer {
+        return Err(FruitBasketError::Unauthorized.into());
+    }
+    // a creation takes every component rounded up so that the basket is never under collateralized, and gives back the same amounts
+    let round_up = in_kind_context.redeem == 0;
+    transfer_components_in_kind(&ctx, first_component, from_user, round_up)?;
+    ctx.accounts.in_kind_context.cursor = (first_component + count) as u8;
+    Ok(())
+}
+
+pub fn finalize_in_kind_context(ctx : Context<FinalizeInKindContext>) -> ProgramResult {
+    let in_kind_context = &ctx.accounts.in_kind_context;
+    if (in_kind_context.cursor as usize) != in_kind_context.components_to_move(ctx.accounts.fruitbasket.components.len()) {
+        return Err(FruitBasketError::NotAllTokensTreatedBeforeFinalize.into());
+    }
+    let (authority, bump) = Pubkey::find_program_address(&[FRUIT_BASKET_AUTHORITY], ctx.program_id);
+    if authority != ctx.accounts.fruit_basket_authority.key() {
+        return Err(FruitBasketError::UnknownAuthority.into());
+    }
+    let seeds = [&FRUIT_BASKET_AUTHORITY[..], &[bump]];
+    let signer = &[&seeds[..]];
+    ctx.accounts.fruitbasket.open_contexts = ctx.accounts.fruitbasket.open_contexts.saturating_sub(1);
+    if in_kind_context.reverting == 1 {
+        return Ok(());
+    }
+
+    if in_kind_context.redeem == 1 {
+        emit!(BasketRedeemedInKind {
+            basket : ctx.accounts.fruitbasket.key(),
+            user : in_kind_context.user,
+            amount : in_kind_context.amount,
+        });
+        return Ok(());
+    }
+    let cpi_accounts = token::MintTo {
+        mint: ctx.accounts.basket_token_mint.to_account_info(),
+        to: ctx.accounts.basket_token_account.to_account_info(),
+        authority: ctx.accounts.fruit_basket_authority.to_account_info(),
+    };
+    let cpi_ctx = CpiContext::new_with_signer(ctx.accounts.token_program.clone(), cpi_accounts, signer);
+    token::mint_to(cpi_ctx, in_kind_context.amount)?;
+
+    emit!(BasketCreatedInKind {
+        basket : ctx.accounts.fruitbasket.key(),
+        user : in_kind_context.user,
+        amount : in_kind_context.amount,
+    });
+    Ok(())
+}
+
+pub fn cancel_in_kind_context(ctx : Context<CancelInKindContext>) -> ProgramResult {
+    let in_kind_context = &mut ctx.accounts.in_kind_context;
+    // the basket tokens of a redeem are burned already, anyone can give the components back to the user
+    if in_kind_context.redeem == 1 {
+        return Err(FruitBasketError::InvalidContextSide.into());
+    }
+    let authority = ctx.accounts.authority.key();
+    if in_kind_context.user != authority {
+        // the curator of the basket cancels the creations which keep it from being rebalanced
+        let group = ctx.accounts.group.load()?;
+        let basket = &ctx.accounts.fruitbasket;
+        if basket.creator != authority && !group.has_role(&authority, GroupRole::BasketCurator) {
+            return Err(FruitBasketError::AccountsMismatch.into());
+        }
+    }
+    if in_kind_context.reverting == 1 {
+        return Ok(());
+    }
+    in_kind_context.reverting = 1;
+    in_kind_context.deposited = in_kind_context.cursor;
+    in_kind_context.cursor = 0;
+    Ok(())
+}
+
+// transfer the components from first_component between the user and the basket vaults
+// remaining accounts are (token description, user token account, basket vault) for each component in basket order
+fn transfer_components_in_kind<'info>(ctx : &Context<'_, '_, '_, 'info, ProcessInKind<'info>>,
+                                      first_component : usize,
+                                      from_user : bool,
+                                      round_up : bool) -> ProgramResult {
+    let basket = &ctx.accounts.fruitbasket;
+    let amount = ctx.accounts.in_kind_context.amount;
+    let (_authority, bump) = Pubkey::find_program_address(&[FRUIT_BASKET_AUTHORITY], ctx.program_id);
+    let seeds = [&FRUIT_BASKET_AUTHORITY[..], &[bump]];
+    let signer = &[&seeds[..]];
+
+    for (index, accounts) in ctx.remaining_accounts.chunks(3).enumerate() {
+        let component = &basket.components[first_component + index];
+        let token_desc_ai = &accounts[0];
+        let user_token_account = &accounts[1];
+        let basket_vault = &accounts[2];
+
+        // token descriptions are trusted only if they are owned by the program
+        if token_desc_ai.owner != ctx.program_id {
+            return Err(FruitBasketError::ErrorDeserializeTokeDesc.into());
+        }
+        let token_desc = deserialize_token_descs(std::slice::from_ref(token_desc_ai))?.remove(0);
+        // components given back by anyone only go to the accounts of the user
+        if token_desc.token_mint != component.token_mint 
+            || basket_vault_address(&basket.key(), &component.token_mint, ctx.program_id) != basket_vault.key()
+            || token::accessor::mint(user_token_account)? != component.token_mint
+            || token::accessor::authority(user_token_account)? != ctx.accounts.user.key() {
+            return Err(FruitBasketError::AccountsMismatch.into());
+        }
+
+        let native_amount = component_native_amount(amount, component, token_desc.token_decimal, round_up);
+        if native_amount == 0 {
+            continue;
+        }
+        let accounts = if from_user {
+            token::Transfer {
+                from: user_token_account.clone(),
+                to: basket_vault.clone(),
+                authority: ctx.accounts.user.clone(),
+            }
+        } else {
+            token::Transfer {
+                from: basket_vault.clone(),
+                to: user_token_account.clone(),
+                authority: ctx.accounts.fruit_basket_authority.clone(),
+            }
+        };
+        let transfer_ctx = CpiContext::new_with_signer(ctx.accounts.token_program.clone(), accounts, signer);
+        token::transfer( transfer_ctx, native_amount)?;
+    }
+    Ok(())
+}
+
+// move the tokens kept by both baskets of a swap from the source vault to the destination vault
+// remaining accounts are (token description, source basket vault, destination basket vault) for each shared leg
+fn transfer_shared_legs<'info>(ctx : &Context<'_, '_, '_, 'info, FinalizeSwapContext<'info>>,
+                               trade_context : &BasketTradeContext,
+                               signer : &[&[&[u8]]]) -> ProgramResult {
+    let shared_legs = trade_context.leg_transfer_amounts.iter().enumerate().filter(|(_, amount)| **amount > 0);
+    let remaining_accounts = ctx.remaining_accounts;
+    if remaining_accounts.len() != shared_legs.clone().count() * 3 {
+        return Err(FruitBasketError::AccountsMismatch.into());
+    }
+    for (position, (token_index, amount)) in shared_legs.enumerate() {
+        let token_desc_ai = &remaining_accounts[position * 3];
+        let source_vault = &remaining_accounts[position * 3 + 1];
+        let destination_vault = &remaining_accounts[position * 3 + 2];
+        // token descriptions are trusted only if they are owned by the program
+        if token_desc_ai.owner != ctx.program_id {
+            return Err(FruitBasketError::ErrorDeserializeTokeDesc.into());
+        }
+        let token_desc = deserialize_token_descs(std::slice::from_ref(token_desc_ai))?.remove(0);
+        let token_mint = trade_context.token_mints[token_index];
+        if token_desc.token_mint != token_mint
+            || basket_vault_address(&trade_context.basket, &token_mint, ctx.program_id) != source_vault.key()
+            || basket_vault_address(&trade_context.destination_basket, &token_mint, ctx.program_id) != destination_vault.key() {
+            return Err(FruitBasketError::UnknownBasketVault.into());
+        }
+        let native_amount = native_token_amount(*amount, token_desc.token_decimal);
+        transfer_from_pool(&ctx.accounts.token_program, source_vault, destination_vault, &ctx.accounts.fruit_basket_authority, signer, native_amount)?;
+    }
+    Ok(())
+}
+
+fn change_authority<'info>(acc : &AccountInfo<'info>, 
+                          from : &AccountInfo<'info>, 
+                          to: Pubkey, 
+                          token_program: &AccountInfo<'info>, 
+                          seeds : Option<&[&[&[u8]]]>) -> ProgramResult{
+
+    let cpi_acc = SetAuthority {
+        account_or_mint: acc.clone(),
+        current_authority: from.clone(),
+    };
+    let mut cpi = CpiContext::new(token_program.clone(), cpi_acc);
+    if let Some(signer_seeds) = seeds {
+        cpi = cpi.with_signer(signer_seeds);
+    }
+    token::set_authority( cpi,  AuthorityType::AccountOwner, Some(to))
+}
+
+// pyth compatible price account of a basket
+fn basket_nav_address(basket : &Pubkey, program_id : &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[FRUIT_BASKET_NAV, &basket.to_bytes()], program_id)
+}
+
+// view the data of a basket nav account as a pyth price account
+fn nav_price_mut(data : &mut [u8]) -> std::result::Result<&mut Price, ProgramError> {
+    if data.len() < size_of::<Price>() {
+        return Err(ProgramError::AccountDataTooSmall);
+    }
+    // same cast as pyth_client::cast, account data is aligned on 8 bytes
+    Ok(unsafe { &mut *(data.as_mut_ptr() as *mut Price) })
+}
+
+// transfer tokens owned by the fruitbasket authority
+fn transfer_from_pool<'info>(token_program : &AccountInfo<'info>,
+                             from : &AccountInfo<'info>,
+                             to : &AccountInfo<'info>,
+                             authority : &AccountInfo<'info>,
+                             signer : &[&[&[u8]]],
+                             amount : u64) -> ProgramResult {
+    let accounts = token::Transfer {
+        from: from.clone(),
+        to: to.clone(),
+        authority: authority.clone(),
+    };
+    let transfer_ctx = CpiContext::new_with_signer(token_program.clone(), accounts, signer);
+    token::transfer( transfer_ctx, amount)
+}
+
+// fails if the operation is paused for the whole group or for the basket
+fn check_not_paused(group : &FruitBasketGroup, basket : &Basket, pause_flag : u8) -> ProgramResult {
+    if group.pause_flags & pause_flag != 0 || basket.pause_flags & pause_flag != 0 {
+        return Err(FruitBasketError::Paused.into());
+    }
+    Ok(())
+}
+
+// moving average after a new price, the new price weighs the number of slots elapsed over the window
+fn ema_step(ema : u64, price : u64, elapsed_slots : u64, window_slots : u64) -> u64 {
+    if ema == 0 || elapsed_slots >= window_slots {
+        return price;
+    }
+    let ema = ema as i128;
+    let delta = (price as i128 - ema) * elapsed_slots as i128 / window_slots as i128;
+    (ema + delta) as u64
+}
+
+// amount in basis points of a quote amount
+fn bps_of(amount : u64, bps : u64) -> u64 {
+    (amount as u128).checked_mul(bps.into()).unwrap().checked_div(10000).unwrap() as u64
+}
+
+// fee vault of a group for a quote token
+fn fee_vault_address(group : &Pubkey, quote_token_mint : &Pubkey, program_id : &Pubkey) -> Pubkey {
+    let (fee_vault, _bump) = Pubkey::find_program_address(&[FRUIT_BASKET_FEE_VAULT, &group.to_bytes(), &quote_token_mint.to_bytes()], program_id);
+    fee_vault
+}
+
+// description of a listed token
+fn token_desc_address(token_mint : &Pubkey, program_id : &Pubkey) -> Pubkey {
+    let (token_desc, _bump) = Pubkey::find_program_address(&[FRUIT_BASKET_TOKEN, &token_mint.to_bytes()], program_id);
+    token_desc
+}
+
+// serum open orders of a basket on a market
+fn basket_open_orders_address(basket : &Pubkey, market : &Pubkey, program_id : &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[FRUIT_BASKET_OPEN_ORDERS, &basket.to_bytes(), &market.to_bytes()], program_id)
+}
+
+// vault of a basket for a token
+fn basket_vault_address(basket : &Pubkey, token_mint : &Pubkey, program_id : &Pubkey) -> Pubkey {
+    let (basket_vault, _bump) = Pubkey::find_program_address(&[FRUIT_BASKET_VAULT, &basket.to_bytes(), &token_mint.to_bytes()], program_id);
+    basket_vault
+}
+
+// creator fee vault of a basket for a quote token
+fn creator_fee_vault_address(basket : &Pubkey, quote_token_mint : &Pubkey, program_id : &Pubkey) -> Pubkey {
+    let (creator_fee_vault, _bump) = Pubkey::find_program_address(&[FRUIT_BASKET_CREATOR_FEE_VAULT, &basket.to_bytes(), &quote_token_mint.to_bytes()], program_id);
+    creator_fee_vault
+}
+
+// convert a token amount with 6 decimals into native token amount
+fn native_token_amount(amount : u64, token_decimal : u8) -> u64 {
+    if token_decimal != 6 { 
+        amount.checked_mul(10u64.pow(token_decimal.into())).unwrap()
+            .checked_div(10u64.pow(6)).unwrap() 
+    } else {
+        amount
+    }
+}
+
+// inverse of native_token_amount, rounded down so that a leg never trades more than its amount
+fn context_token_amount(native_amount : u64, token_decimal : u8) -> u64 {
+    if token_decimal != 6 {
+        (native_amount as u128)
+            .checked_mul(10u128.pow(6)).unwrap()
+            .checked_div(10u128.pow(token_decimal.into())).unwrap() as u64
+    } else {
+        native_amount
+    }
+}
+
+// native amount of a component for an amount of basket tokens
+fn component_native_amount(amount : u64, component : &BasketComponentDescription, token_decimal : u8, round_up : bool) -> u64 {
+    let numerator = (amount as u128)
+                        .checked_mul(component.amount.into()).unwrap()
+                        .checked_mul(10u128.pow(token_decimal.into())).unwrap();
+    let denominator = 10u128.pow(component.decimal.into()).checked_mul(10u128.pow(6)).unwrap();
+    let native_amount = if round_up {
+        numerator.checked_add(denominator - 1).unwrap().checked_div(denominator).unwrap()
+    } else {
+        numerator.checked_div(denominator).unwrap()
+    };
+    native_amount as u64
+}
+
+// client order id used for a leg of a limit context, unique for each context and leg
+fn leg_client_order_id(trade_context : &Pubkey, token_index : usize) -> u64 {
+    let mut key_bytes = [0u8; 8];
+    key_bytes.copy_from_slice(&trade_context.to_bytes()[..8]);
+    u64::from_le_bytes(key_bytes).wrapping_add(token_index as u64).max(1)
+}
+
+// convert quote native amount for a whole token into price in serum lots
+// bids are rounded down and asks are rounded up so that the leg never trades worse than the limit
+fn leg_price_in_lots(leg_price : u64, token_decimal : u8, coin_lot_size : u64, pc_lot_size : u64, is_buy_side : bool) -> u64 {
+    let numerator = (leg_price as u128).checked_mul(coin_lot_size.into()).unwrap();
+    let denominator = 10u128.pow(token_decimal.into()).checked_mul(pc_lot_size.into()).unwrap();
+    let price = if is_buy_side {
+        numerator.checked_div(denominator).unwrap()
+    } else {
+        numerator.checked_add(denominator - 1).unwrap().checked_div(denominator).unwrap()
+    };
+    price as u64
+}
+
+impl<'info> ProcessTokenOnContext<'info>{
+    fn create_new_order(&self, 
+                        side:Side, 
+                        limit_price: u64,
+                        max_coin_qty: u64, 
+                        max_native_token : u64,
+                        seeds:&[&[&[u8]]]) -> ProgramResult {
+        let is_buy_side = side == Side::Bid;
+        let client_order_id = 0;
+        let limit = 65535;
+        let ctx_orders = CpiContext::new(self.dex_program.clone(), self.new_order_accounts(is_buy_side));
+        dex::new_order_v3(
+            ctx_orders.with_signer(seeds),
+            side,
+            NonZeroU64::new(limit_price).ok_or(FruitBasketError::EmptyOrder)?,
+            NonZeroU64::new(max_coin_qty).ok_or(FruitBasketError::EmptyOrder)?,
+            NonZeroU64::new(max_native_token).ok_or(FruitBasketError::EmptyOrder)?,
+            SelfTradeBehavior::DecrementTake,
+            OrderType::ImmediateOrCancel,
+            client_order_id,
+            limit,
+        )
+    }
+
+    fn create_post_only_order(&self,
+                        side:Side,
+                        limit_price: u64,
+                        max_coin_qty: u64,
+                        max_native_token : u64,
+                        client_order_id : u64,
+                        seeds:&[&[&[u8]]]) -> ProgramResult {
+        let is_buy_side = side == Side::Bid;
+        let limit = 65535;
+        let ctx_orders = CpiContext::new(self.dex_program.clone(), self.new_order_accounts(is_buy_side));
+        dex::new_order_v3(
+            ctx_orders.with_signer(seeds),
+            side,
+            NonZeroU64::new(limit_price).ok_or(FruitBasketError::EmptyOrder)?,
+            NonZeroU64::new(max_coin_qty).ok_or(FruitBasketError::EmptyOrder)?,
+            NonZeroU64::new(max_native_token).ok_or(FruitBasketError::EmptyOrder)?,
+            SelfTradeBehavior::DecrementTake,
+            OrderType::PostOnly,
             client_order_id,
             limit,
         )
     }
 
+    fn new_order_accounts(&self, is_buy_side : bool) -> dex::NewOrderV3<'info> {
+        dex::NewOrderV3 {
+            market: self.market.clone(),
+            open_orders: self.open_orders.clone(),
+            request_queue: self.request_queue.clone(),
+            event_queue: self.event_queue.clone(),
+            market_bids: self.bids.clone(),
+            market_asks: self.asks.clone(),
+            order_payer_token_account: if is_buy_side { self.quote_token_transaction_pool.to_account_info().clone() } else { self.token_pool.clone() },
+            open_orders_authority: self.fruit_basket_authority.clone(),
+            coin_vault: self.token_vault.clone(),
+            pc_vault: self.quote_token_vault.clone(),
+            token_program: self.token_program.clone(),
+            rent: self.rent.clone(),
+        }
+    }
+
+    fn cancel_order(&self, side : Side, order_id : u128, seeds : &[&[&[u8]]]) -> ProgramResult {
+        let cancel_accs = dex::CancelOrderV2 {
+            market: self.market.clone(),
+            market_bids: self.bids.clone(),
+            market_asks: self.asks.clone(),
+            open_orders: self.open_orders.clone(),
+            open_orders_authority: self.fruit_basket_authority.clone(),
+            event_queue: self.event_queue.clone(),
+        };
+        let cancel_ctx = CpiContext::new(self.dex_program.clone(), cancel_accs);
+        dex::cancel_order_v2(cancel_ctx.with_signer(seeds), side, order_id)
+    }
+
+    // find the serum order id of an order placed with the given client order id
+    fn find_resting_order(&self, client_order_id : u64) -> std::result::Result<Option<u128>, ProgramError> {
+        let market = Market::load(&self.market, self.dex_program.key)?;
+        let open_orders = market.load_orders_mut(&self.open_orders, None, self.dex_program.key, None, None)?;
+        let client_order_ids = open_orders.client_order_ids;
+        let orders = open_orders.orders;
+        let free_slot_bits = open_orders.free_slot_bits;
+        let slot = (0..128).find(|&slot| free_slot_bits & (1u128 << slot) == 0 && client_order_ids[slot] == client_order_id);
+        Ok(slot.map(|slot| orders[slot]))
+    }
+
+    // quantity in coin lots still resting on the orderbook for an order
+    // an order removed from the orderbook is completely filled
+    fn resting_quantity(&self, side : Side, order_id : u128) -> std::result::Result<u64, ProgramError> {
+        let market_state = MarketState::load(&self.market, self.dex_program.key)?;
+        let slab = if side == Side::Bid { market_state.load_bids_mut(&self.bids)? } else { market_state.load_asks_mut(&self.asks)? };
+        let quantity = (0..slab.capacity() as u32)
+                        .filter_map(|handle| slab.get(handle).and_then(|node| node.as_leaf()))
+                        .find(|leaf| leaf.order_id() == order_id)
+                        .map(|leaf| leaf.quantity())
+                        .unwrap_or(0);
+        Ok(quantity)
+    }
+
+    // a limit leg is placed once as a post only order, every later call accounts the new fills
+    // fills are accounted at the limit price as a post only order is always the maker
+    fn process_limit_leg(&self,
+                        trade_context : &mut BasketTradeContext,
+                        token_index : usize,
+                        coin_lot_size : u64,
+                        pc_lot_size : u64,
+                        seeds : &[&[&[u8]]]) -> ProgramResult {
+        let is_buy_side = trade_context.side == ContextSide::Buy;
+        let side : Side = if is_buy_side { Side::Bid } else { Side::Ask };
+        let client_order_id = leg_client_order_id(&self.trade_context.key(), token_index);
+        let limit_price = leg_price_in_lots(trade_context.leg_limit_prices[token_index],
+                                            self.token_desc.token_decimal,
+                                            coin_lot_size,
+                                            pc_lot_size,
+                                            is_buy_side);
+        if limit_price == 0 {
+            return Err(FruitBasketError::InvalidLimitPrice.into());
+        }
+
+        if trade_context.leg_order_quantities[token_index] == 0 {
+            let token_amount = native_token_amount(trade_context.token_amounts[token_index], self.token_desc.token_decimal);
+            let max_coin_qty = token_amount.checked_div(coin_lot_size).unwrap();
+            if max_coin_qty == 0 {
+                trade_context.tokens_treated[token_index] = 1;
+                return Ok(());
+            }
+            let max_native_token = if is_buy_side {
+                max_coin_qty.checked_mul(limit_price).unwrap().checked_mul(pc_lot_size).unwrap()
+            } else {
+                u64::MAX
+            };
+            self.create_post_only_order(side, limit_price, max_coin_qty, max_native_token, client_order_id, seeds)?;
+            // serum drops post only orders which would cross the orderbook
+            if self.find_resting_order(client_order_id)?.is_none() {
+                return Err(FruitBasketError::LimitOrderWouldCross.into());
+            }
+            trade_context.leg_order_quantities[token_index] = max_coin_qty;
+            trade_context.leg_filled_quantities[token_index] = 0;
+            return Ok(());
+        }
+
+        // order stays in open orders until serum events are consumed
+        let order_id = self.find_resting_order(client_order_id)?;
+        let remaining_quantity = match order_id {
+            Some(order_id) => self.resting_quantity(side, order_id)?,
+            None => 0,
+        };
+        self.account_limit_fills(trade_context, token_index, remaining_quantity, limit_price, coin_lot_size, pc_lot_size)?;
+        self.settle_accounts(seeds)?;
+        if order_id.is_none() {
+            trade_context.leg_order_quantities[token_index] = 0;
+            trade_context.tokens_treated[token_index] = 1;
+        }
+        Ok(())
+    }
+
+    // cancel the resting order of a leg, token amount is updated to the filled amount which should be reverted
+    fn cancel_limit_leg(&self,
+                        trade_context : &mut BasketTradeContext,
+                        token_index : usize,
+                        coin_lot_size : u64,
+                        pc_lot_size : u64,
+                        seeds : &[&[&[u8]]]) -> ProgramResult {
+        let is_buy_side = trade_context.side == ContextSide::Buy;
+        let side : Side = if is_buy_side { Side::Bid } else { Side::Ask };
+        let client_order_id = leg_client_order_id(&self.trade_context.key(), token_index);
+        let limit_price = leg_price_in_lots(trade_context.leg_limit_prices[token_index],
+                                            self.token_desc.token_decimal,
+                                            coin_lot_size,
+                                            pc_lot_size,
+                                            is_buy_side);
+        let remaining_quantity = match self.find_resting_order(client_order_id)? {
+            Some(order_id) => {
+                let remaining_quantity = self.resting_quantity(side, order_id)?;
+                if remaining_quantity > 0 {
+                    self.cancel_order(side, order_id, seeds)?;
+                }
+                remaining_quantity
+            },
+            None => 0,
+        };
+        self.account_limit_fills(trade_context, token_index, remaining_quantity, limit_price, coin_lot_size, pc_lot_size)?;
+        self.settle_accounts(seeds)?;
+        let filled_amount = trade_context.leg_filled_quantities[token_index].checked_mul(coin_lot_size).unwrap();
+        trade_context.token_amounts[token_index] = context_token_amount(filled_amount, self.token_desc.token_decimal);
+        trade_context.leg_order_quantities[token_index] = 0;
+        Ok(())
+    }
+
+    fn account_limit_fills(&self,
+                        trade_context : &mut BasketTradeContext,
+                        token_index : usize,
+                        remaining_quantity : u64,
+                        limit_price : u64,
+                        coin_lot_size : u64,
+                        pc_lot_size : u64) -> ProgramResult {
+        let filled_quantity = trade_context.leg_order_quantities[token_index]
+                                .checked_sub(remaining_quantity)
+                                .ok_or(FruitBasketError::InvalidLegAmount)?;
+        let new_fills = filled_quantity
+                                .checked_sub(trade_context.leg_filled_quantities[token_index])
+                                .ok_or(FruitBasketError::InvalidLegAmount)?;
+        trade_context.leg_filled_quantities[token_index] = filled_quantity;
+        // amounts of the context stay in 6 decimals units, the fills are in lots of native tokens
+        let filled_amount = context_token_amount(filled_quantity.checked_mul(coin_lot_size).unwrap(), self.token_desc.token_decimal);
+        trade_context.token_amounts[token_index] = trade_context.initial_token_amounts[token_index]
+                                                    .checked_sub(filled_amount)
+                                                    .ok_or(FruitBasketError::InvalidLegAmount)?;
+        let quote_amount = new_fills.checked_mul(limit_price).unwrap().checked_mul(pc_lot_size).unwrap();
+        trade_context.usdc_amount_left = if trade_context.side == ContextSide::Buy {
+            trade_context.usdc_amount_left.checked_sub(quote_amount).ok_or(FruitBasketError::InvalidLegAmount)?
+        } else {
+            trade_context.usdc_amount_left.checked_add(quote_amount).unwrap()
+        };
+        Ok(())
+    }
+
     fn settle_accounts(&self,
                         seeds : &[&[&[u8]]]) -> ProgramResult {
         let settle_accs = dex::SettleFunds {
@@ -572,44 +2821,54 @@ impl<'info> ProcessTokenOnContext<'info>{
 
 
 impl Basket {
-    pub fn update_price(&mut self, token_descs : &Vec<&TokenDescription>) -> ProgramResult {
+    // update the price of components whose token description is given
+    // basket price is updated once all the components are priced
+    // caches older than the maximum price age are rejected
+    pub fn update_price(&mut self, token_descs : &Vec<&TokenDescription>, current_slot : u64, max_price_age_slots : u64) -> ProgramResult {
+        if !self.is_complete() {
+            return Err(FruitBasketError::BasketIncomplete.into());
+        }
         let mut price  = I80F48::from_num(0);
         let mut confidence  = I80F48::from_num(0);
         let decimal : u8 = 6;
+        let mut nb_updated = 0;
         
-        for i in 0..self.number_of_components {
-            let comp = self.components[i as usize];
+        for i in 0..self.components.len() {
+            let comp = self.components[i];
             let position = token_descs.iter().position(|x| x.token_mint == comp.token_mint);
-            if position == None {
-                return Err(FruitBasketError::TokenNotFound.into());
-            }
-            let token_index = position.unwrap();
-            let cache = token_descs[token_index].cache;
-            
-            let mut comp_price = cache.last_price.checked_mul(comp.amount).unwrap().checked_div(10u64.pow(comp.decimal as u32)).unwrap();
-            let mut comp_conf = cache.last_confidence.checked_mul(comp.amount).unwrap().checked_div(10u64.pow(comp.decimal as u32)).unwrap();
-
-            //pyth decimal is negative usual decimal
-            let pyth_decimal = if cache.last_exp >= 0 { 0 } else {-cache.last_exp as u8};
-            
-            if pyth_decimal != decimal {
-                if pyth_decimal > decimal {
-                    let exp : u32 = (pyth_decimal - decimal) as u32;
-                    comp_price = comp_price.checked_div(10u64.pow(exp)).unwrap();
-                    comp_conf = comp_conf.checked_div(10u64.pow(exp)).unwrap();
-                }
-                else {
-                    let exp : u32 = (decimal - pyth_decimal) as u32;
-                    comp_price = comp_price.checked_mul(10u64.pow(exp)).unwrap();
-                    comp_conf = comp_conf.checked_mul(10u64.pow(exp)).unwrap();
+            if let Some(token_index) = position {
+                let cache = token_descs[token_index].cache;
+                if current_slot.saturating_sub(cache.publish_slot) > max_price_age_slots {
+                    return Err(FruitBasketError::StalePrice.into());
                 }
+                self.component_prices[i] = ComponentPrice {
+                    price : cache.price_in_quote_native(),
+                    confidence : cache.confidence_in_quote_native(),
+                    publish_slot : cache.publish_slot,
+                };
+                nb_updated += 1;
+            }
+            let comp_price = self.component_prices[i];
+            if comp_price.price == 0 {
+                continue;
             }
-            price = price.checked_add( I80F48::from_num(comp_price) ).unwrap();
-            confidence = confidence.checked_add(I80F48::from_num(comp_conf) ).unwrap();
+            let unit = 10u128.pow(comp.decimal as u32);
+            let comp_value = (comp_price.price as u128).checked_mul(comp.amount.into()).unwrap().checked_div(unit).unwrap();
+            let comp_conf = (comp_price.confidence as u128).checked_mul(comp.amount.into()).unwrap().checked_div(unit).unwrap();
+            price = price.checked_add( I80F48::from_num(comp_value) ).unwrap();
+            confidence = confidence.checked_add( I80F48::from_num(comp_conf) ).unwrap();
+        }
+        if nb_updated == 0 {
+            return Err(FruitBasketError::TokenNotFound.into());
+        }
+        if self.component_prices.iter().any(|x| x.price == 0) {
+            msg!("basket components priced partially");
+            return Ok(());
         }
         self.last_price = price.to_num::<u64>();
         self.confidence = confidence.to_num::<u64>();
         self.decimal = decimal;
+        self.price_slot = self.component_prices.iter().map(|x| x.publish_slot).min().unwrap_or(0);
         let msg2= format!("total price {} confidence {}", price.to_num::<u64>(), confidence.to_num::<u64>());
             msg!(&msg2[..]);
         if self.last_price <= 0 {
@@ -618,3 +2877,83 @@ impl Basket {
         Ok(())
     }
 }
+
+impl FruitBasketGroup {
+    // quote currency accepted by the group for a mint
+    pub fn quote_currency(&self, mint : &Pubkey) -> std::result::Result<QuoteCurrency, ProgramError> {
+        self.quote_currencies[..self.nb_quote_currencies as usize].iter()
+            .find(|x| x.mint == *mint)
+            .copied()
+            .ok_or_else(|| FruitBasketError::UnknownQuoteCurrency.into())
+    }
+
+    // owner holds every role, a role can also be delegated to another key
+    pub fn has_role(&self, key : &Pubkey, role : GroupRole) -> bool {
+        let member = match role {
+            GroupRole::TokenLister => self.token_lister,
+            GroupRole::BasketCurator => self.basket_curator,
+            GroupRole::Pauser => self.pauser,
+            GroupRole::FeeManager => self.fee_manager,
+        };
+        *key == self.owner || (member != Pubkey::default() && *key == member)
+    }
+}
+
+impl QuoteCurrency {
+    // convert an amount in base mint native units into quote native units
+    pub fn in_quote_native(&self, amount : u64) -> std::result::Result<u64, ProgramError> {
+        let price = self.price;
+        if price == 0 {
+            return Err(FruitBasketError::QuoteCurrencyNotPriced.into());
+        }
+        Ok((amount as u128)
+            .checked_mul(10u128.pow(self.decimal.into())).unwrap()
+            .checked_div(price.into()).unwrap() as u64)
+    }
+}
+
+impl TokenDescription {
+    // market and open orders of the token for a quote currency
+    pub fn market_for(&self, quote_mint : &Pubkey, base_mint : &Pubkey) -> Option<(Pubkey, Pubkey)> {
+        if quote_mint == base_mint {
+            return Some((self.market, self.token_open_orders));
+        }
+        self.quote_markets.iter()
+            .find(|x| x.quote_mint == *quote_mint)
+            .map(|x| (x.market, x.open_orders))
+    }
+}
+
+impl Cache {
+    // cached price of a whole token in quote native units (6 decimals)
+    pub fn price_in_quote_native(&self) -> u64 {
+        self.scale_to_quote_native(self.last_price)
+    }
+
+    // cached confidence of a whole token in quote native units (6 decimals)
+    pub fn confidence_in_quote_native(&self) -> u64 {
+        self.scale_to_quote_native(self.last_confidence)
+    }
+
+    // worst price for a leg, oracle price moved by the confidence and the slippage band
+    pub fn price_with_slippage(&self, is_buy_side : bool, slippage_bps : u64) -> u64 {
+        let price = self.price_in_quote_native();
+        let slippage = (price as u128).checked_mul(slippage_bps.into()).unwrap().checked_div(10000).unwrap() as u64;
+        let band = self.confidence_in_quote_native().checked_add(slippage).unwrap();
+        if is_buy_side {
+            price.checked_add(band).unwrap()
+        } else {
+            price.saturating_sub(band)
+        }
+    }
+
+    fn scale_to_quote_native(&self, value : u64) -> u64 {
+        let decimal : u8 = 6;
+        let pyth_decimal = if self.last_exp >= 0 { 0 } else {-self.last_exp as u8};
+        if pyth_decimal > decimal {
+            value.checked_div(10u64.pow((pyth_decimal - decimal) as u32)).unwrap()
+        } else {
+            value.checked_mul(10u64.pow((decimal - pyth_decimal) as u32)).unwrap()
+        }
+    }
+}
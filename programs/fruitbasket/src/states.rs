@@ -5,26 +5,114 @@ use crate::*;
 #[account(zero_copy)]
 pub struct FruitBasketGroup {
     pub owner: Pubkey,              // owner
+    pub pending_owner: Pubkey,      // owner proposed by the current owner, until it accepts the ownership
+    pub token_lister: Pubkey,       // can add and configure tokens, default if only the owner can
+    pub basket_curator: Pubkey,     // can create baskets, default if anyone can
+    pub pauser: Pubkey,             // can pause the group, default if only the owner can
+    pub fee_manager: Pubkey,        // can change fees and withdraw them, default if only the owner can
+    pub pause_flags: u8,            // PAUSE_* flags applied to every basket of the group
+    pub audit_auto_pause: u8,       // 1 if new contexts of a basket are paused when an audit finds it under collateralised
     pub token_count: u64,            // number of tokens that can be handled
     pub base_mint: Pubkey,          // usdc public key
     pub base_mint_name : [u8; 10],  // name of base / USDC
     pub number_of_baskets : u64,    // number of baskets currenly create
     pub nb_users: u8,              // number of users registered
     pub quote_token_transaction_pool : Pubkey,
+    pub leg_slippage_bps : u64,     // allowed slippage from oracle price for each leg in basis points
+    pub mint_fee_bps : u64,         // fee taken on quote spent when buying a basket in basis points
+    pub redeem_fee_bps : u64,       // fee taken on quote received when selling a basket in basis points
+    pub max_creator_fee_bps : u64,  // maximum fee a basket creator can ask in basis points
+    pub max_price_age_slots : u64,  // caches older than this number of slots cannot be used to price baskets
+    pub ema_window_slots : u64,     // number of slots over which the price moving averages are computed
+    pub ema_bound : u8,             // 1 if trade contexts are bounded by the moving average of the basket price
+    pub nb_quote_currencies : u8,   // number of quote currencies accepted by the group
+    pub quote_currencies : [QuoteCurrency; 4], // MAX_QUOTE_CURRENCIES, first quote currency is the base mint
+}
+
+/// Roles which can be delegated by the owner of a group
+#[derive(AnchorSerialize, AnchorDeserialize, Copy, Clone, PartialEq, Eq)]
+pub enum GroupRole {
+    TokenLister,
+    BasketCurator,
+    Pauser,
+    FeeManager,
+}
+
+/// Quote currency accepted by a group to buy and sell baskets
+#[zero_copy]
+pub struct QuoteCurrency {
+    pub mint : Pubkey,
+    pub transaction_pool : Pubkey,  // pool where quote tokens of the trade contexts are kept
+    pub price_oracle : Pubkey,      // pyth price of the quote currency, default for the base mint
+    pub price : u64,                // price of a whole quote token in base mint native units (6 decimals)
+    pub decimal : u8,
 }
 
 /// state to define a basket
+/// Components are stored in a vector, the account is allocated for the number of components given at creation.
+/// Components can be added in multiple transactions, the basket can be traded once all the components are added.
 #[account()]
 pub struct Basket {
     pub magic : u32,
     pub basket_name: [u8; 128],      // basket name
     pub desc: [u8; 256],
-    pub number_of_components: u8,    // basket description
-    pub components : [BasketComponentDescription; 10],
+    pub number_of_components: u8,    // number of components the basket is allocated for
+    pub creator : Pubkey,           // creator of the basket, only the creator can add components
+    pub group : Pubkey,             // group of the basket
+    pub creator_fee_bps : u64,      // fee given to the creator on each buy and sell in basis points
+    pub components : Vec<BasketComponentDescription>,
+    pub component_prices : Vec<ComponentPrice>, // last cached price of each component
     pub basket_mint : Pubkey,
     pub last_price : u64,
     pub confidence : u64,
     pub decimal : u8,               // always 6
+    pub price_slot : u64,           // oldest publish slot of the component prices
+    pub book_buy_price : u64,       // price of a whole basket when buying book_price_amount on serum
+    pub book_sell_price : u64,      // price of a whole basket when selling book_price_amount on serum
+    pub book_price_amount : u64,    // amount of basket tokens for which the orderbooks were walked
+    pub book_price_slot : u64,      // slot at which the orderbooks were walked
+    pub ema_price : u64,            // moving average of the basket price
+    pub ema_slot : u64,             // slot at which the moving average was updated
+    pub pause_flags : u8,           // PAUSE_* flags of the basket
+    pub pending_components : Vec<BasketComponentDescription>, // components swapped in when the rebalance is finalized
+    pub rebalance_context : Pubkey, // context rebalancing the basket, default if the basket is not rebalancing
+    pub solvency_bps : u64,         // lowest ratio between vault balance and required backing of a component at the last audit
+    pub audit_timestamp : i64,      // time of the last audit, 0 if the basket was never audited
+    pub open_contexts : u64,        // buy, sell and swap contexts of the basket which are not finalized yet
+    pub book_cursor : u8,           // components walked for the next book prices, 0 if no walk is pending
+    pub book_pending_amount : u64,  // amount of basket tokens of the pending walk
+    pub book_pending_slot : u64,    // slot at which the pending walk started
+    pub book_pending_buy_cost : u64,       // quote native cost of the components walked so far
+    pub book_pending_sell_proceeds : u64,  // quote native proceeds of the components walked so far
+    pub audit_cursor : u8,          // components audited for the next solvency, 0 if no audit is pending
+    pub audit_pending_supply : u64, // supply of the basket when the pending audit started
+    pub audit_pending_slot : u64,   // slot at which the pending audit started
+    pub audit_pending_solvency_bps : u64, // lowest solvency of the components audited so far
+}
+
+impl Basket {
+    pub fn space(number_of_components : usize) -> usize {
+        4 + 128 + 256 + 1 + 32 + 32 + 8
+            + 4 + number_of_components * (32 + 8 + 1)
+            + 4 + number_of_components * (8 + 8 + 8)
+            + 32 + 8 + 8 + 1 + 8
+            + 8 + 8 + 8 + 8
+            + 8 + 8
+            + 1
+            + 4 + number_of_components * (32 + 8 + 1) + 32
+            + 8 + 8
+            + 8
+            + 1 + 8 + 8 + 8 + 8
+            + 1 + 8 + 8 + 8
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.components.len() == self.number_of_components as usize
+    }
+
+    pub fn is_rebalancing(&self) -> bool {
+        self.rebalance_context != Pubkey::default()
+    }
 }
 
 #[account()]
@@ -32,15 +120,28 @@ pub struct TokenDescription
 {
     pub magic : u32,
     pub id : u64,
+    pub group : Pubkey,         // group which listed the token, only its roles configure it
     pub token_mint: Pubkey,     // token mints
     pub price_oracle: Pubkey,   // oracle keys
     pub product_oracle: Pubkey, // product info keys
     pub token_name: [u8; 10],      // token names
-    pub token_pool : Pubkey, // pool for each token 
+    pub token_pool : Pubkey, // pool given when the token was added, baskets keep their tokens in their own vaults
     pub token_decimal : u8,     // number of decimal places for token (1 SOL -> 10^9 lamports = 9 decimal places )
-    pub token_open_orders : Pubkey,
-    pub market : Pubkey,
+    pub token_open_orders : Pubkey, // open orders given when the token was added, baskets trade with their own open orders
+    pub market : Pubkey,            // market quoted in the base mint of the group
     pub cache : Cache,
+    pub max_confidence_bps : u64,   // maximum oracle confidence relative to the price in basis points
+    pub max_deviation_bps : u64,    // maximum move from the cached price in basis points, 0 to disable
+    pub quote_markets : [QuoteMarket; 4], // MAX_QUOTE_CURRENCIES markets quoted in the other quote currencies
+    pub paused : u8,                // 1 if legs of this token cannot be processed, except for reverting contexts
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default, Copy)]
+#[repr(C)]
+pub struct QuoteMarket {
+    pub quote_mint : Pubkey,
+    pub market : Pubkey,
+    pub open_orders : Pubkey,   // open orders given when the market was added, baskets trade with their own open orders
 }
 
 
@@ -50,6 +151,10 @@ pub struct Cache {
     pub last_price: u64,
     pub last_exp: i32,
     pub last_confidence: u64,
+    pub publish_slot: u64,          // slot at which the oracle price was published
+    pub last_update_slot: u64,      // slot at which the cache was updated
+    pub last_update_timestamp: i64,
+    pub ema_price: u64,             // moving average of the price in quote native units (6 decimals)
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Default, Copy)]
@@ -60,14 +165,37 @@ pub struct BasketComponentDescription{
     pub decimal : u8,
 }
 
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default, Copy)]
+#[repr(C)]
+pub struct ComponentPrice {
+    pub price : u64,        // price of a whole token in quote native units
+    pub confidence : u64,   // confidence of a whole token in quote native units
+    pub publish_slot : u64, // publish slot of the oracle price
+}
+
 #[derive(AnchorSerialize, AnchorDeserialize, Copy, Clone, PartialEq, Eq)]
 #[repr(u8)]
 pub enum ContextSide {
     Buy,
     Sell,
+    // source basket is swapped for destination basket, each leg is either bought or sold
+    Swap,
+    // basket backing is traded from its components to its pending components
+    Rebalance,
 }
 
-#[account(zero_copy)]
+#[derive(AnchorSerialize, AnchorDeserialize, Copy, Clone, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ContextOrderType {
+    // every leg is swept on serum with an immediate or cancel order
+    Market,
+    // every leg rests on serum as a post only order until filled or cancelled by the user
+    Limit,
+}
+
+/// Trade context, the account is allocated for the number of legs i.e the components of the basket,
+/// or the components of both baskets for a swap context.
+#[account()]
 pub struct BasketTradeContext
 {
     // to find current trade context which are bieng processed by offchain programs.
@@ -81,17 +209,89 @@ pub struct BasketTradeContext
     pub usdc_amount_left : u64,
     pub quote_token_account: Pubkey,
     pub basket_token_account : Pubkey,
+    // user who created the context
+    pub user : Pubkey,
+    // quote currency used to buy or sell the basket
+    pub quote_token_mint : Pubkey,
     // contains number of usdc deposited by user
     pub initial_usdc_transfer_amount : u64,
+    // part of the deposit of a buy put aside for the fees, legs cannot spend it
+    pub fee_reserve : u64,
     pub created_on : u64,
-    pub token_mints : [Pubkey; 10],
+    pub order_type : ContextOrderType,
+    // basket price requested by the user for limit contexts
+    pub limit_price : u64,
+    pub token_mints : Vec<Pubkey>,
     // tracks number of tokens to be treated
-    pub token_amounts: [u64; 10],
+    pub token_amounts: Vec<u64>,
     // initial amount of tokens to be transfered
-    pub initial_token_amounts: [u64; 10],
-    pub tokens_treated: [u8; 10],
+    pub initial_token_amounts: Vec<u64>,
+    pub tokens_treated: Vec<u8>,
+    // quote native amount for one whole token at which each leg is placed on serum
+    pub leg_limit_prices : Vec<u64>,
+    // coin lots placed on serum for each leg, 0 if no order is resting
+    pub leg_order_quantities : Vec<u64>,
+    // coin lots of the resting order already accounted as filled
+    pub leg_filled_quantities : Vec<u64>,
+    // side of each leg, same as the context side except for swap contexts
+    pub leg_sides : Vec<ContextSide>,
+    // tokens moved from the source basket vault to the destination basket vault when a swap is finalized
+    pub leg_transfer_amounts : Vec<u64>,
+    // quote spent by buy legs and received by sell legs, swap fees are taken on them
+    pub quote_spent : u64,
+    pub quote_received : u64,
+    // basket bought by a swap context, default for buy and sell contexts
+    pub destination_basket : Pubkey,
+    pub destination_basket_token_account : Pubkey,
+    pub destination_amount : u64,
+    // fees when the context was created, the context is finalized with them even if the group changes its fees
+    pub mint_fee_bps : u64,
+    pub redeem_fee_bps : u64,
+    pub creator_fee_bps : u64,
+    // creator fee of the destination basket of a swap
+    pub destination_creator_fee_bps : u64,
+}
+
+impl BasketTradeContext {
+    pub fn space(number_of_components : usize) -> usize {
+        let vectors = 9 * 4;
+        let leg_size = 32 + 8 + 8 + 1 + 8 + 8 + 8 + 1 + 8;
+        4 + 1 + 32 + 1 + 8 + 8 + 32 + 32 + 32 + 32 + 8 + 8 + 8 + 1 + 8
+            + 8 + 8 + 32 + 32 + 8
+            + 8 + 8 + 8 + 8
+            + vectors + number_of_components * leg_size
+    }
+}
+
+/// Basket tokens created or redeemed in kind, the components are moved in basket order over several transactions.
+#[account()]
+pub struct InKindContext
+{
+    pub magic : u32,
+    pub basket : Pubkey,
+    // user who gives or gets back the components
+    pub user : Pubkey,
+    pub basket_token_account : Pubkey,
+    // amount of basket tokens
+    pub amount : u64,
+    // 1 if the basket tokens were burned at init and the components are given back to the user
+    pub redeem : u8,
+    // components moved so far
+    pub cursor : u8,
+    // 1 once a creation is cancelled, the components deposited so far are given back to the user
+    pub reverting : u8,
+    // components deposited when the creation was cancelled
+    pub deposited : u8,
+}
+
+impl InKindContext {
+    // components to move before the context is finalized
+    pub fn components_to_move(&self, number_of_components : usize) -> usize {
+        if self.reverting == 1 { self.deposited as usize } else { number_of_components }
+    }
 }
 
 pub const BASKET_TRADE_CONTEXT_MAGIC : u32 = 0xba873cfd;
+pub const IN_KIND_CONTEXT_MAGIC : u32 = 0x6b1d4e52;
 pub const BASKET_DESC_MAGIC : u32 = 0xa435efbb;
 pub const TOKEN_DESC_MAGIC : u32 = 0xcde78987;
\ No newline at end of file
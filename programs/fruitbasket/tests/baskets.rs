@@ -0,0 +1,231 @@
+// Baskets created by the group owner and completed by their creator
+mod common;
+
+use anchor_lang::solana_program::entrypoint::MAX_PERMITTED_DATA_INCREASE;
+use common::assert_program_error;
+use common::basket::{BasketFixture, BASKET_COMPONENTS, BASKET_PRICE};
+use common::group::{GroupFixture, TOKEN_PRICES};
+use fruitbasket::errors::FruitBasketError;
+use fruitbasket::states::{Basket, BasketComponentDescription, BasketTradeContext, ContextSide};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signer;
+
+// slots over which the chunks of an audit are accepted
+const MAX_AUDIT_WINDOW_SLOTS : u64 = 150;
+
+// largest basket whose account and trade contexts can be created by the program
+fn max_components() -> usize {
+    (2..).take_while(|x| 8 + Basket::space(*x) <= MAX_PERMITTED_DATA_INCREASE
+                        && 8 + BasketTradeContext::space(*x) <= MAX_PERMITTED_DATA_INCREASE)
+        .last()
+        .unwrap()
+}
+
+// components of distinct tokens listed for them, their markets are never traded
+async fn listed_components(fixture : &mut GroupFixture, count : usize) -> Vec<BasketComponentDescription> {
+    let mut components = Vec::new();
+    for _ in 0..count {
+        let token = fixture.list_unbooked_token(TOKEN_PRICES[0], 6).await;
+        components.push(BasketComponentDescription { token_mint : fixture.tokens[token].mint, amount : 1_000_000, decimal : 6 });
+    }
+    components
+}
+
+#[tokio::test]
+async fn components_added_up_to_account_size() {
+    let mut fixture = GroupFixture::new().await;
+    let max_components = max_components();
+    assert!(max_components > 10);
+    let all_components = listed_components(&mut fixture, max_components + 1).await;
+    // the account of a larger basket cannot be created
+    assert!(fixture.add_basket(1, max_components as u8 + 1, all_components[..2].to_vec()).await.is_err());
+
+    let (basket, _) = fixture.add_complete_basket(1, all_components[..max_components].to_vec()).await;
+    assert_program_error(fixture.add_basket_components(&basket, all_components[max_components..].to_vec()).await,
+                         FruitBasketError::ComponentCountOverflow);
+
+    let basket = fruitbasket_client::state::Basket::decode(&fixture.test.account_data(&basket).await).unwrap();
+    assert!(basket.is_complete());
+    let mints = basket.components.iter().map(|x| x.token_mint).collect::<Vec<_>>();
+    assert_eq!(mints, all_components[..max_components].iter().map(|x| x.token_mint).collect::<Vec<_>>());
+}
+
+// a token backs a single component of a basket, and components are tokens listed in the group of the basket
+#[tokio::test]
+async fn duplicate_and_unlisted_components() {
+    let mut fixture = GroupFixture::new().await;
+    let components = fixture.tokens.iter()
+        .map(|x| BasketComponentDescription { token_mint : x.mint, amount : 1_000_000, decimal : 6 })
+        .collect::<Vec<_>>();
+    let unlisted = BasketComponentDescription { token_mint : Pubkey::new_unique(), amount : 1_000_000, decimal : 6 };
+    let duplicate = vec![components[0], components[1], components[0]];
+    assert_program_error(fixture.add_basket(1, 3, duplicate).await, FruitBasketError::DuplicateComponent);
+    assert_program_error(fixture.add_basket(1, 3, vec![components[0], unlisted]).await, FruitBasketError::ComponentNotListed);
+
+    // a token listed by another group
+    let (foreign_owner, foreign_group) = fixture.create_foreign_group().await;
+    let mint = fixture.list_foreign_token(&foreign_owner, &foreign_group).await;
+    let foreign = BasketComponentDescription { token_mint : mint, amount : 1_000_000, decimal : 6 };
+    assert_program_error(fixture.add_basket(1, 3, vec![components[0], foreign]).await, FruitBasketError::ComponentNotListed);
+
+    // components added later are checked against those of the basket
+    let (basket, _) = fixture.add_basket(1, 3, components[..2].to_vec()).await.unwrap();
+    assert_program_error(fixture.add_basket_components(&basket, vec![components[1]]).await, FruitBasketError::DuplicateComponent);
+    assert_program_error(fixture.add_basket_components(&basket, vec![unlisted]).await, FruitBasketError::ComponentNotListed);
+    fixture.add_basket_components(&basket, vec![components[2]]).await.unwrap();
+}
+
+// a complete basket is traded once its price is updated from the caches of its components
+#[tokio::test]
+async fn unpriced_basket() {
+    let mut fixture = BasketFixture::new().await;
+    let components = fixture.tokens.iter()
+        .map(|x| BasketComponentDescription { token_mint : x.mint, amount : 1_000_000, decimal : 6 })
+        .collect::<Vec<_>>();
+    let (basket, basket_mint) = fixture.add_basket(1, 3, components).await.unwrap();
+    let user = fixture.user.pubkey();
+    let user_basket = fixture.test.create_token_account(&basket_mint, &user).await;
+
+    fixture.basket = basket;
+    fixture.basket_mint = basket_mint;
+    fixture.user_basket = user_basket;
+    assert_program_error(fixture.init_trade_context(ContextSide::Buy, 1_000_000, BASKET_PRICE).await, FruitBasketError::BasketNotPriced);
+}
+
+// components of a large basket are moved in kind over several transactions, in the order of the basket
+#[tokio::test]
+async fn in_kind_by_chunks() {
+    let mut fixture = GroupFixture::new().await;
+    let user = fixture.user.pubkey();
+    let (basket, basket_mint) = fixture.add_cycled_basket(1, 12).await;
+    let user_tokens = fixture.user_token_accounts(10_000_000).await;
+    let user_basket = fixture.test.create_token_account(&basket_mint, &user).await;
+
+    fixture.init_in_kind_context(&basket, &user_basket, 1_000_000, false).await.unwrap();
+    assert_program_error(fixture.process_in_kind(&basket, &user_tokens, 4, 4, true).await, FruitBasketError::InKindMismatch);
+    fixture.process_in_kind(&basket, &user_tokens, 0, 4, true).await.unwrap();
+    assert_program_error(fixture.finalize_in_kind_context(&basket, &user_basket).await, FruitBasketError::NotAllTokensTreatedBeforeFinalize);
+    // a new blockhash keeps the chunk from being deduplicated with the failed one
+    fixture.test.context.get_new_latest_blockhash().await.unwrap();
+    fixture.process_in_kind(&basket, &user_tokens, 4, 4, true).await.unwrap();
+    fixture.process_in_kind(&basket, &user_tokens, 8, 4, true).await.unwrap();
+    fixture.finalize_in_kind_context(&basket, &user_basket).await.unwrap();
+    assert_eq!(fixture.test.token_balance(&user_basket).await, 1_000_000);
+    let basket_tokens = &user_tokens[3..];
+    for account in basket_tokens {
+        assert_eq!(fixture.test.token_balance(account).await, 9_000_000);
+    }
+
+    fixture.redeem_in_kind(&basket, &user_basket, &user_tokens, 1_000_000).await.unwrap();
+    assert_eq!(fixture.test.token_balance(&user_basket).await, 0);
+    for account in basket_tokens {
+        assert_eq!(fixture.test.token_balance(account).await, 10_000_000);
+    }
+}
+
+// a pending creation in kind blocks the rebalance of its basket until it is cancelled, its deposits are then given back
+#[tokio::test]
+async fn in_kind_cancelled_by_curator() {
+    let mut fixture = BasketFixture::new().await;
+    let (basket, user_basket) = (fixture.basket, fixture.user_basket);
+    let user_tokens = fixture.user_token_accounts(10_000_000).await;
+    let new_components = fixture.tokens.iter().zip(BASKET_COMPONENTS.iter().rev())
+        .map(|(token, amount)| BasketComponentDescription { token_mint : token.mint, amount : *amount, decimal : 6 })
+        .collect::<Vec<_>>();
+
+    fixture.init_in_kind_context(&basket, &user_basket, 1_000_000, false).await.unwrap();
+    fixture.process_in_kind(&basket, &user_tokens, 0, 2, true).await.unwrap();
+    assert_program_error(fixture.init_rebalance_context(new_components.clone()).await, FruitBasketError::BasketHasOpenContexts);
+    // components are only taken with the signature of the user
+    assert_program_error(fixture.process_in_kind(&basket, &user_tokens, 2, 1, false).await, FruitBasketError::Unauthorized);
+    let stranger = fixture.test.create_wallet(1_000_000_000).await;
+    assert_program_error(fixture.cancel_in_kind_context(&basket, &stranger).await, FruitBasketError::AccountsMismatch);
+    // the owner of the group created the basket of the fixture
+    let curator = fixture.owner();
+    fixture.cancel_in_kind_context(&basket, &curator).await.unwrap();
+    fixture.process_in_kind(&basket, &user_tokens, 0, 2, false).await.unwrap();
+    fixture.finalize_in_kind_context(&basket, &user_basket).await.unwrap();
+    assert_eq!(fixture.test.token_balance(&user_basket).await, 0);
+    for account in &user_tokens {
+        assert_eq!(fixture.test.token_balance(account).await, 10_000_000);
+    }
+
+    // a new blockhash keeps the rebalance from being deduplicated with the failed one
+    fixture.test.context.get_new_latest_blockhash().await.unwrap();
+    fixture.init_rebalance_context(new_components).await.unwrap();
+}
+
+// orderbooks of a large basket are walked over several transactions, prices are updated by the last one
+#[tokio::test]
+async fn book_price_walked_by_chunks() {
+    let mut fixture = GroupFixture::new().await;
+    let (basket, _) = fixture.add_cycled_basket(1, 12).await;
+    let book_price = |data : &[u8]| fruitbasket_client::state::Basket::decode(data).unwrap().book_price;
+
+    fixture.update_basket_book_price(&basket, 100_000, 0, 4).await.unwrap();
+    fixture.update_basket_book_price(&basket, 100_000, 4, 4).await.unwrap();
+    let pending = book_price(&fixture.test.account_data(&basket).await);
+    assert_eq!((pending.cursor, pending.amount), (8, 0));
+    // the walk is continued from its cursor with the same amount
+    fixture.test.context.get_new_latest_blockhash().await.unwrap();
+    assert_program_error(fixture.update_basket_book_price(&basket, 100_000, 4, 4).await, FruitBasketError::BookWalkMismatch);
+    assert_program_error(fixture.update_basket_book_price(&basket, 200_000, 8, 4).await, FruitBasketError::BookWalkMismatch);
+
+    fixture.update_basket_book_price(&basket, 100_000, 8, 4).await.unwrap();
+    let book_price = book_price(&fixture.test.account_data(&basket).await);
+    assert_eq!((book_price.cursor, book_price.amount), (0, 100_000));
+    assert!(book_price.sell_price > 0 && book_price.buy_price >= book_price.sell_price);
+}
+
+// a component smaller than a lot is bought as a whole lot and never sold, so that the book price does not undervalue the basket
+#[tokio::test]
+async fn book_price_below_a_lot() {
+    let mut fixture = BasketFixture::new().await;
+    let basket = fixture.basket;
+    // half a lot of the first component
+    fixture.update_basket_book_price(&basket, 50_000, 0, 3).await.unwrap();
+    let book_price = fruitbasket_client::state::Basket::decode(&fixture.test.account_data(&basket).await).unwrap().book_price;
+    // the whole lot bought doubles the cost of the first component, which is a third of the basket
+    assert!(book_price.buy_price > BASKET_PRICE);
+    assert!(book_price.sell_price < BASKET_PRICE * 2 / 3);
+}
+
+// backing of a large basket is audited over several transactions while its supply is unchanged
+#[tokio::test]
+async fn audit_by_chunks() {
+    let mut fixture = GroupFixture::new().await;
+    let decode = |data : &[u8]| fruitbasket_client::state::Basket::decode(data).unwrap();
+    let (basket, _) = fixture.add_cycled_basket(1, 12).await;
+    fixture.audit_basket(&basket, 0, 4).await.unwrap();
+    fixture.audit_basket(&basket, 4, 4).await.unwrap();
+    let pending = decode(&fixture.test.account_data(&basket).await);
+    assert_eq!((pending.audit_cursor, pending.audit_timestamp), (8, None));
+    fixture.test.context.get_new_latest_blockhash().await.unwrap();
+    assert_program_error(fixture.audit_basket(&basket, 4, 4).await, FruitBasketError::AuditMismatch);
+    fixture.audit_basket(&basket, 8, 4).await.unwrap();
+    let audited = decode(&fixture.test.account_data(&basket).await);
+    assert_eq!(audited.audit_cursor, 0);
+    assert_eq!(audited.solvency_bps, 10_000);
+    assert!(audited.audit_timestamp.is_some());
+
+    // basket tokens created between two chunks drop the pending audit
+    let user = fixture.user.pubkey();
+    let (basket, basket_mint) = fixture.add_cycled_basket(2, 8).await;
+    let user_tokens = fixture.user_token_accounts(10_000_000).await;
+    let user_basket = fixture.test.create_token_account(&basket_mint, &user).await;
+    fixture.create_in_kind(&basket, &user_basket, &user_tokens, 1_000_000).await.unwrap();
+    fixture.audit_basket(&basket, 0, 4).await.unwrap();
+    fixture.create_in_kind(&basket, &user_basket, &user_tokens, 500_000).await.unwrap();
+    assert_program_error(fixture.audit_basket(&basket, 4, 4).await, FruitBasketError::AuditMismatch);
+    fixture.audit_basket(&basket, 0, 8).await.unwrap();
+    let audited = decode(&fixture.test.account_data(&basket).await);
+    // each component is backed by its own vault
+    assert_eq!((audited.audit_cursor, audited.solvency_bps), (0, 10_000));
+
+    // chunks of an audit are accepted over a limited number of slots
+    fixture.audit_basket(&basket, 0, 4).await.unwrap();
+    let slot = fixture.test.slot().await + MAX_AUDIT_WINDOW_SLOTS + 1;
+    fixture.test.context.warp_to_slot(slot).unwrap();
+    fixture.test.context.get_new_latest_blockhash().await.unwrap();
+    assert_program_error(fixture.audit_basket(&basket, 4, 4).await, FruitBasketError::AuditMismatch);
+}
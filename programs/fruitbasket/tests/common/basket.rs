@@ -0,0 +1,140 @@
+// Priced basket of the three listed tokens of a group, with a user holding quote tokens to trade it
+use fruitbasket::states::BasketComponentDescription;
+use fruitbasket_client::instructions;
+use fruitbasket_client::pda;
+use solana_program_test::BanksClientError;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signer;
+use std::ops::{Deref, DerefMut};
+
+use super::group::{replace_account, GroupFixture};
+
+// amount of BTC, ETH and SOL in one basket
+pub const BASKET_COMPONENTS : [u64; 3] = [10_000, 100_000, 2_000_000];
+// one basket is worth 1200 quote tokens
+pub const BASKET_PRICE : u64 = 1_200_000_000;
+pub const USER_QUOTE_AMOUNT : u64 = 10_000_000_000;
+
+// basket the user swaps into, with the account of the user holding its tokens
+pub struct SwapDestination {
+    pub basket : Pubkey,
+    pub basket_mint : Pubkey,
+    pub user_basket : Pubkey,
+}
+
+// the group, its tokens and its user are reached through the group fixture
+pub struct BasketFixture {
+    pub group_fixture : GroupFixture,
+    pub basket : Pubkey,
+    pub basket_mint : Pubkey,
+    pub user_quote : Pubkey,
+    pub user_basket : Pubkey,
+}
+
+impl Deref for BasketFixture {
+    type Target = GroupFixture;
+
+    fn deref(&self) -> &GroupFixture {
+        &self.group_fixture
+    }
+}
+
+impl DerefMut for BasketFixture {
+    fn deref_mut(&mut self) -> &mut GroupFixture {
+        &mut self.group_fixture
+    }
+}
+
+impl BasketFixture {
+    pub async fn new() -> Self {
+        Self::with_decimals([6, 6, 6]).await
+    }
+
+    // basket whose tokens have these decimals, the amounts of the basket components keep 6 decimals
+    pub async fn with_decimals(decimals : [u8; 3]) -> Self {
+        let mut group_fixture = GroupFixture::with_decimals(decimals).await;
+        let owner = group_fixture.test.payer();
+        let quote_mint = group_fixture.quote_mint;
+        let components = group_fixture.tokens.iter().zip(BASKET_COMPONENTS.iter())
+            .map(|(token, amount)| BasketComponentDescription { token_mint : token.mint, amount : *amount, decimal : 6 })
+            .collect::<Vec<_>>();
+        let (basket, basket_mint) = (pda::basket(0).0, pda::basket_mint(0).0);
+        let instructions = vec![
+            instructions::add_basket(&owner, &group_fixture.group, 0, "First tier coins".to_string(), "Basket for first tier coins".to_string(),
+                                     components.len() as u8, components.clone(), 20),
+            instructions::create_creator_fee_vault(&owner, &basket, &quote_mint),
+            instructions::create_basket_nav(&owner, &basket),
+        ];
+        group_fixture.test.process(&instructions, &[]).await.unwrap();
+        let token_mints = components.iter().map(|x| x.token_mint).collect::<Vec<_>>();
+        group_fixture.create_basket_accounts(&basket, &token_mints, true).await;
+
+        let user = group_fixture.user.pubkey();
+        let user_quote = group_fixture.test.create_token_account(&quote_mint, &user).await;
+        group_fixture.test.mint_to(&quote_mint, &user_quote, USER_QUOTE_AMOUNT).await;
+        let user_basket = group_fixture.test.create_token_account(&basket_mint, &user).await;
+
+        let mut fixture = BasketFixture { group_fixture, basket, basket_mint, user_quote, user_basket };
+        fixture.update_prices().await;
+        fixture
+    }
+
+    // complete basket of the group owner with its vaults and open orders, the user holds an account of its tokens
+    pub async fn add_swap_destination(&mut self, basket_number : u64, components : Vec<BasketComponentDescription>) -> SwapDestination {
+        let (basket, basket_mint) = self.add_basket(basket_number, components.len() as u8, components.clone()).await.unwrap();
+        let token_mints = components.iter().map(|x| x.token_mint).collect::<Vec<_>>();
+        self.create_basket_accounts(&basket, &token_mints, true).await;
+        // the fees of a swap are reserved at the cached prices of the destination components
+        let basket_state = self.basket_state(&basket).await;
+        self.test.process(&[instructions::update_basket_price(&basket, &basket_state)], &[]).await.unwrap();
+        let user = self.user.pubkey();
+        let user_basket = self.test.create_token_account(&basket_mint, &user).await;
+        SwapDestination { basket, basket_mint, user_basket }
+    }
+
+    // fees taken by the protocol and by the creator of the basket
+    pub async fn fee_balances(&mut self) -> (u64, u64) {
+        let fee_vault = pda::fee_vault(&self.group, &self.quote_mint).0;
+        let creator_fee_vault = pda::creator_fee_vault(&self.basket, &self.quote_mint).0;
+        (self.test.token_balance(&fee_vault).await, self.test.token_balance(&creator_fee_vault).await)
+    }
+
+    // token caches from the oracles, then the basket price from the caches
+    pub async fn update_prices(&mut self) {
+        let mut instructions = Vec::new();
+        for token in 0..self.tokens.len() {
+            let token_desc = self.token_desc(token).await;
+            instructions.push(instructions::update_price(&self.group, &token_desc));
+        }
+        let basket = self.basket;
+        let basket_state = self.basket_state(&basket).await;
+        instructions.push(instructions::update_basket_price(&basket, &basket_state));
+        self.test.process(&instructions, &[]).await.unwrap();
+    }
+
+    // basket price from the given token descriptions, in the order of the components
+    pub async fn update_basket_price(&mut self, token_descs : &[Pubkey]) -> Result<(), BanksClientError> {
+        let basket = self.basket;
+        let basket_state = self.basket_state(&basket).await;
+        let mut instruction = instructions::update_basket_price(&basket, &basket_state);
+        for (component, token_desc) in basket_state.components.iter().zip(token_descs.iter()) {
+            replace_account(&mut instruction, &pda::token_desc(&component.token_mint).0, token_desc);
+        }
+        self.test.process(&[instruction], &[]).await
+    }
+
+    // balances of the vaults of the basket components
+    pub async fn vault_balances(&mut self) -> Vec<u64> {
+        let vaults = self.tokens[..BASKET_COMPONENTS.len()].iter().map(|token| pda::basket_vault(&self.basket, &token.mint).0).collect::<Vec<_>>();
+        let mut balances = Vec::new();
+        for vault in vaults.iter() {
+            balances.push(self.test.token_balance(vault).await);
+        }
+        balances
+    }
+
+    pub async fn user_balances(&mut self) -> (u64, u64) {
+        let (user_quote, user_basket) = (self.user_quote, self.user_basket);
+        (self.test.token_balance(&user_quote).await, self.test.token_balance(&user_basket).await)
+    }
+}
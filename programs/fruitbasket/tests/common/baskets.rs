@@ -0,0 +1,156 @@
+// Baskets of the listed tokens, walked, audited and created in kind over several transactions
+use fruitbasket::states::BasketComponentDescription;
+use fruitbasket_client::instructions;
+use fruitbasket_client::pda;
+use solana_program_test::BanksClientError;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+
+use super::group::{GroupFixture, TOKEN_PRICES};
+
+// components added with their token descriptions by a single instruction
+pub const COMPONENTS_PER_TRANSACTION : usize = 8;
+// components moved with their token description, user account and vault by a single instruction
+pub const IN_KIND_COMPONENTS_PER_TRANSACTION : usize = 4;
+
+impl GroupFixture {
+    // basket of the group owner, components can be added later up to number_of_components
+    pub async fn add_basket(&mut self, basket_number : u64, number_of_components : u8, components : Vec<BasketComponentDescription>)
+                            -> Result<(Pubkey, Pubkey), BanksClientError> {
+        let instruction = instructions::add_basket(&self.test.payer(), &self.group, basket_number, format!("Basket {}", basket_number),
+                                                   String::new(), number_of_components, components, 0);
+        self.test.process(&[instruction], &[]).await?;
+        Ok((pda::basket(basket_number).0, pda::basket_mint(basket_number).0))
+    }
+
+    pub async fn add_basket_components(&mut self, basket : &Pubkey, components : Vec<BasketComponentDescription>) -> Result<(), BanksClientError> {
+        let instruction = instructions::add_basket_components(&self.test.payer(), basket, components);
+        self.test.process(&[instruction], &[]).await
+    }
+
+    // vaults of the components of a basket, with its open orders on their markets when the basket is traded
+    pub async fn create_basket_accounts(&mut self, basket : &Pubkey, token_mints : &[Pubkey], open_orders : bool) {
+        let owner = self.test.payer();
+        for chunk in token_mints.chunks(COMPONENTS_PER_TRANSACTION) {
+            let vaults = chunk.iter().map(|x| instructions::create_basket_vault(&owner, basket, x)).collect::<Vec<_>>();
+            self.test.process(&vaults, &[]).await.unwrap();
+        }
+        if open_orders {
+            let open_orders = token_mints.iter()
+                .map(|x| instructions::create_basket_open_orders(&owner, basket, &self.market_accounts(x).market, &anchor_spl::dex::ID))
+                .collect::<Vec<_>>();
+            self.test.process(&open_orders, &[]).await.unwrap();
+        }
+    }
+
+    // complete basket of the group owner whose components are distinct tokens listed for it, cycling through the prices of the group
+    pub async fn add_cycled_basket(&mut self, basket_number : u64, count : usize) -> (Pubkey, Pubkey) {
+        let mut token_mints = Vec::new();
+        for index in 0..count {
+            let token = self.list_token(TOKEN_PRICES[index % TOKEN_PRICES.len()], 6).await;
+            token_mints.push(self.tokens[token].mint);
+        }
+        let components = token_mints.iter()
+            .map(|x| BasketComponentDescription { token_mint : *x, amount : 1_000_000, decimal : 6 })
+            .collect::<Vec<_>>();
+        let (basket, basket_mint) = self.add_complete_basket(basket_number, components).await;
+        self.create_basket_accounts(&basket, &token_mints, false).await;
+        (basket, basket_mint)
+    }
+
+    // basket added by chunks which fit in a transaction with the token descriptions of their components
+    pub async fn add_complete_basket(&mut self, basket_number : u64, components : Vec<BasketComponentDescription>) -> (Pubkey, Pubkey) {
+        let first_chunk = components.len().min(COMPONENTS_PER_TRANSACTION);
+        let (basket, basket_mint) = self.add_basket(basket_number, components.len() as u8, components[..first_chunk].to_vec()).await.unwrap();
+        for chunk in components[first_chunk..].chunks(COMPONENTS_PER_TRANSACTION) {
+            self.add_basket_components(&basket, chunk.to_vec()).await.unwrap();
+        }
+        (basket, basket_mint)
+    }
+
+    // orderbooks of count components of a basket walked from first_component
+    pub async fn update_basket_book_price(&mut self, basket : &Pubkey, amount : u64, first_component : usize, count : usize)
+                                          -> Result<(), BanksClientError> {
+        let basket_state = self.basket_state(basket).await;
+        let markets = basket_state.components[first_component..first_component + count].iter()
+            .map(|x| self.market_accounts(&x.token_mint))
+            .collect::<Vec<_>>();
+        let instruction = instructions::update_basket_book_price(basket, &basket_state, first_component as u8, &markets, &anchor_spl::dex::ID, amount);
+        self.test.process(&[instruction], &[]).await
+    }
+
+    // backing of count components of a basket audited from first_component
+    pub async fn audit_basket(&mut self, basket : &Pubkey, first_component : usize, count : usize) -> Result<(), BanksClientError> {
+        let basket_state = self.basket_state(basket).await;
+        let instruction = instructions::audit_basket(basket, &basket_state, first_component as u8, count);
+        self.test.process(&[instruction], &[]).await
+    }
+
+    // accounts of the user holding amount of each listed token
+    pub async fn user_token_accounts(&mut self, amount : u64) -> Vec<Pubkey> {
+        let user = self.user.pubkey();
+        let mut accounts = Vec::new();
+        for mint in self.tokens.iter().map(|x| x.mint).collect::<Vec<_>>() {
+            let account = self.test.create_token_account(&mint, &user).await;
+            self.test.mint_to(&mint, &account, amount).await;
+            accounts.push(account);
+        }
+        accounts
+    }
+
+    // the user opens its in kind context on a basket, burning its basket tokens for a redeem
+    pub async fn init_in_kind_context(&mut self, basket : &Pubkey, user_basket : &Pubkey, amount : u64, redeem : bool) -> Result<(), BanksClientError> {
+        let basket_state = self.basket_state(basket).await;
+        let instruction = instructions::init_in_kind_context(&self.user.pubkey(), basket, &basket_state, user_basket, amount, redeem);
+        let user = Keypair::from_bytes(&self.user.to_bytes()).unwrap();
+        self.test.process(&[instruction], &[&user]).await
+    }
+
+    // count components moved from first_component with the accounts of the user, given in the order of the listed tokens
+    // the user signs when its components are taken, anyone gives them back
+    pub async fn process_in_kind(&mut self, basket : &Pubkey, user_tokens : &[Pubkey], first_component : usize, count : usize, user_signs : bool)
+                                 -> Result<(), BanksClientError> {
+        let basket_state = self.basket_state(basket).await;
+        let token_accounts = basket_state.components[first_component..first_component + count].iter()
+            .map(|component| user_tokens[self.tokens.iter().position(|x| x.mint == component.token_mint).unwrap()])
+            .collect::<Vec<_>>();
+        let instruction = instructions::process_in_kind(&self.user.pubkey(), basket, &basket_state, first_component as u8, &token_accounts, user_signs);
+        let user = Keypair::from_bytes(&self.user.to_bytes()).unwrap();
+        let signers = if user_signs { vec![&user] } else { vec![] };
+        self.test.process(&[instruction], &signers).await
+    }
+
+    pub async fn finalize_in_kind_context(&mut self, basket : &Pubkey, user_basket : &Pubkey) -> Result<(), BanksClientError> {
+        let basket_state = self.basket_state(basket).await;
+        let instruction = instructions::finalize_in_kind_context(&self.user.pubkey(), basket, &basket_state, user_basket);
+        self.test.process(&[instruction], &[]).await
+    }
+
+    pub async fn cancel_in_kind_context(&mut self, basket : &Pubkey, authority : &Keypair) -> Result<(), BanksClientError> {
+        let basket_state = self.basket_state(basket).await;
+        let instruction = instructions::cancel_in_kind_context(&authority.pubkey(), &self.user.pubkey(), basket, &basket_state);
+        self.test.process(&[instruction], &[authority]).await
+    }
+
+    pub async fn create_in_kind(&mut self, basket : &Pubkey, user_basket : &Pubkey, user_tokens : &[Pubkey], amount : u64)
+                                -> Result<(), BanksClientError> {
+        self.in_kind(basket, user_basket, user_tokens, amount, false).await
+    }
+
+    pub async fn redeem_in_kind(&mut self, basket : &Pubkey, user_basket : &Pubkey, user_tokens : &[Pubkey], amount : u64)
+                                -> Result<(), BanksClientError> {
+        self.in_kind(basket, user_basket, user_tokens, amount, true).await
+    }
+
+    // every component of the basket is moved by chunks before the context is finalized
+    async fn in_kind(&mut self, basket : &Pubkey, user_basket : &Pubkey, user_tokens : &[Pubkey], amount : u64, redeem : bool)
+                     -> Result<(), BanksClientError> {
+        self.init_in_kind_context(basket, user_basket, amount, redeem).await?;
+        let number_of_components = self.basket_state(basket).await.components.len();
+        for first_component in (0..number_of_components).step_by(IN_KIND_COMPONENTS_PER_TRANSACTION) {
+            let count = IN_KIND_COMPONENTS_PER_TRANSACTION.min(number_of_components - first_component);
+            self.process_in_kind(basket, user_tokens, first_component, count, true).await?;
+        }
+        self.finalize_in_kind_context(basket, user_basket).await
+    }
+}
@@ -0,0 +1,146 @@
+// Trade, swap and rebalance contexts of the basket fixture, opened, processed and finalized like the keeper does
+use fruitbasket::states::{BasketComponentDescription, ContextOrderType, ContextSide};
+use fruitbasket_client::instructions;
+use fruitbasket_client::pda;
+use solana_program_test::BanksClientError;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+
+use super::basket::{BasketFixture, SwapDestination};
+use super::group::replace_account;
+
+impl BasketFixture {
+    pub fn trade_context(&self, order_id : u8) -> Pubkey {
+        pda::trade_context(&self.user.pubkey(), order_id).0
+    }
+
+    // the user signs the instructions opening and cancelling its contexts
+    fn user_signer(&self) -> Keypair {
+        Keypair::from_bytes(&self.user.to_bytes()).unwrap()
+    }
+
+    pub async fn init_trade_context(&mut self, side : ContextSide, amount : u64, price : u64) -> Result<Pubkey, BanksClientError> {
+        self.open_context(side, amount, price, ContextOrderType::Market).await
+    }
+
+    // legs of a limit context rest on the orderbooks at the price of a whole basket scaled to each component
+    pub async fn init_limit_context(&mut self, side : ContextSide, amount : u64, price : u64) -> Result<Pubkey, BanksClientError> {
+        self.open_context(side, amount, price, ContextOrderType::Limit).await
+    }
+
+    async fn open_context(&mut self, side : ContextSide, amount : u64, price : u64, order_type : ContextOrderType) -> Result<Pubkey, BanksClientError> {
+        let (group, basket) = (self.group, self.basket);
+        let (group_state, basket_state) = (self.group_state().await, self.basket_state(&basket).await);
+        let instruction = instructions::init_trade_context(&group, &group_state, &self.user.pubkey(), &basket, &basket_state, &self.quote_mint,
+                                                           &self.user_quote, &self.user_basket, 0, side, amount, price, order_type).unwrap();
+        let user = self.user_signer();
+        self.test.process(&[instruction], &[&user]).await?;
+        Ok(self.trade_context(0))
+    }
+
+    // legs of a buy or sell context are the components of the basket, in the same order
+    pub async fn process_leg(&mut self, trade_context : &Pubkey, leg : usize) -> Result<(), BanksClientError> {
+        let (instruction, _) = self.process_leg_instruction(trade_context, leg).await;
+        self.test.process(&[instruction], &[]).await
+    }
+
+    // the open orders of the basket derived by the client are swapped for the given ones
+    pub async fn process_leg_with_open_orders(&mut self, trade_context : &Pubkey, leg : usize, open_orders : &Pubkey) -> Result<(), BanksClientError> {
+        let (mut instruction, basket_open_orders) = self.process_leg_instruction(trade_context, leg).await;
+        replace_account(&mut instruction, &basket_open_orders, open_orders);
+        self.test.process(&[instruction], &[]).await
+    }
+
+    // instruction processing a leg, with the open orders it trades with
+    async fn process_leg_instruction(&mut self, trade_context : &Pubkey, leg : usize) -> (Instruction, Pubkey) {
+        let group = self.group;
+        let (group_state, context_state) = (self.group_state().await, self.context_state(trade_context).await);
+        let token_mint = context_state.legs[leg].token_mint;
+        let token = self.tokens.iter().position(|x| x.mint == token_mint).unwrap();
+        let token_desc = self.token_desc(token).await;
+        let market = self.market_accounts(&token_mint);
+        let instruction = instructions::process_token_for_context(&group, &group_state, trade_context, &context_state, leg,
+                                                                  &token_desc, &market, &anchor_spl::dex::ID).unwrap();
+        let open_orders = pda::basket_open_orders(&context_state.leg_basket(leg).unwrap(), &market.market).0;
+        (instruction, open_orders)
+    }
+
+    pub async fn process_legs(&mut self, trade_context : &Pubkey, legs : &[usize]) -> Result<(), BanksClientError> {
+        for leg in legs {
+            self.process_leg(trade_context, *leg).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn revert(&mut self, trade_context : &Pubkey) -> Result<(), BanksClientError> {
+        let (group_state, context_state) = (self.group_state().await, self.context_state(trade_context).await);
+        let instruction = instructions::revert_trade_context(&group_state, trade_context, &context_state).unwrap();
+        self.test.process(&[instruction], &[]).await
+    }
+
+    // the user cancels a limit context, its legs are then reverted
+    pub async fn cancel(&mut self, trade_context : &Pubkey) -> Result<(), BanksClientError> {
+        let user = self.user_signer();
+        self.cancel_as(trade_context, &user).await
+    }
+
+    pub async fn cancel_as(&mut self, trade_context : &Pubkey, authority : &Keypair) -> Result<(), BanksClientError> {
+        let group = self.group;
+        let (group_state, context_state) = (self.group_state().await, self.context_state(trade_context).await);
+        let instruction = instructions::cancel_trade_context(&group, &group_state, &authority.pubkey(), trade_context, &context_state).unwrap();
+        self.test.process(&[instruction], &[authority]).await
+    }
+
+    // the user swaps basket tokens of the fixture for tokens of the destination basket
+    pub async fn init_swap_context(&mut self, destination : &SwapDestination, amount : u64, destination_amount : u64, max_quote_in : u64)
+                                   -> Result<Pubkey, BanksClientError> {
+        let (group, basket) = (self.group, self.basket);
+        let (group_state, basket_state) = (self.group_state().await, self.basket_state(&basket).await);
+        let instruction = instructions::init_swap_context(&group, &group_state, &self.user.pubkey(), &basket, &basket_state, &destination.basket,
+                                                          &self.quote_mint, &self.user_quote, &self.user_basket, &destination.user_basket,
+                                                          0, amount, destination_amount, max_quote_in).unwrap();
+        let user = self.user_signer();
+        self.test.process(&[instruction], &[&user]).await?;
+        Ok(self.trade_context(0))
+    }
+
+    // the owner, who created the basket, rebalances it to new components netted against its vaults
+    pub async fn init_rebalance_context(&mut self, new_components : Vec<BasketComponentDescription>) -> Result<Pubkey, BanksClientError> {
+        let (group, basket, curator) = (self.group, self.basket, self.test.payer());
+        let basket_state = self.basket_state(&basket).await;
+        let instruction = instructions::init_rebalance_context(&group, &curator, &basket, &basket_state, 0, new_components);
+        self.test.process(&[instruction], &[]).await?;
+        Ok(pda::trade_context(&curator, 0).0)
+    }
+
+    // legs shared by both baskets are moved between their vaults, none are moved when the swap is reverted
+    pub async fn finalize_swap(&mut self, trade_context : &Pubkey, destination : &SwapDestination) -> Result<(), BanksClientError> {
+        let (group, basket) = (self.group, self.basket);
+        let (group_state, context_state) = (self.group_state().await, self.context_state(trade_context).await);
+        let (source_state, destination_state) = (self.basket_state(&basket).await, self.basket_state(&destination.basket).await);
+        let instruction = instructions::finalize_swap_context(&group, &group_state, trade_context, &context_state, &source_state, &destination_state).unwrap();
+        self.test.process(&[instruction], &[]).await
+    }
+
+    pub async fn finalize(&mut self, trade_context : &Pubkey) -> Result<(), BanksClientError> {
+        let creator_fee_vault = pda::creator_fee_vault(&self.basket, &self.quote_mint).0;
+        self.finalize_with_creator_fee_vault(trade_context, &creator_fee_vault).await
+    }
+
+    pub async fn finalize_with_creator_fee_vault(&mut self, trade_context : &Pubkey, creator_fee_vault : &Pubkey) -> Result<(), BanksClientError> {
+        let (group, basket) = (self.group, self.basket);
+        let (group_state, context_state) = (self.group_state().await, self.context_state(trade_context).await);
+        let basket_state = self.basket_state(&basket).await;
+        let mut instruction = instructions::finalize_context(&group, &group_state, trade_context, &context_state, &basket_state).unwrap();
+        replace_account(&mut instruction, &pda::creator_fee_vault(&basket, &self.quote_mint).0, creator_fee_vault);
+        self.test.process(&[instruction], &[]).await
+    }
+
+    // opens a market context, processes all its legs and finalizes it
+    pub async fn trade(&mut self, side : ContextSide, amount : u64, price : u64) {
+        let trade_context = self.init_trade_context(side, amount, price).await.unwrap();
+        self.process_legs(&trade_context, &[0, 1, 2]).await.unwrap();
+        self.finalize(&trade_context).await.unwrap();
+    }
+}
@@ -0,0 +1,214 @@
+// Group with listed tokens and a user, configured by its owner with the instructions of fruitbasket-client
+use fruitbasket_client::instructions;
+use fruitbasket_client::pda;
+use fruitbasket_client::serum::MarketAccounts;
+use fruitbasket_client::state::{Basket, Group, TokenDescription, TradeContext};
+use solana_program_test::BanksClientError;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+
+use super::serum::{open_orders_space, Market};
+use super::TestContext;
+
+pub const QUOTE_DECIMALS : u8 = 6;
+pub const PRICE_EXPONENT : i32 = -6;
+// BTC, ETH and SOL prices in native quote units
+pub const TOKEN_PRICES : [u64; 3] = [40_000_000_000, 4_000_000_000, 200_000_000];
+
+pub struct ListedToken {
+    pub mint : Pubkey,
+    pub market : Market,
+    pub price_oracle : Keypair,
+    pub product_oracle : Pubkey,
+    pub open_orders : Pubkey,
+}
+
+pub struct GroupFixture {
+    pub test : TestContext,
+    pub quote_mint : Pubkey,
+    pub quote_pool : Pubkey,
+    pub group : Pubkey,
+    pub tokens : Vec<ListedToken>,
+    pub user : Keypair,
+}
+
+// account given to an instruction swapped for another one, to pass accounts the client would not derive
+pub fn replace_account(instruction : &mut Instruction, account : &Pubkey, replacement : &Pubkey) {
+    for meta in instruction.accounts.iter_mut().filter(|x| x.pubkey == *account) {
+        meta.pubkey = *replacement;
+    }
+}
+
+impl GroupFixture {
+    pub async fn new() -> Self {
+        Self::with_decimals([6, 6, 6]).await
+    }
+
+    // group whose tokens have these decimals
+    pub async fn with_decimals(decimals : [u8; 3]) -> Self {
+        let mut test = TestContext::new().await;
+        let owner = test.payer();
+        let quote_mint = test.create_mint(QUOTE_DECIMALS).await;
+        let quote_pool = test.create_token_account(&quote_mint, &owner).await;
+        let group = pda::group(&owner).0;
+        test.process(&[
+            instructions::initialize_group(&owner, &quote_mint, &quote_pool, "USDC".to_string()),
+            instructions::create_fee_vault(&owner, &group, &quote_mint),
+        ], &[]).await.unwrap();
+        let user = test.create_wallet(10_000_000_000).await;
+
+        let mut fixture = GroupFixture { test, quote_mint, quote_pool, group, tokens : Vec::new(), user };
+        for (index, price) in TOKEN_PRICES.iter().enumerate() {
+            fixture.list_token(*price, decimals[index]).await;
+        }
+        fixture
+    }
+
+    // token with a market made around its price and an oracle publishing a 1% confidence, its index is returned
+    pub async fn list_token(&mut self, price : u64, decimals : u8) -> usize {
+        let token = self.list_unbooked_token(price, decimals).await;
+        let market = self.tokens[token].market.clone();
+        // 40000 quote tokens on each side of the book
+        self.test.make_market(&market, price, (40_000_000_000 * 10u128.pow(decimals.into()) / price as u128) as u64).await;
+        token
+    }
+
+    // token whose market has an empty book, for baskets which are never traded
+    pub async fn list_unbooked_token(&mut self, price : u64, decimals : u8) -> usize {
+        let owner = self.test.payer();
+        let name = format!("TOKEN{}", self.tokens.len());
+        let mint = self.test.create_mint(decimals).await;
+        let market = self.test.create_market(&mint, decimals, &self.quote_mint).await;
+        let price_oracle = self.test.create_oracle().await;
+        let product_oracle = self.test.create_product(&price_oracle, &name).await;
+        self.test.set_oracle_price(&price_oracle, price as i64, price / 100, PRICE_EXPONENT).await;
+
+        let token_pool = self.test.create_token_account(&mint, &owner).await;
+        let open_orders = Keypair::new();
+        let instructions = [
+            self.test.create_account_instruction(&open_orders, open_orders_space(), &anchor_spl::dex::ID).await,
+            instructions::add_token(&owner, &self.group, &mint, &price_oracle.pubkey(), &product_oracle.pubkey(), &token_pool,
+                                    &market.market, &open_orders.pubkey(), &anchor_spl::dex::ID, name),
+        ];
+        self.test.process(&instructions, &[&open_orders]).await.unwrap();
+        self.tokens.push(ListedToken { mint, market, price_oracle, product_oracle : product_oracle.pubkey(), open_orders : open_orders.pubkey() });
+        self.tokens.len() - 1
+    }
+
+    // owner of the group of the fixture, it pays for every transaction
+    pub fn owner(&self) -> Keypair {
+        Keypair::from_bytes(&self.test.context.payer.to_bytes()).unwrap()
+    }
+
+    pub async fn group_state(&mut self) -> Group {
+        let group = self.group;
+        Group::decode(&self.test.account_data(&group).await).unwrap()
+    }
+
+    pub async fn basket_state(&mut self, basket : &Pubkey) -> Basket {
+        Basket::decode(&self.test.account_data(basket).await).unwrap()
+    }
+
+    pub async fn context_state(&mut self, trade_context : &Pubkey) -> TradeContext {
+        TradeContext::decode(&self.test.account_data(trade_context).await).unwrap()
+    }
+
+    pub fn token_desc_address(&self, token : usize) -> Pubkey {
+        pda::token_desc(&self.tokens[token].mint).0
+    }
+
+    pub async fn token_desc(&mut self, token : usize) -> TokenDescription {
+        let token_desc = self.token_desc_address(token);
+        TokenDescription::decode(&self.test.account_data(&token_desc).await).unwrap()
+    }
+
+    // serum accounts of the market of a listed token, looked up by its mint
+    pub fn market_accounts(&self, token_mint : &Pubkey) -> MarketAccounts {
+        self.tokens.iter().find(|x| x.mint == *token_mint).unwrap().market.accounts()
+    }
+
+    // token cache from the oracle of a single token
+    pub async fn update_token_price(&mut self, token : usize) -> Result<(), BanksClientError> {
+        let token_desc = self.token_desc(token).await;
+        let instruction = instructions::update_price(&self.group, &token_desc);
+        self.test.process(&[instruction], &[]).await
+    }
+
+    // group of another owner on the same quote currency, the owner signs its configuration
+    pub async fn create_foreign_group(&mut self) -> (Keypair, Pubkey) {
+        let owner = self.test.create_wallet(1_000_000_000).await;
+        let quote_mint = self.quote_mint;
+        let quote_pool = self.test.create_token_account(&quote_mint, &owner.pubkey()).await;
+        let instruction = instructions::initialize_group(&owner.pubkey(), &quote_mint, &quote_pool, "USDC".to_string());
+        self.test.process(&[instruction], &[&owner]).await.unwrap();
+        (owner, pda::group(&owner.pubkey()).0)
+    }
+
+    // token listed by the owner of another group, it is not added to the tokens of the fixture
+    pub async fn list_foreign_token(&mut self, owner : &Keypair, group : &Pubkey) -> Pubkey {
+        let mint = self.test.create_mint(6).await;
+        let market = self.test.create_market(&mint, 6, &self.quote_mint).await;
+        let price_oracle = self.test.create_oracle().await;
+        let product_oracle = self.test.create_product(&price_oracle, "FOREIGN").await;
+        let token_pool = self.test.create_token_account(&mint, &owner.pubkey()).await;
+        let open_orders = Keypair::new();
+        let instructions = [
+            self.test.create_account_instruction(&open_orders, open_orders_space(), &anchor_spl::dex::ID).await,
+            instructions::add_token(&owner.pubkey(), group, &mint, &price_oracle.pubkey(), &product_oracle.pubkey(), &token_pool,
+                                    &market.market, &open_orders.pubkey(), &anchor_spl::dex::ID, "FOREIGN".to_string()),
+        ];
+        self.test.process(&instructions, &[owner, &open_orders]).await.unwrap();
+        mint
+    }
+
+    // quote currency added by the owner of a group, it is never priced
+    pub async fn add_quote_currency(&mut self, owner : &Keypair, group : &Pubkey) -> Pubkey {
+        let quote_mint = self.test.create_mint(QUOTE_DECIMALS).await;
+        let quote_pool = self.test.create_token_account(&quote_mint, &owner.pubkey()).await;
+        let instruction = instructions::add_quote_currency(&owner.pubkey(), group, &quote_mint, &quote_pool, &Pubkey::new_unique());
+        self.test.process(&[instruction], &[owner]).await.unwrap();
+        quote_mint
+    }
+
+    // market of a listed token against another quote currency, added by a token lister of a group
+    pub async fn add_token_market(&mut self, owner : &Keypair, group : &Pubkey, token : usize, quote_mint : &Pubkey)
+                                  -> Result<(), BanksClientError> {
+        let mint = self.tokens[token].mint;
+        self.add_market_of(owner, group, token, quote_mint, &mint, quote_mint).await
+    }
+
+    // market trading coin_mint for pc_mint added to a listed token for a quote currency
+    pub async fn add_market_of(&mut self, owner : &Keypair, group : &Pubkey, token : usize, quote_mint : &Pubkey, coin_mint : &Pubkey, pc_mint : &Pubkey)
+                               -> Result<(), BanksClientError> {
+        let mint = self.tokens[token].mint;
+        let market = self.test.create_market(coin_mint, 6, pc_mint).await;
+        let open_orders = Keypair::new();
+        let instructions = [
+            self.test.create_account_instruction(&open_orders, open_orders_space(), &anchor_spl::dex::ID).await,
+            instructions::add_token_market(&owner.pubkey(), group, &mint, quote_mint, &market.market, &open_orders.pubkey(), &anchor_spl::dex::ID),
+        ];
+        self.test.process(&instructions, &[owner, &open_orders]).await
+    }
+
+    pub async fn configure_token(&mut self, owner : &Keypair, group : &Pubkey, token : usize, max_confidence_bps : u64, max_deviation_bps : u64)
+                                 -> Result<(), BanksClientError> {
+        let instruction = instructions::configure_token(&owner.pubkey(), group, &self.tokens[token].mint, max_confidence_bps, max_deviation_bps);
+        self.test.process(&[instruction], &[owner]).await
+    }
+
+    pub async fn set_token_pause(&mut self, pauser : &Keypair, group : &Pubkey, token : usize, paused : bool) -> Result<(), BanksClientError> {
+        let instruction = instructions::set_token_pause(&pauser.pubkey(), group, &self.tokens[token].mint, paused);
+        self.test.process(&[instruction], &[pauser]).await
+    }
+
+    pub async fn set_leg_slippage(&mut self, slippage_bps : u64) -> Result<(), BanksClientError> {
+        let instruction = instructions::set_leg_slippage(&self.test.payer(), &self.group, slippage_bps);
+        self.test.process(&[instruction], &[]).await
+    }
+
+    pub async fn set_fees(&mut self, mint_fee_bps : u64, redeem_fee_bps : u64) -> Result<(), BanksClientError> {
+        let instruction = instructions::set_fees(&self.test.payer(), &self.group, mint_fee_bps, redeem_fee_bps);
+        self.test.process(&[instruction], &[]).await
+    }
+}
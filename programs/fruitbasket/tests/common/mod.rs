@@ -0,0 +1,173 @@
+// Bank running the fruitbasket program, the serum dex binary of deps/ and the mock oracle, with token helpers
+#![allow(dead_code)]
+use anchor_lang::prelude::{AccountInfo, Context, ProgramError, ProgramResult};
+use anchor_lang::solana_program::hash::hash;
+use anchor_lang::{Accounts, AccountsExit, AnchorDeserialize};
+use mock_oracle::instruction;
+use solana_program_test::{processor, BanksClientError, ProgramTest, ProgramTestContext};
+use solana_sdk::account::Account;
+use solana_sdk::clock::Clock;
+use solana_sdk::instruction::{Instruction, InstructionError};
+use solana_sdk::program_pack::Pack;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::{bpf_loader, system_instruction};
+use solana_sdk::transaction::{Transaction, TransactionError};
+
+pub mod basket;
+pub mod baskets;
+pub mod contexts;
+pub mod group;
+pub mod pyth;
+pub mod serum;
+
+// error of anchor when a constraint of an accounts struct is violated
+pub const CONSTRAINT_RAW : ProgramError = ProgramError::Custom(2003);
+
+const SERUM_DEX_SO : &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../../deps/serum_dex.so");
+
+pub struct TestContext {
+    pub context : ProgramTestContext,
+}
+
+// anchor discriminator of an instruction of the mock oracle
+fn sighash(name : &str) -> [u8; 8] {
+    let mut discriminator = [0u8; 8];
+    discriminator.copy_from_slice(&hash(format!("global:{}", name).as_bytes()).to_bytes()[..8]);
+    discriminator
+}
+
+// the mock oracle is linked without its entrypoint, which would clash with the one of fruitbasket
+fn process_oracle_instruction(program_id : &Pubkey, accounts : &[AccountInfo], data : &[u8]) -> ProgramResult {
+    let (discriminator, mut args) = data.split_at(8);
+    let mut remaining_accounts = accounts;
+    if discriminator == sighash("create_price") {
+        let mut create = mock_oracle::CreatePrice::try_accounts(program_id, &mut remaining_accounts, data)?;
+        mock_oracle::mock_oracle::create_price(Context::new(program_id, &mut create, remaining_accounts))?;
+        create.exit(program_id)
+    } else if discriminator == sighash("set_price") {
+        let args = instruction::SetPrice::deserialize(&mut args)?;
+        let mut set = mock_oracle::SetPrice::try_accounts(program_id, &mut remaining_accounts, data)?;
+        mock_oracle::mock_oracle::set_price(Context::new(program_id, &mut set, remaining_accounts),
+                                            args.price, args.conf, args.expo, args.status, args.slot)?;
+        set.exit(program_id)
+    } else if discriminator == sighash("set_product") {
+        let args = instruction::SetProduct::deserialize(&mut args)?;
+        let mut set = mock_oracle::SetProduct::try_accounts(program_id, &mut remaining_accounts, data)?;
+        mock_oracle::mock_oracle::set_product(Context::new(program_id, &mut set, remaining_accounts), args.attributes)?;
+        set.exit(program_id)
+    } else {
+        Err(ProgramError::InvalidInstructionData)
+    }
+}
+
+// transaction failed on the custom error of a program
+pub fn assert_program_error<T : std::fmt::Debug>(result : Result<T, BanksClientError>, error : impl Into<ProgramError>) {
+    let code = match error.into() {
+        ProgramError::Custom(code) => code,
+        error => panic!("{:?} is not a custom error", error),
+    };
+    match result {
+        Err(BanksClientError::TransactionError(TransactionError::InstructionError(_, InstructionError::Custom(x)))) => assert_eq!(x, code),
+        result => panic!("expected custom error {}, got {:?}", code, result),
+    }
+}
+
+impl TestContext {
+    pub async fn new() -> Self {
+        let mut program_test = ProgramTest::new("fruitbasket", fruitbasket::ID, processor!(fruitbasket::entry));
+        program_test.add_program("mock_oracle", mock_oracle::ID, processor!(process_oracle_instruction));
+        let serum_dex = std::fs::read(SERUM_DEX_SO).expect("deps/serum_dex.so");
+        program_test.add_account(anchor_spl::dex::ID, Account {
+            lamports : 1_000_000_000,
+            data : serum_dex,
+            owner : bpf_loader::id(),
+            executable : true,
+            rent_epoch : 0,
+        });
+        TestContext { context : program_test.start_with_context().await }
+    }
+
+    pub fn payer(&self) -> Pubkey {
+        self.context.payer.pubkey()
+    }
+
+    pub async fn process(&mut self, instructions : &[Instruction], signers : &[&Keypair]) -> Result<(), BanksClientError> {
+        let blockhash = self.context.banks_client.get_latest_blockhash().await?;
+        let mut all_signers = vec![&self.context.payer];
+        all_signers.extend(signers);
+        let transaction = Transaction::new_signed_with_payer(instructions, Some(&self.context.payer.pubkey()), &all_signers, blockhash);
+        self.context.banks_client.process_transaction(transaction).await
+    }
+
+    pub async fn slot(&mut self) -> u64 {
+        self.context.banks_client.get_sysvar::<Clock>().await.unwrap().slot
+    }
+
+    pub async fn account_data(&mut self, account : &Pubkey) -> Vec<u8> {
+        self.context.banks_client.get_account(*account).await.unwrap().expect("account not found").data
+    }
+
+    // account written directly in the bank, as any program could have created it
+    pub async fn set_account(&mut self, account : &Pubkey, owner : &Pubkey, data : Vec<u8>) {
+        let rent = self.context.banks_client.get_rent().await.unwrap();
+        let account_data = Account { lamports : rent.minimum_balance(data.len()), data, owner : *owner, executable : false, rent_epoch : 0 };
+        self.context.set_account(account, &account_data.into());
+    }
+
+    pub async fn account_exists(&mut self, account : &Pubkey) -> bool {
+        self.context.banks_client.get_account(*account).await.unwrap().is_some()
+    }
+
+    pub async fn create_account_instruction(&mut self, account : &Keypair, space : usize, owner : &Pubkey) -> Instruction {
+        let rent = self.context.banks_client.get_rent().await.unwrap();
+        system_instruction::create_account(&self.payer(), &account.pubkey(), rent.minimum_balance(space), space as u64, owner)
+    }
+
+    pub async fn create_account(&mut self, space : usize, owner : &Pubkey) -> Keypair {
+        let account = Keypair::new();
+        let instruction = self.create_account_instruction(&account, space, owner).await;
+        self.process(&[instruction], &[&account]).await.unwrap();
+        account
+    }
+
+    // wallet funded with lamports for its transaction fees and rents
+    pub async fn create_wallet(&mut self, lamports : u64) -> Keypair {
+        let wallet = Keypair::new();
+        let payer = self.payer();
+        self.process(&[system_instruction::transfer(&payer, &wallet.pubkey(), lamports)], &[]).await.unwrap();
+        wallet
+    }
+
+    // mint whose authority is the payer
+    pub async fn create_mint(&mut self, decimals : u8) -> Pubkey {
+        let mint = Keypair::new();
+        let payer = self.payer();
+        let instructions = [
+            self.create_account_instruction(&mint, spl_token::state::Mint::LEN, &spl_token::ID).await,
+            spl_token::instruction::initialize_mint(&spl_token::ID, &mint.pubkey(), &payer, None, decimals).unwrap(),
+        ];
+        self.process(&instructions, &[&mint]).await.unwrap();
+        mint.pubkey()
+    }
+
+    pub async fn create_token_account(&mut self, mint : &Pubkey, owner : &Pubkey) -> Pubkey {
+        let account = Keypair::new();
+        let instructions = [
+            self.create_account_instruction(&account, spl_token::state::Account::LEN, &spl_token::ID).await,
+            spl_token::instruction::initialize_account(&spl_token::ID, &account.pubkey(), mint, owner).unwrap(),
+        ];
+        self.process(&instructions, &[&account]).await.unwrap();
+        account.pubkey()
+    }
+
+    pub async fn mint_to(&mut self, mint : &Pubkey, account : &Pubkey, amount : u64) {
+        let payer = self.payer();
+        let instruction = spl_token::instruction::mint_to(&spl_token::ID, mint, account, &payer, &[], amount).unwrap();
+        self.process(&[instruction], &[]).await.unwrap();
+    }
+
+    pub async fn token_balance(&mut self, account : &Pubkey) -> u64 {
+        spl_token::state::Account::unpack(&self.account_data(account).await).unwrap().amount
+    }
+}
@@ -0,0 +1,58 @@
+// Pyth price and product accounts of the mock oracle, like tests/utils/pyth.ts
+use anchor_lang::{InstructionData, ToAccountMetas};
+use mock_oracle::{accounts, instruction, OracleStatus, ProductAttribute};
+use solana_sdk::instruction::Instruction;
+use solana_sdk::signature::{Keypair, Signer};
+
+use super::TestContext;
+
+pub const PRICE_ACCOUNT_SIZE : usize = 3312;
+pub const PRODUCT_ACCOUNT_SIZE : usize = pyth_client::PROD_ACCT_SIZE;
+
+impl TestContext {
+    pub async fn create_oracle(&mut self) -> Keypair {
+        let oracle = Keypair::new();
+        let instructions = [
+            self.create_account_instruction(&oracle, PRICE_ACCOUNT_SIZE, &mock_oracle::ID).await,
+            Instruction {
+                program_id : mock_oracle::ID,
+                accounts : accounts::CreatePrice { price : oracle.pubkey() }.to_account_metas(None),
+                data : instruction::CreatePrice {}.data(),
+            },
+        ];
+        self.process(&instructions, &[&oracle]).await.unwrap();
+        oracle
+    }
+
+    // product of a price account, described by its symbol
+    pub async fn create_product(&mut self, oracle : &Keypair, symbol : &str) -> Keypair {
+        let product = Keypair::new();
+        let attributes = vec![ProductAttribute { key : "symbol".to_string(), value : symbol.to_string() }];
+        let instructions = [
+            self.create_account_instruction(&product, PRODUCT_ACCOUNT_SIZE, &mock_oracle::ID).await,
+            Instruction {
+                program_id : mock_oracle::ID,
+                accounts : accounts::SetProduct { product : product.pubkey(), price : oracle.pubkey() }.to_account_metas(None),
+                data : instruction::SetProduct { attributes }.data(),
+            },
+        ];
+        self.process(&instructions, &[&product, oracle]).await.unwrap();
+        product
+    }
+
+    // a halted status, an old slot or a wide confidence make an unusable price
+    pub async fn set_oracle(&mut self, oracle : &Keypair, price : i64, conf : u64, expo : i32, status : OracleStatus, slot : u64) {
+        let instruction = Instruction {
+            program_id : mock_oracle::ID,
+            accounts : accounts::SetPrice { price : oracle.pubkey() }.to_account_metas(None),
+            data : instruction::SetPrice { price, conf, expo, status, slot }.data(),
+        };
+        self.process(&[instruction], &[oracle]).await.unwrap();
+    }
+
+    // trading price published at the current slot
+    pub async fn set_oracle_price(&mut self, oracle : &Keypair, price : i64, conf : u64, expo : i32) {
+        let slot = self.slot().await;
+        self.set_oracle(oracle, price, conf, expo, OracleStatus::Trading, slot).await;
+    }
+}
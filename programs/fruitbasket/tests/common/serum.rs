@@ -0,0 +1,159 @@
+// Serum markets with a market maker resting on both sides, like tests/utils/serum.ts
+use anchor_spl::dex::serum_dex;
+use fruitbasket_client::serum::MarketAccounts;
+use serum_dex::instruction::SelfTradeBehavior;
+use serum_dex::matching::{OrderType, Side};
+use serum_dex::state::{gen_vault_signer_key, MarketState, OpenOrders};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::sysvar;
+use std::convert::TryInto;
+use std::num::NonZeroU64;
+
+use super::TestContext;
+
+// lot size of 6 decimals tokens, scaled with the decimals of the token
+pub const BASE_LOT_SIZE : u64 = 1000;
+pub const QUOTE_LOT_SIZE : u64 = 100;
+// serum accounts are padded with 5 bytes in front and 7 bytes at the end
+const ACCOUNT_PADDING : usize = 12;
+const REQUEST_QUEUE_SIZE : usize = 5132;
+const EVENT_QUEUE_SIZE : usize = 262156;
+const ORDERBOOK_SIZE : usize = 65548;
+
+#[derive(Clone)]
+pub struct Market {
+    pub market : Pubkey,
+    pub request_queue : Pubkey,
+    pub event_queue : Pubkey,
+    pub bids : Pubkey,
+    pub asks : Pubkey,
+    pub coin_vault : Pubkey,
+    pub pc_vault : Pubkey,
+    pub vault_signer : Pubkey,
+    pub coin_mint : Pubkey,
+    pub pc_mint : Pubkey,
+    pub coin_decimals : u8,
+    pub coin_lot_size : u64,
+}
+
+pub fn open_orders_space() -> usize {
+    std::mem::size_of::<OpenOrders>() + ACCOUNT_PADDING
+}
+
+impl Market {
+    // price of a whole token in native quote units to a price in quote lots per base lot
+    pub fn price_in_lots(&self, price : u64) -> u64 {
+        price * self.coin_lot_size / (10u64.pow(self.coin_decimals.into()) * QUOTE_LOT_SIZE)
+    }
+
+    // accounts the client needs to trade on the market
+    pub fn accounts(&self) -> MarketAccounts {
+        MarketAccounts {
+            market : self.market,
+            request_queue : self.request_queue,
+            event_queue : self.event_queue,
+            bids : self.bids,
+            asks : self.asks,
+            coin_vault : self.coin_vault,
+            pc_vault : self.pc_vault,
+            vault_signer : self.vault_signer,
+        }
+    }
+}
+
+impl TestContext {
+    pub async fn create_market(&mut self, coin_mint : &Pubkey, coin_decimals : u8, pc_mint : &Pubkey) -> Market {
+        let coin_lot_size = BASE_LOT_SIZE * 10u64.pow(coin_decimals.into()) / 1_000_000;
+        let dex_program = anchor_spl::dex::ID;
+        let market = self.create_account(std::mem::size_of::<MarketState>() + ACCOUNT_PADDING, &dex_program).await.pubkey();
+        let request_queue = self.create_account(REQUEST_QUEUE_SIZE, &dex_program).await.pubkey();
+        let event_queue = self.create_account(EVENT_QUEUE_SIZE, &dex_program).await.pubkey();
+        let bids = self.create_account(ORDERBOOK_SIZE, &dex_program).await.pubkey();
+        let asks = self.create_account(ORDERBOOK_SIZE, &dex_program).await.pubkey();
+        let (vault_signer_nonce, vault_signer) = (0..u64::MAX)
+            .find_map(|nonce| gen_vault_signer_key(nonce, &market, &dex_program).ok().map(|key| (nonce, key)))
+            .unwrap();
+        let coin_vault = self.create_token_account(coin_mint, &vault_signer).await;
+        let pc_vault = self.create_token_account(pc_mint, &vault_signer).await;
+
+        let instruction = serum_dex::instruction::initialize_market(
+            &market, &dex_program, coin_mint, pc_mint, &coin_vault, &pc_vault, None, None,
+            &bids, &asks, &request_queue, &event_queue, coin_lot_size, QUOTE_LOT_SIZE, vault_signer_nonce, 100,
+        ).unwrap();
+        self.process(&[instruction], &[]).await.unwrap();
+        Market { market, request_queue, event_queue, bids, asks, coin_vault, pc_vault, vault_signer,
+                 coin_mint : *coin_mint, pc_mint : *pc_mint, coin_decimals, coin_lot_size }
+    }
+
+    // a market maker bids 0.5% under the price and asks 0.5% over it, for size native tokens on each side
+    pub async fn make_market(&mut self, market : &Market, price : u64, size : u64) {
+        let maker = self.create_wallet(1_000_000_000).await;
+        let open_orders = self.create_account(open_orders_space(), &anchor_spl::dex::ID).await.pubkey();
+        let coin_account = self.create_token_account(&market.coin_mint, &maker.pubkey()).await;
+        let pc_account = self.create_token_account(&market.pc_mint, &maker.pubkey()).await;
+        let bid_price = market.price_in_lots(price * 995 / 1000);
+        let ask_price = market.price_in_lots(price * 1005 / 1000);
+        let size_in_lots = size / market.coin_lot_size;
+        let pc_amount = bid_price * size_in_lots * QUOTE_LOT_SIZE * 101 / 100;
+        self.mint_to(&market.coin_mint, &coin_account, size).await;
+        self.mint_to(&market.pc_mint, &pc_account, pc_amount).await;
+
+        self.place_order(market, &maker, &open_orders, &pc_account, Side::Bid, bid_price, size_in_lots, pc_amount).await;
+        self.place_order(market, &maker, &open_orders, &coin_account, Side::Ask, ask_price, size_in_lots, u64::MAX).await;
+    }
+
+    // a taker sends a limit order of size lots at price lots, the open orders of the taker are returned for the crank
+    pub async fn take(&mut self, market : &Market, side : Side, price : u64, size : u64) -> Pubkey {
+        let taker = self.create_wallet(1_000_000_000).await;
+        let open_orders = self.create_account(open_orders_space(), &anchor_spl::dex::ID).await.pubkey();
+        let (payer, max_pc_amount) = if side == Side::Bid {
+            let pc_account = self.create_token_account(&market.pc_mint, &taker.pubkey()).await;
+            let pc_amount = price * size * QUOTE_LOT_SIZE * 101 / 100;
+            self.mint_to(&market.pc_mint, &pc_account, pc_amount).await;
+            (pc_account, pc_amount)
+        } else {
+            let coin_account = self.create_token_account(&market.coin_mint, &taker.pubkey()).await;
+            self.mint_to(&market.coin_mint, &coin_account, size * market.coin_lot_size).await;
+            (coin_account, u64::MAX)
+        };
+        self.place_order(market, &taker, &open_orders, &payer, side, price, size, max_pc_amount).await;
+        open_orders
+    }
+
+    // fills reach the open orders of makers once the events of the market are consumed, like a crank does
+    pub async fn consume_events(&mut self, market : &Market) {
+        // event queue is padded by 5 bytes then a header of flags, head, count and sequence number, events are 88 bytes
+        let data = self.account_data(&market.event_queue).await;
+        let word = |offset : usize| u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap());
+        let (head, count) = (word(13) as usize, word(21) as usize);
+        let capacity = (data.len() - 5 - 32 - 7) / 88;
+        let mut owners = (0..count)
+            .map(|x| 37 + (head + x) % capacity * 88 + 48)
+            .map(|offset| [word(offset), word(offset + 8), word(offset + 16), word(offset + 24)])
+            .collect::<Vec<_>>();
+        // serum looks the open orders up by a binary search on their key as words
+        owners.sort_unstable();
+        owners.dedup();
+        let open_orders = owners.iter()
+            .map(|words| Pubkey::new_from_array(words.iter().flat_map(|x| x.to_le_bytes()).collect::<Vec<_>>().try_into().unwrap()))
+            .collect::<Vec<_>>();
+        let instruction = serum_dex::instruction::consume_events(
+            &anchor_spl::dex::ID, open_orders.iter().collect(), &market.market, &market.event_queue,
+            &market.coin_vault, &market.pc_vault, u16::MAX,
+        ).unwrap();
+        self.process(&[instruction], &[]).await.unwrap();
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn place_order(&mut self, market : &Market, owner : &Keypair, open_orders : &Pubkey, payer : &Pubkey,
+                         side : Side, price : u64, size : u64, max_pc_amount : u64) {
+        let instruction = serum_dex::instruction::new_order(
+            &market.market, open_orders, &market.request_queue, &market.event_queue, &market.bids, &market.asks,
+            payer, &owner.pubkey(), &market.coin_vault, &market.pc_vault, &spl_token::ID, &sysvar::rent::ID, None,
+            &anchor_spl::dex::ID, side, NonZeroU64::new(price).unwrap(), NonZeroU64::new(size).unwrap(), OrderType::Limit,
+            0, SelfTradeBehavior::DecrementTake, u16::MAX, NonZeroU64::new(max_pc_amount).unwrap(),
+        ).unwrap();
+        self.process(&[instruction], &[owner]).await.unwrap();
+    }
+}
@@ -0,0 +1,83 @@
+// Group and token settings, bounded and restricted to the owner of the group
+mod common;
+
+use common::group::GroupFixture;
+use common::{assert_program_error, CONSTRAINT_RAW};
+use fruitbasket::errors::FruitBasketError;
+
+#[tokio::test]
+async fn leg_slippage_bounded() {
+    let mut fixture = GroupFixture::new().await;
+    fixture.set_leg_slippage(1000).await.unwrap();
+    assert_program_error(fixture.set_leg_slippage(1001).await, FruitBasketError::SlippageTooHigh);
+}
+
+// a token is listed once for all groups, only the group which listed it adds its markets
+#[tokio::test]
+async fn token_market_of_foreign_group() {
+    let mut fixture = GroupFixture::new().await;
+    let (owner, group) = (fixture.owner(), fixture.group);
+    let quote_mint = fixture.add_quote_currency(&owner, &group).await;
+    fixture.add_token_market(&owner, &group, 0, &quote_mint).await.unwrap();
+    let token_desc = fixture.token_desc(0).await;
+    assert_eq!(token_desc.group, group);
+    assert_eq!(token_desc.quote_markets[0].quote_mint, quote_mint);
+
+    let (foreign_owner, foreign_group) = fixture.create_foreign_group().await;
+    let foreign_quote_mint = fixture.add_quote_currency(&foreign_owner, &foreign_group).await;
+    assert_program_error(fixture.add_token_market(&foreign_owner, &foreign_group, 1, &foreign_quote_mint).await, CONSTRAINT_RAW);
+    assert!(fixture.token_desc(1).await.quote_markets.is_empty());
+}
+
+// legs are routed to the market of a token for a quote currency, it trades the token against that quote currency
+#[tokio::test]
+async fn token_market_of_other_mints() {
+    let mut fixture = GroupFixture::new().await;
+    let (owner, group, base_mint) = (fixture.owner(), fixture.group, fixture.quote_mint);
+    let quote_mint = fixture.add_quote_currency(&owner, &group).await;
+    let (token_mint, other_mint) = (fixture.tokens[0].mint, fixture.tokens[1].mint);
+    assert_program_error(fixture.add_market_of(&owner, &group, 0, &quote_mint, &other_mint, &quote_mint).await, FruitBasketError::MarketMintMismatch);
+    assert_program_error(fixture.add_market_of(&owner, &group, 0, &quote_mint, &token_mint, &base_mint).await, FruitBasketError::MarketMintMismatch);
+    assert!(fixture.token_desc(0).await.quote_markets.is_empty());
+    fixture.add_market_of(&owner, &group, 0, &quote_mint, &token_mint, &quote_mint).await.unwrap();
+    assert_eq!(fixture.token_desc(0).await.quote_markets[0].quote_mint, quote_mint);
+}
+
+// oracle guards of a token are only set by the group which listed it
+#[tokio::test]
+async fn token_guards_of_foreign_group() {
+    let mut fixture = GroupFixture::new().await;
+    let (owner, group) = (fixture.owner(), fixture.group);
+    fixture.configure_token(&owner, &group, 0, 200, 500).await.unwrap();
+    let token_desc = fixture.token_desc(0).await;
+    assert_eq!((token_desc.max_confidence_bps, token_desc.max_deviation_bps), (200, 500));
+
+    let (foreign_owner, foreign_group) = fixture.create_foreign_group().await;
+    assert_program_error(fixture.configure_token(&foreign_owner, &foreign_group, 0, 1000, 0).await, CONSTRAINT_RAW);
+    let token_desc = fixture.token_desc(0).await;
+    assert_eq!((token_desc.max_confidence_bps, token_desc.max_deviation_bps), (200, 500));
+}
+
+// a token is only paused by the group which listed it
+#[tokio::test]
+async fn token_pause_of_foreign_group() {
+    let mut fixture = GroupFixture::new().await;
+    let (foreign_owner, foreign_group) = fixture.create_foreign_group().await;
+    assert_program_error(fixture.set_token_pause(&foreign_owner, &foreign_group, 0, true).await, CONSTRAINT_RAW);
+    assert!(!fixture.token_desc(0).await.paused);
+
+    let (owner, group) = (fixture.owner(), fixture.group);
+    fixture.set_token_pause(&owner, &group, 0, true).await.unwrap();
+    assert!(fixture.token_desc(0).await.paused);
+}
+
+#[tokio::test]
+async fn token_guards_bounded() {
+    let mut fixture = GroupFixture::new().await;
+    let (owner, group) = (fixture.owner(), fixture.group);
+    assert_program_error(fixture.configure_token(&owner, &group, 0, 0, 500).await, FruitBasketError::ConfidenceThresholdOutOfRange);
+    assert_program_error(fixture.configure_token(&owner, &group, 0, 2001, 500).await, FruitBasketError::ConfidenceThresholdOutOfRange);
+    assert_program_error(fixture.configure_token(&owner, &group, 0, 2000, 5001).await, FruitBasketError::DeviationTooHigh);
+    fixture.configure_token(&owner, &group, 0, 2000, 5000).await.unwrap();
+    fixture.configure_token(&owner, &group, 0, 1, 0).await.unwrap();
+}
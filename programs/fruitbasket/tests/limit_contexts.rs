@@ -0,0 +1,178 @@
+// Limit contexts resting on serum, filled by takers, then finalized or cancelled and reverted
+mod common;
+
+use anchor_spl::dex::serum_dex::matching::Side;
+use common::assert_program_error;
+use common::basket::{BasketFixture, BASKET_COMPONENTS, BASKET_PRICE};
+use fruitbasket::errors::FruitBasketError;
+use fruitbasket::states::{BasketComponentDescription, ContextSide};
+use fruitbasket_client::state::TradeContext;
+use solana_sdk::pubkey::Pubkey;
+
+// the third token has 9 decimals like SOL while the amounts of a context keep 6 decimals
+const DECIMALS : [u8; 3] = [6, 6, 9];
+const MAX_BUY_PRICE : u64 = BASKET_PRICE * 105 / 100;
+// quote tokens given to the transaction pool to offset the spread lost by a revert
+const REVERT_INSURANCE : u64 = 100_000_000;
+
+fn native_components_for(amount : u64) -> Vec<u64> {
+    BASKET_COMPONENTS.iter().zip(DECIMALS.iter())
+        .map(|(x, decimals)| x * amount / 1_000_000 * 10u64.pow(*decimals as u32) / 1_000_000)
+        .collect()
+}
+
+// a taker crosses the resting order of a leg for size lots and the crank credits the fill to the leg
+async fn fill_leg(fixture : &mut BasketFixture, trade_context : &Pubkey, leg : usize, taker_side : Side, size : u64) {
+    let limit_price = TradeContext::decode(&fixture.test.account_data(trade_context).await).unwrap().legs[leg].limit_price;
+    let market = fixture.tokens[leg].market.clone();
+    fixture.test.take(&market, taker_side, market.price_in_lots(limit_price), size).await;
+    fixture.test.consume_events(&market).await;
+}
+
+fn lots(fixture : &BasketFixture, leg : usize, native_amount : u64) -> u64 {
+    native_amount / fixture.tokens[leg].market.coin_lot_size
+}
+
+#[tokio::test]
+async fn limit_sell_filled() {
+    let mut fixture = BasketFixture::with_decimals(DECIMALS).await;
+    fixture.trade(ContextSide::Buy, 1_000_000, MAX_BUY_PRICE).await;
+    assert_eq!(fixture.vault_balances().await, native_components_for(1_000_000));
+    let (quote_before, _) = fixture.user_balances().await;
+
+    let trade_context = fixture.init_limit_context(ContextSide::Sell, 1_000_000, BASKET_PRICE).await.unwrap();
+    fixture.process_legs(&trade_context, &[0, 1, 2]).await.unwrap();
+    for (leg, amount) in native_components_for(1_000_000).into_iter().enumerate() {
+        let size = lots(&fixture, leg, amount);
+        fill_leg(&mut fixture, &trade_context, leg, Side::Bid, size).await;
+    }
+    fixture.process_legs(&trade_context, &[0, 1, 2]).await.unwrap();
+    fixture.finalize(&trade_context).await.unwrap();
+
+    let (quote_after, basket_tokens) = fixture.user_balances().await;
+    assert_eq!(basket_tokens, 0);
+    assert_eq!(fixture.vault_balances().await, vec![0, 0, 0]);
+    // legs are sold at the limit price, the basket creator takes 20 bps of it
+    assert_eq!(quote_after - quote_before, BASKET_PRICE - BASKET_PRICE * 20 / 10000);
+}
+
+// legs filled at the limit price spend the whole limit, the fees are paid by the fee reserve
+#[tokio::test]
+async fn limit_buy_filled() {
+    let mut fixture = BasketFixture::with_decimals(DECIMALS).await;
+    let (quote_before, _) = fixture.user_balances().await;
+
+    let trade_context = fixture.init_limit_context(ContextSide::Buy, 1_000_000, BASKET_PRICE).await.unwrap();
+    fixture.process_legs(&trade_context, &[0, 1, 2]).await.unwrap();
+    for (leg, amount) in native_components_for(1_000_000).into_iter().enumerate() {
+        let size = lots(&fixture, leg, amount);
+        fill_leg(&mut fixture, &trade_context, leg, Side::Ask, size).await;
+    }
+    fixture.process_legs(&trade_context, &[0, 1, 2]).await.unwrap();
+    fixture.finalize(&trade_context).await.unwrap();
+
+    let (quote_after, basket_tokens) = fixture.user_balances().await;
+    assert_eq!(basket_tokens, 1_000_000);
+    assert_eq!(fixture.vault_balances().await, native_components_for(1_000_000));
+    assert_eq!(quote_before - quote_after, BASKET_PRICE + BASKET_PRICE * 20 / 10000);
+}
+
+#[tokio::test]
+async fn limit_buy_cancelled_after_partial_fill() {
+    let mut fixture = BasketFixture::with_decimals(DECIMALS).await;
+    let (quote_mint, quote_pool) = (fixture.quote_mint, fixture.quote_pool);
+    fixture.test.mint_to(&quote_mint, &quote_pool, REVERT_INSURANCE).await;
+    let user_before = fixture.user_balances().await;
+
+    let trade_context = fixture.init_limit_context(ContextSide::Buy, 1_000_000, BASKET_PRICE).await.unwrap();
+    fixture.process_legs(&trade_context, &[0, 1, 2]).await.unwrap();
+    let components = native_components_for(1_000_000);
+    let (btc_lots, sol_lots) = (lots(&fixture, 0, components[0]) / 2, lots(&fixture, 2, components[2]) / 2);
+    fill_leg(&mut fixture, &trade_context, 0, Side::Ask, btc_lots).await;
+    fill_leg(&mut fixture, &trade_context, 2, Side::Ask, sol_lots).await;
+    fixture.process_legs(&trade_context, &[0, 2]).await.unwrap();
+    assert_eq!(fixture.vault_balances().await, vec![components[0] / 2, 0, components[2] / 2]);
+
+    fixture.cancel(&trade_context).await.unwrap();
+    // resting orders are cancelled and the filled parts are sold back
+    fixture.process_legs(&trade_context, &[0, 1, 2]).await.unwrap();
+    fixture.finalize(&trade_context).await.unwrap();
+
+    assert_eq!(fixture.user_balances().await, user_before);
+    assert_eq!(fixture.vault_balances().await, vec![0, 0, 0]);
+}
+
+// fills cranked on serum but not accounted by the legs yet are accounted when the orders are cancelled
+#[tokio::test]
+async fn limit_buy_cancelled_before_fills_accounted() {
+    let mut fixture = BasketFixture::with_decimals(DECIMALS).await;
+    let (quote_mint, quote_pool) = (fixture.quote_mint, fixture.quote_pool);
+    fixture.test.mint_to(&quote_mint, &quote_pool, REVERT_INSURANCE).await;
+    let user_before = fixture.user_balances().await;
+
+    let trade_context = fixture.init_limit_context(ContextSide::Buy, 1_000_000, BASKET_PRICE).await.unwrap();
+    fixture.process_legs(&trade_context, &[0, 1, 2]).await.unwrap();
+    let components = native_components_for(1_000_000);
+    let (btc_lots, sol_lots) = (lots(&fixture, 0, components[0]) / 2, lots(&fixture, 2, components[2]) / 2);
+    fill_leg(&mut fixture, &trade_context, 0, Side::Ask, btc_lots).await;
+    fill_leg(&mut fixture, &trade_context, 2, Side::Ask, sol_lots).await;
+
+    fixture.cancel(&trade_context).await.unwrap();
+    fixture.process_legs(&trade_context, &[0, 1, 2]).await.unwrap();
+    fixture.finalize(&trade_context).await.unwrap();
+
+    assert_eq!(fixture.user_balances().await, user_before);
+    assert_eq!(fixture.vault_balances().await, vec![0, 0, 0]);
+}
+
+#[tokio::test]
+async fn limit_sell_cancelled_after_partial_fill() {
+    let mut fixture = BasketFixture::with_decimals(DECIMALS).await;
+    fixture.trade(ContextSide::Buy, 1_000_000, MAX_BUY_PRICE).await;
+    let (quote_mint, quote_pool) = (fixture.quote_mint, fixture.quote_pool);
+    fixture.test.mint_to(&quote_mint, &quote_pool, REVERT_INSURANCE).await;
+    let user_before = fixture.user_balances().await;
+    let vaults_before = fixture.vault_balances().await;
+
+    let trade_context = fixture.init_limit_context(ContextSide::Sell, 1_000_000, BASKET_PRICE).await.unwrap();
+    fixture.process_legs(&trade_context, &[0, 1, 2]).await.unwrap();
+    let sol_lots = lots(&fixture, 2, vaults_before[2]) / 4;
+    fill_leg(&mut fixture, &trade_context, 2, Side::Bid, sol_lots).await;
+    fixture.process_leg(&trade_context, 2).await.unwrap();
+
+    fixture.cancel(&trade_context).await.unwrap();
+    // the sold part is bought back with the quote of the transaction pool
+    fixture.process_legs(&trade_context, &[0, 1, 2]).await.unwrap();
+    fixture.finalize(&trade_context).await.unwrap();
+
+    assert_eq!(fixture.user_balances().await, user_before);
+    assert_eq!(fixture.vault_balances().await, vaults_before);
+}
+
+// a resting limit context would block the rebalance of its basket until the user cancels it, the curator cancels it instead
+#[tokio::test]
+async fn limit_context_cancelled_by_curator() {
+    let mut fixture = BasketFixture::with_decimals(DECIMALS).await;
+    let (quote_mint, quote_pool) = (fixture.quote_mint, fixture.quote_pool);
+    fixture.test.mint_to(&quote_mint, &quote_pool, REVERT_INSURANCE).await;
+    let user_before = fixture.user_balances().await;
+    let new_components = fixture.tokens.iter().zip(BASKET_COMPONENTS.iter().rev())
+        .map(|(token, amount)| BasketComponentDescription { token_mint : token.mint, amount : *amount, decimal : 6 })
+        .collect::<Vec<_>>();
+
+    let trade_context = fixture.init_limit_context(ContextSide::Buy, 1_000_000, BASKET_PRICE).await.unwrap();
+    fixture.process_legs(&trade_context, &[0, 1, 2]).await.unwrap();
+    assert_program_error(fixture.init_rebalance_context(new_components.clone()).await, FruitBasketError::BasketHasOpenContexts);
+    let stranger = fixture.test.create_wallet(1_000_000_000).await;
+    assert_program_error(fixture.cancel_as(&trade_context, &stranger).await, FruitBasketError::AccountsMismatch);
+    // the owner of the group created the basket of the fixture
+    let curator = fixture.owner();
+    fixture.cancel_as(&trade_context, &curator).await.unwrap();
+    fixture.process_legs(&trade_context, &[0, 1, 2]).await.unwrap();
+    fixture.finalize(&trade_context).await.unwrap();
+    assert_eq!(fixture.user_balances().await, user_before);
+
+    // a new blockhash keeps the rebalance from being deduplicated with the failed one
+    fixture.test.context.get_new_latest_blockhash().await.unwrap();
+    fixture.init_rebalance_context(new_components).await.unwrap();
+}
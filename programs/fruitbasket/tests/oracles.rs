@@ -0,0 +1,94 @@
+// Token prices read from mock oracle feeds which are stale, halted or too uncertain
+mod common;
+
+use common::assert_program_error;
+use common::basket::BasketFixture;
+use common::group::{GroupFixture, PRICE_EXPONENT, TOKEN_PRICES};
+use fruitbasket::errors::FruitBasketError;
+use mock_oracle::OracleStatus;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+
+// group default of the maximum age of a price
+const MAX_PRICE_AGE_SLOTS : u64 = 50;
+
+async fn set_btc_oracle(fixture : &mut GroupFixture, conf : u64, status : OracleStatus, slot : u64) {
+    let oracle = Keypair::from_bytes(&fixture.tokens[0].price_oracle.to_bytes()).unwrap();
+    fixture.test.set_oracle(&oracle, TOKEN_PRICES[0] as i64, conf, PRICE_EXPONENT, status, slot).await;
+    // a new blockhash keeps the next price update from being deduplicated
+    fixture.test.context.get_new_latest_blockhash().await.unwrap();
+}
+
+#[tokio::test]
+async fn stale_price() {
+    let mut fixture = GroupFixture::new().await;
+    let slot = fixture.test.slot().await + 1000;
+    fixture.test.context.warp_to_slot(slot).unwrap();
+
+    set_btc_oracle(&mut fixture, TOKEN_PRICES[0] / 100, OracleStatus::Trading, slot - MAX_PRICE_AGE_SLOTS - 1).await;
+    assert!(fixture.update_token_price(0).await.is_err());
+    set_btc_oracle(&mut fixture, TOKEN_PRICES[0] / 100, OracleStatus::Trading, slot - MAX_PRICE_AGE_SLOTS).await;
+    fixture.update_token_price(0).await.unwrap();
+}
+
+#[tokio::test]
+async fn halted_price() {
+    let mut fixture = GroupFixture::new().await;
+    let slot = fixture.test.slot().await;
+
+    for status in [OracleStatus::Halted, OracleStatus::Auction, OracleStatus::Unknown].iter() {
+        set_btc_oracle(&mut fixture, TOKEN_PRICES[0] / 100, *status, slot).await;
+        assert!(fixture.update_token_price(0).await.is_err());
+    }
+    set_btc_oracle(&mut fixture, TOKEN_PRICES[0] / 100, OracleStatus::Trading, slot).await;
+    fixture.update_token_price(0).await.unwrap();
+}
+
+// tokens accept a confidence of 10% of the price by default
+#[tokio::test]
+async fn wide_confidence() {
+    let mut fixture = GroupFixture::new().await;
+    let slot = fixture.test.slot().await;
+
+    set_btc_oracle(&mut fixture, TOKEN_PRICES[0] / 5, OracleStatus::Trading, slot).await;
+    assert!(fixture.update_token_price(0).await.is_err());
+    set_btc_oracle(&mut fixture, TOKEN_PRICES[0] / 10, OracleStatus::Trading, slot).await;
+    fixture.update_token_price(0).await.unwrap();
+}
+
+#[tokio::test]
+async fn product_linked_to_price() {
+    let mut fixture = GroupFixture::new().await;
+    let (price_oracle, product_oracle) = (fixture.tokens[0].price_oracle.pubkey(), fixture.tokens[0].product_oracle);
+
+    let price_data = fixture.test.account_data(&price_oracle).await;
+    let price = pyth_client::cast::<pyth_client::Price>(&price_data);
+    assert_eq!(price.prod.val, product_oracle.to_bytes());
+    assert_eq!(price.agg.price, TOKEN_PRICES[0] as i64);
+    assert_eq!(price.expo, PRICE_EXPONENT);
+
+    let product_data = fixture.test.account_data(&product_oracle).await;
+    let product = pyth_client::cast::<pyth_client::Product>(&product_data);
+    assert_eq!(product.magic, pyth_client::MAGIC);
+    assert_eq!(product.atype, pyth_client::AccountType::Product as u32);
+    assert_eq!(product.px_acc.val, price_oracle.to_bytes());
+    assert_eq!(&product.attr[..14], b"\x06symbol\x06TOKEN0");
+}
+
+// a basket is only priced from the token descriptions of the program
+#[tokio::test]
+async fn forged_token_desc() {
+    let mut fixture = BasketFixture::new().await;
+    fixture.update_prices().await;
+    let mut token_descs = (0..fixture.tokens.len()).map(|token| fixture.token_desc_address(token)).collect::<Vec<_>>();
+
+    // copy of the description of the first token owned by another program
+    let forged_desc = Pubkey::new_unique();
+    let data = fixture.test.account_data(&token_descs[0]).await;
+    fixture.test.set_account(&forged_desc, &Pubkey::new_unique(), data).await;
+    let real_desc = std::mem::replace(&mut token_descs[0], forged_desc);
+    assert_program_error(fixture.update_basket_price(&token_descs).await, FruitBasketError::ErrorDeserializeTokeDesc);
+
+    token_descs[0] = real_desc;
+    fixture.update_basket_price(&token_descs).await.unwrap();
+}
@@ -0,0 +1,92 @@
+// Trade contexts simulated by the client against the orderbooks, then executed on serum
+mod common;
+
+use common::assert_program_error;
+use common::basket::{BasketFixture, BASKET_PRICE};
+use common::group::{PRICE_EXPONENT, TOKEN_PRICES};
+use fruitbasket::errors::FruitBasketError;
+use fruitbasket::states::ContextSide;
+use fruitbasket_client::serum::OrderBook;
+use fruitbasket_client::simulation::{simulate_trade, RevertReason, TradeSimulation};
+use fruitbasket_client::state::{Basket, Group, TokenDescription, TradeContext};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Keypair;
+
+const MAX_BUY_PRICE : u64 = BASKET_PRICE * 105 / 100;
+const MIN_SELL_PRICE : u64 = BASKET_PRICE * 95 / 100;
+
+// simulation with the accounts of the fixture, quote the legs can spend is read from the trade context
+async fn simulate(fixture : &mut BasketFixture, trade_context : &Pubkey, side : ContextSide, amount : u64) -> TradeSimulation {
+    let trade_context = TradeContext::decode(&fixture.test.account_data(trade_context).await).unwrap();
+    let quote_deposit = trade_context.quote_deposited - trade_context.fee_reserve;
+    let group = Group::decode(&fixture.test.account_data(&fixture.group).await).unwrap();
+    let basket_key = fixture.basket;
+    let basket = Basket::decode(&fixture.test.account_data(&basket_key).await).unwrap();
+    let accounts = fixture.tokens.iter()
+        .map(|x| (fruitbasket_client::pda::token_desc(&x.mint).0, x.market.market, x.market.bids, x.market.asks))
+        .collect::<Vec<_>>();
+    let mut token_descs = Vec::new();
+    let mut books = Vec::new();
+    for (token_desc, market, bids, asks) in accounts.iter() {
+        token_descs.push(TokenDescription::decode(&fixture.test.account_data(token_desc).await).unwrap());
+        let (market_data, bids_data, asks_data) = (fixture.test.account_data(market).await,
+                                                   fixture.test.account_data(bids).await,
+                                                   fixture.test.account_data(asks).await);
+        books.push(OrderBook::decode(&market_data, &bids_data, &asks_data).unwrap());
+    }
+    simulate_trade(&group, &basket, &token_descs, &books, &fixture.quote_mint, side, amount, quote_deposit).unwrap()
+}
+
+#[tokio::test]
+async fn simulated_buy() {
+    let mut fixture = BasketFixture::new().await;
+    let (quote_before, _) = fixture.user_balances().await;
+
+    let trade_context = fixture.init_trade_context(ContextSide::Buy, 1_000_000, MAX_BUY_PRICE).await.unwrap();
+    let simulation = simulate(&mut fixture, &trade_context, ContextSide::Buy, 1_000_000).await;
+    fixture.process_legs(&trade_context, &[0, 1, 2]).await.unwrap();
+    fixture.finalize(&trade_context).await.unwrap();
+
+    assert!(!simulation.would_revert());
+    let (quote_after, _) = fixture.user_balances().await;
+    assert_eq!(quote_before - quote_after, simulation.quote_amount());
+    assert_eq!(fixture.vault_balances().await, simulation.legs.iter().map(|x| x.filled).collect::<Vec<_>>());
+}
+
+#[tokio::test]
+async fn simulated_sell() {
+    let mut fixture = BasketFixture::new().await;
+    fixture.trade(ContextSide::Buy, 1_000_000, MAX_BUY_PRICE).await;
+    let (quote_before, _) = fixture.user_balances().await;
+    let vaults_before = fixture.vault_balances().await;
+
+    let trade_context = fixture.init_trade_context(ContextSide::Sell, 300_000, MIN_SELL_PRICE).await.unwrap();
+    let simulation = simulate(&mut fixture, &trade_context, ContextSide::Sell, 300_000).await;
+    fixture.process_legs(&trade_context, &[0, 1, 2]).await.unwrap();
+    fixture.finalize(&trade_context).await.unwrap();
+
+    assert!(!simulation.would_revert());
+    let (quote_after, _) = fixture.user_balances().await;
+    assert_eq!(quote_after - quote_before, simulation.quote_amount());
+    let sold = vaults_before.iter().zip(fixture.vault_balances().await.iter()).map(|(x, y)| x - y).collect::<Vec<_>>();
+    assert_eq!(sold, simulation.legs.iter().map(|x| x.filled).collect::<Vec<_>>());
+}
+
+// the oracle of the second token moved away from its book, the best ask is out of the band around the cached price
+#[tokio::test]
+async fn simulated_revert() {
+    let mut fixture = BasketFixture::new().await;
+    let oracle = Keypair::from_bytes(&fixture.tokens[1].price_oracle.to_bytes()).unwrap();
+    let price = TOKEN_PRICES[1] * 95 / 100;
+    fixture.test.set_oracle_price(&oracle, price as i64, price / 100, PRICE_EXPONENT).await;
+    fixture.test.context.get_new_latest_blockhash().await.unwrap();
+    fixture.update_prices().await;
+
+    let trade_context = fixture.init_trade_context(ContextSide::Buy, 1_000_000, MAX_BUY_PRICE).await.unwrap();
+    let simulation = simulate(&mut fixture, &trade_context, ContextSide::Buy, 1_000_000).await;
+
+    assert_eq!(simulation.revert, Some(RevertReason::LegOutOfBand(1)));
+    assert_eq!(simulation.legs[1].filled, 0);
+    fixture.process_leg(&trade_context, 0).await.unwrap();
+    assert_program_error(fixture.process_leg(&trade_context, 1).await, FruitBasketError::LegPriceOutOfBand);
+}
@@ -0,0 +1,304 @@
+// Buy, sell and reverted trade contexts against serum markets, mirroring the trade tests of tests/fruitbasket.ts
+mod common;
+
+use common::basket::{BasketFixture, BASKET_COMPONENTS, BASKET_PRICE};
+use common::group::{PRICE_EXPONENT, TOKEN_PRICES};
+use common::{assert_program_error, CONSTRAINT_RAW};
+use fruitbasket::errors::FruitBasketError;
+use fruitbasket::states::{BasketComponentDescription, ContextSide};
+use fruitbasket_client::state::TradeContext;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Keypair;
+
+// worst prices accepted for a basket, 5% around its oracle price
+const MAX_BUY_PRICE : u64 = BASKET_PRICE * 105 / 100;
+const MIN_SELL_PRICE : u64 = BASKET_PRICE * 95 / 100;
+// quote tokens given to the transaction pool to offset the spread lost by a revert
+const REVERT_INSURANCE : u64 = 100_000_000;
+// quote added by the user to a swap, it pays for the spread of the legs
+const SWAP_QUOTE_IN : u64 = 10_000_000;
+
+fn components_for(amount : u64) -> Vec<u64> {
+    BASKET_COMPONENTS.iter().map(|x| x * amount / 1_000_000).collect()
+}
+
+#[tokio::test]
+async fn buy() {
+    let mut fixture = BasketFixture::new().await;
+    let (quote_before, _) = fixture.user_balances().await;
+
+    fixture.trade(ContextSide::Buy, 1_000_000, MAX_BUY_PRICE).await;
+
+    let (quote_after, basket_tokens) = fixture.user_balances().await;
+    assert_eq!(basket_tokens, 1_000_000);
+    assert_eq!(fixture.vault_balances().await, components_for(1_000_000));
+    assert!(quote_before - quote_after <= MAX_BUY_PRICE);
+    // the trade context is closed once finalized
+    let trade_context = fixture.trade_context(0);
+    assert!(!fixture.test.account_exists(&trade_context).await);
+}
+
+#[tokio::test]
+async fn sell() {
+    let mut fixture = BasketFixture::new().await;
+    fixture.trade(ContextSide::Buy, 1_000_000, MAX_BUY_PRICE).await;
+    let (quote_before, _) = fixture.user_balances().await;
+
+    fixture.trade(ContextSide::Sell, 300_000, MIN_SELL_PRICE).await;
+
+    let (quote_after, basket_tokens) = fixture.user_balances().await;
+    assert_eq!(basket_tokens, 700_000);
+    assert_eq!(fixture.vault_balances().await, components_for(700_000));
+    assert!(quote_after - quote_before >= MIN_SELL_PRICE * 3 / 10);
+}
+
+// the fees of a buy are deposited on top of the quote its legs can spend
+#[tokio::test]
+async fn buy_fee_reserved() {
+    let mut fixture = BasketFixture::new().await;
+    fixture.set_fees(30, 0).await.unwrap();
+    let (quote_before, _) = fixture.user_balances().await;
+
+    let trade_context = fixture.init_trade_context(ContextSide::Buy, 1_000_000, MAX_BUY_PRICE).await.unwrap();
+    let context = TradeContext::decode(&fixture.test.account_data(&trade_context).await).unwrap();
+    // the basket creator takes 20 bps
+    assert_eq!(context.fee_reserve, (context.quote_deposited - context.fee_reserve) * 50 / 10000);
+    assert_eq!(fixture.user_balances().await.0, quote_before - context.quote_deposited);
+    fixture.process_legs(&trade_context, &[0, 1, 2]).await.unwrap();
+    let quote_spent = TradeContext::decode(&fixture.test.account_data(&trade_context).await).unwrap().quote_spent;
+    let unknown_vault = Pubkey::new_unique();
+    assert_program_error(fixture.finalize_with_creator_fee_vault(&trade_context, &unknown_vault).await, FruitBasketError::UnknownFeeVault);
+    fixture.finalize(&trade_context).await.unwrap();
+
+    let (fee, creator_fee) = fixture.fee_balances().await;
+    assert_eq!(fee, quote_spent * 30 / 10000);
+    assert_eq!(creator_fee, quote_spent * 20 / 10000);
+    assert_eq!(fixture.user_balances().await.0, quote_before - quote_spent - fee - creator_fee);
+}
+
+// a context is finalized with the fees of the group when it was created
+#[tokio::test]
+async fn fees_set_at_init() {
+    let mut fixture = BasketFixture::new().await;
+    fixture.set_fees(30, 0).await.unwrap();
+    let trade_context = fixture.init_trade_context(ContextSide::Buy, 1_000_000, MAX_BUY_PRICE).await.unwrap();
+    let context = TradeContext::decode(&fixture.test.account_data(&trade_context).await).unwrap();
+    assert_eq!((context.mint_fee_bps, context.redeem_fee_bps, context.creator_fee_bps), (30, 0, 20));
+    fixture.set_fees(100, 100).await.unwrap();
+    fixture.process_legs(&trade_context, &[0, 1, 2]).await.unwrap();
+    let quote_spent = TradeContext::decode(&fixture.test.account_data(&trade_context).await).unwrap().quote_spent;
+    fixture.finalize(&trade_context).await.unwrap();
+
+    assert_eq!(fixture.fee_balances().await, (quote_spent * 30 / 10000, quote_spent * 20 / 10000));
+}
+
+// the deposit of a buy is the worst price of a whole basket for the amount bought
+#[tokio::test]
+async fn buy_deposit_scaled_by_amount() {
+    let mut fixture = BasketFixture::new().await;
+    let trade_context = fixture.init_trade_context(ContextSide::Buy, 2_000_000, MAX_BUY_PRICE).await.unwrap();
+    let context = TradeContext::decode(&fixture.test.account_data(&trade_context).await).unwrap();
+    assert_eq!(context.quote_deposited - context.fee_reserve, MAX_BUY_PRICE * 2);
+    fixture.process_legs(&trade_context, &[0, 1, 2]).await.unwrap();
+    fixture.finalize(&trade_context).await.unwrap();
+
+    assert_eq!(fixture.user_balances().await.1, 2_000_000);
+    assert_eq!(fixture.vault_balances().await, components_for(2_000_000));
+}
+
+// a leg smaller than one lot of its market is treated without trading, the other legs are traded
+#[tokio::test]
+async fn leg_smaller_than_a_lot() {
+    let mut fixture = BasketFixture::new().await;
+    // 500 BTC units for a lot of 1000
+    let trade_context = fixture.init_trade_context(ContextSide::Buy, 50_000, MAX_BUY_PRICE).await.unwrap();
+    fixture.process_legs(&trade_context, &[0, 1, 2]).await.unwrap();
+
+    let context = TradeContext::decode(&fixture.test.account_data(&trade_context).await).unwrap();
+    assert!(context.legs.iter().all(|x| x.treated));
+    assert_eq!((context.legs[0].amount_left, context.quote_spent > 0), (500, true));
+    assert_eq!(fixture.vault_balances().await, vec![0, 5_000, 100_000]);
+}
+
+#[tokio::test]
+async fn revert_on_buy() {
+    let mut fixture = BasketFixture::new().await;
+    let (quote_mint, quote_pool) = (fixture.quote_mint, fixture.quote_pool);
+    fixture.test.mint_to(&quote_mint, &quote_pool, REVERT_INSURANCE).await;
+    let user_before = fixture.user_balances().await;
+    let vaults_before = fixture.vault_balances().await;
+
+    let trade_context = fixture.init_trade_context(ContextSide::Buy, 1_000_000, MAX_BUY_PRICE).await.unwrap();
+    fixture.process_legs(&trade_context, &[0, 1]).await.unwrap();
+    fixture.revert(&trade_context).await.unwrap();
+    // every leg is processed again, bought legs are sold back and the pending one is skipped
+    fixture.process_legs(&trade_context, &[0, 1, 2]).await.unwrap();
+    fixture.finalize(&trade_context).await.unwrap();
+
+    assert_eq!(fixture.user_balances().await, user_before);
+    assert_eq!(fixture.vault_balances().await, vaults_before);
+}
+
+#[tokio::test]
+async fn revert_on_sell() {
+    let mut fixture = BasketFixture::new().await;
+    fixture.trade(ContextSide::Buy, 1_000_000, MAX_BUY_PRICE).await;
+    let (quote_mint, quote_pool) = (fixture.quote_mint, fixture.quote_pool);
+    fixture.test.mint_to(&quote_mint, &quote_pool, REVERT_INSURANCE).await;
+    let user_before = fixture.user_balances().await;
+    let vaults_before = fixture.vault_balances().await;
+
+    let trade_context = fixture.init_trade_context(ContextSide::Sell, 200_000, MIN_SELL_PRICE).await.unwrap();
+    fixture.process_legs(&trade_context, &[1, 2]).await.unwrap();
+    fixture.revert(&trade_context).await.unwrap();
+    fixture.process_legs(&trade_context, &[0, 1, 2]).await.unwrap();
+    // no fee is taken on a reverted context, its creator fee vault does not have to exist
+    fixture.finalize_with_creator_fee_vault(&trade_context, &Pubkey::new_unique()).await.unwrap();
+
+    assert_eq!(fixture.user_balances().await, user_before);
+    assert_eq!(fixture.vault_balances().await, vaults_before);
+}
+
+// a reverted swap buys back its sold legs with its own quote, not with the whole shared pool
+#[tokio::test]
+async fn revert_on_swap() {
+    let mut fixture = BasketFixture::new().await;
+    fixture.trade(ContextSide::Buy, 1_000_000, MAX_BUY_PRICE).await;
+    let (quote_mint, quote_pool) = (fixture.quote_mint, fixture.quote_pool);
+    fixture.test.mint_to(&quote_mint, &quote_pool, REVERT_INSURANCE).await;
+    // one destination basket is worth one basket of the fixture, it keeps its SOL and trades its ETH for BTC
+    let components = vec![BasketComponentDescription { token_mint : fixture.tokens[0].mint, amount : 20_000, decimal : 6 },
+                          BasketComponentDescription { token_mint : fixture.tokens[2].mint, amount : 2_000_000, decimal : 6 }];
+    let destination = fixture.add_swap_destination(1, components).await;
+    let user_before = fixture.user_balances().await;
+    let vaults_before = fixture.vault_balances().await;
+
+    let trade_context = fixture.init_swap_context(&destination, 1_000_000, 1_000_000, SWAP_QUOTE_IN).await.unwrap();
+    fixture.process_leg(&trade_context, 1).await.unwrap();
+    fixture.revert(&trade_context).await.unwrap();
+    let context = TradeContext::decode(&fixture.test.account_data(&trade_context).await).unwrap();
+    assert_eq!(context.quote_amount_left, context.quote_deposited + context.quote_received);
+    assert!(context.quote_amount_left < fixture.test.token_balance(&quote_pool).await);
+    fixture.process_legs(&trade_context, &[0, 1, 2]).await.unwrap();
+    fixture.finalize_swap(&trade_context, &destination).await.unwrap();
+
+    assert_eq!(fixture.user_balances().await, user_before);
+    assert_eq!(fixture.vault_balances().await, vaults_before);
+    assert_eq!(fixture.test.token_balance(&destination.user_basket).await, 0);
+}
+
+// the fees of a swap are deposited on top of the quote added by the user, its buy legs cannot spend them
+#[tokio::test]
+async fn swap_fee_reserved() {
+    let mut fixture = BasketFixture::new().await;
+    fixture.trade(ContextSide::Buy, 1_000_000, MAX_BUY_PRICE).await;
+    fixture.set_fees(30, 40).await.unwrap();
+    let components = vec![BasketComponentDescription { token_mint : fixture.tokens[0].mint, amount : 20_000, decimal : 6 },
+                          BasketComponentDescription { token_mint : fixture.tokens[2].mint, amount : 2_000_000, decimal : 6 }];
+    let destination = fixture.add_swap_destination(1, components).await;
+    let (quote_before, _) = fixture.user_balances().await;
+    let fees_before = fixture.fee_balances().await;
+
+    let trade_context = fixture.init_swap_context(&destination, 1_000_000, 1_000_000, SWAP_QUOTE_IN).await.unwrap();
+    let context = TradeContext::decode(&fixture.test.account_data(&trade_context).await).unwrap();
+    assert!(context.fee_reserve > 0);
+    assert_eq!(context.quote_deposited, SWAP_QUOTE_IN + context.fee_reserve);
+    assert_eq!(fixture.user_balances().await.0, quote_before - context.quote_deposited);
+    // the ETH leg is sold before the BTC leg is bought, SOL is kept by both baskets
+    fixture.process_legs(&trade_context, &[1, 0, 2]).await.unwrap();
+    let context = TradeContext::decode(&fixture.test.account_data(&trade_context).await).unwrap();
+    assert!(context.quote_amount_left >= context.fee_reserve);
+    fixture.finalize_swap(&trade_context, &destination).await.unwrap();
+
+    // the source basket creator takes 20 bps of the quote received, the destination basket has no creator fee
+    let fee = context.quote_spent * 30 / 10000 + context.quote_received * 40 / 10000;
+    let creator_fee = context.quote_received * 20 / 10000;
+    assert_eq!(fixture.fee_balances().await, (fees_before.0 + fee, fees_before.1 + creator_fee));
+    assert_eq!(fixture.user_balances().await.0, quote_before + context.quote_received - context.quote_spent - fee - creator_fee);
+    assert_eq!(fixture.test.token_balance(&destination.user_basket).await, 1_000_000);
+}
+
+// a leg can not be processed twice in the same direction
+#[tokio::test]
+async fn leg_processed_once() {
+    let mut fixture = BasketFixture::new().await;
+    let trade_context = fixture.init_trade_context(ContextSide::Buy, 1_000_000, MAX_BUY_PRICE).await.unwrap();
+    fixture.process_leg(&trade_context, 0).await.unwrap();
+    let vaults = fixture.vault_balances().await;
+    // a new blockhash keeps the transaction from being deduplicated
+    fixture.test.context.get_new_latest_blockhash().await.unwrap();
+    let _ = fixture.process_leg(&trade_context, 0).await;
+    assert_eq!(fixture.vault_balances().await, vaults);
+}
+
+// the oracle moved away from the book, the best ask is out of the band around the cached price
+#[tokio::test]
+async fn leg_price_out_of_band() {
+    let mut fixture = BasketFixture::new().await;
+    let oracle = Keypair::from_bytes(&fixture.tokens[0].price_oracle.to_bytes()).unwrap();
+    let price = TOKEN_PRICES[0] * 95 / 100;
+    fixture.test.set_oracle_price(&oracle, price as i64, price / 100, PRICE_EXPONENT).await;
+    fixture.test.context.get_new_latest_blockhash().await.unwrap();
+    fixture.update_prices().await;
+
+    let trade_context = fixture.init_trade_context(ContextSide::Buy, 1_000_000, MAX_BUY_PRICE).await.unwrap();
+    assert_program_error(fixture.process_leg(&trade_context, 0).await, FruitBasketError::LegPriceOutOfBand);
+    fixture.process_leg(&trade_context, 1).await.unwrap();
+}
+
+// legs trade with the open orders of their basket, not with the open orders given when the token was listed
+#[tokio::test]
+async fn leg_with_listing_open_orders() {
+    let mut fixture = BasketFixture::new().await;
+    let trade_context = fixture.init_trade_context(ContextSide::Buy, 1_000_000, MAX_BUY_PRICE).await.unwrap();
+    let open_orders = fixture.tokens[0].open_orders;
+    assert_program_error(fixture.process_leg_with_open_orders(&trade_context, 0, &open_orders).await, FruitBasketError::UnknownOpenOrders);
+    fixture.process_leg(&trade_context, 0).await.unwrap();
+}
+
+// legs are processed with the group of the basket only
+#[tokio::test]
+async fn leg_of_foreign_group() {
+    let mut fixture = BasketFixture::new().await;
+    let (_, foreign_group) = fixture.create_foreign_group().await;
+    let trade_context = fixture.init_trade_context(ContextSide::Buy, 1_000_000, MAX_BUY_PRICE).await.unwrap();
+
+    let group = std::mem::replace(&mut fixture.group, foreign_group);
+    assert_program_error(fixture.process_leg(&trade_context, 0).await, CONSTRAINT_RAW);
+    fixture.group = group;
+    fixture.process_leg(&trade_context, 0).await.unwrap();
+}
+
+// contexts are opened with the group of the basket only, its pool and its settings
+#[tokio::test]
+async fn context_of_foreign_group() {
+    let mut fixture = BasketFixture::new().await;
+    let (_, foreign_group) = fixture.create_foreign_group().await;
+    let (quote_before, _) = fixture.user_balances().await;
+
+    let group = std::mem::replace(&mut fixture.group, foreign_group);
+    assert_program_error(fixture.init_trade_context(ContextSide::Buy, 1_000_000, MAX_BUY_PRICE).await, CONSTRAINT_RAW);
+    assert_eq!(fixture.user_balances().await.0, quote_before);
+    fixture.group = group;
+    fixture.init_trade_context(ContextSide::Buy, 1_000_000, MAX_BUY_PRICE).await.unwrap();
+}
+
+// a basket is rebalanced against its vaults once its contexts are finalized
+#[tokio::test]
+async fn rebalance_blocked_by_open_context() {
+    let mut fixture = BasketFixture::new().await;
+    let new_components = fixture.tokens.iter().zip(BASKET_COMPONENTS.iter().rev())
+        .map(|(token, amount)| BasketComponentDescription { token_mint : token.mint, amount : *amount, decimal : 6 })
+        .collect::<Vec<_>>();
+    let trade_context = fixture.init_trade_context(ContextSide::Buy, 1_000_000, MAX_BUY_PRICE).await.unwrap();
+
+    assert_program_error(fixture.init_rebalance_context(new_components.clone()).await, FruitBasketError::BasketHasOpenContexts);
+    fixture.process_legs(&trade_context, &[0, 1, 2]).await.unwrap();
+    fixture.finalize(&trade_context).await.unwrap();
+    // a new blockhash keeps the rebalance from being deduplicated with the failed one
+    fixture.test.context.get_new_latest_blockhash().await.unwrap();
+    fixture.init_rebalance_context(new_components).await.unwrap();
+
+    // the basket cannot be traded until the rebalance is finalized
+    assert_program_error(fixture.init_trade_context(ContextSide::Buy, 2_000_000, MAX_BUY_PRICE * 2).await, FruitBasketError::BasketRebalancing);
+}
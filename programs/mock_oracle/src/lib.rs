@@ -0,0 +1,151 @@
+use anchor_lang::prelude::*;
+use pyth_client::{AccountType, CorpAction, Price, PriceStatus, PriceType, Product};
+use std::mem::size_of;
+declare_id!("8qKF2ndxS1PAtKksgKscjJMvaDfZQV9iMykscxLbU5cM");
+
+// Pyth price and product accounts for local tests.
+// Accounts are created by the test with this program as owner, every write is signed by the account keypair.
+#[program]
+pub mod mock_oracle {
+    use super::*;
+
+    // header of a price account, its price is unknown until set
+    pub fn create_price(ctx : Context<CreatePrice>) -> ProgramResult {
+        let mut data = ctx.accounts.price.try_borrow_mut_data()?;
+        let price = cast_mut::<Price>(&mut data)?;
+        if price.magic == pyth_client::MAGIC {
+            return Err(MockOracleError::AlreadyInitialized.into());
+        }
+        price.magic = pyth_client::MAGIC;
+        price.ver = pyth_client::VERSION_2;
+        price.atype = AccountType::Price as u32;
+        price.size = size_of::<Price>() as u32;
+        price.ptype = PriceType::Price;
+        price.agg.status = PriceStatus::Unknown;
+        price.agg.corp_act = CorpAction::NoCorpAct;
+        Ok(())
+    }
+
+    // aggregate price published at slot, an old slot makes a stale price
+    pub fn set_price(ctx : Context<SetPrice>, price : i64, conf : u64, expo : i32, status : OracleStatus, slot : u64) -> ProgramResult {
+        let mut data = ctx.accounts.price.try_borrow_mut_data()?;
+        let account = price_mut(&mut data)?;
+        account.prev_slot = account.valid_slot;
+        account.prev_price = account.agg.price;
+        account.prev_conf = account.agg.conf;
+        account.expo = expo;
+        account.last_slot = slot;
+        account.valid_slot = slot;
+        account.twap.val = price;
+        account.twac.val = conf as i64;
+        account.agg.price = price;
+        account.agg.conf = conf;
+        account.agg.status = status.into();
+        account.agg.pub_slot = slot;
+        Ok(())
+    }
+
+    // product account with its reference attributes, linked both ways with its price account
+    pub fn set_product(ctx : Context<SetProduct>, attributes : Vec<ProductAttribute>) -> ProgramResult {
+        let mut attr = Vec::new();
+        for attribute in attributes.iter() {
+            for text in [&attribute.key, &attribute.value].iter() {
+                if text.len() > u8::MAX as usize {
+                    return Err(MockOracleError::AttributesTooLong.into());
+                }
+                attr.push(text.len() as u8);
+                attr.extend_from_slice(text.as_bytes());
+            }
+        }
+        if attr.len() > pyth_client::PROD_ATTR_SIZE {
+            return Err(MockOracleError::AttributesTooLong.into());
+        }
+
+        let mut price_data = ctx.accounts.price.try_borrow_mut_data()?;
+        price_mut(&mut price_data)?.prod.val = ctx.accounts.product.key.to_bytes();
+        let mut product_data = ctx.accounts.product.try_borrow_mut_data()?;
+        let product = cast_mut::<Product>(&mut product_data)?;
+        product.magic = pyth_client::MAGIC;
+        product.ver = pyth_client::VERSION_2;
+        product.atype = AccountType::Product as u32;
+        product.size = (pyth_client::PROD_HDR_SIZE + attr.len()) as u32;
+        product.px_acc.val = ctx.accounts.price.key.to_bytes();
+        product.attr = [0; pyth_client::PROD_ATTR_SIZE];
+        product.attr[..attr.len()].copy_from_slice(&attr);
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct CreatePrice<'info> {
+    #[account(mut, signer)]
+    pub price : AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetPrice<'info> {
+    #[account(mut, signer)]
+    pub price : AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetProduct<'info> {
+    #[account(mut, signer)]
+    pub product : AccountInfo<'info>,
+    #[account(mut, signer)]
+    pub price : AccountInfo<'info>,
+}
+
+// status of an aggregate price, only trading prices are valid
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq)]
+pub enum OracleStatus {
+    Unknown,
+    Trading,
+    Halted,
+    Auction,
+}
+
+impl From<OracleStatus> for PriceStatus {
+    fn from(status : OracleStatus) -> Self {
+        match status {
+            OracleStatus::Unknown => PriceStatus::Unknown,
+            OracleStatus::Trading => PriceStatus::Trading,
+            OracleStatus::Halted => PriceStatus::Halted,
+            OracleStatus::Auction => PriceStatus::Auction,
+        }
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+pub struct ProductAttribute {
+    pub key : String,
+    pub value : String,
+}
+
+#[error]
+pub enum MockOracleError {
+    #[msg("Account is too small for a pyth account")]
+    AccountTooSmall,
+    #[msg("Price account is already initialized")]
+    AlreadyInitialized,
+    #[msg("Account is not a pyth price account")]
+    NotAPriceAccount,
+    #[msg("Product attributes do not fit in the product account")]
+    AttributesTooLong,
+}
+
+// same cast as pyth_client::cast, account data is aligned on 8 bytes
+fn cast_mut<T>(data : &mut [u8]) -> std::result::Result<&mut T, ProgramError> {
+    if data.len() < size_of::<T>() {
+        return Err(MockOracleError::AccountTooSmall.into());
+    }
+    Ok(unsafe { &mut *(data.as_mut_ptr() as *mut T) })
+}
+
+fn price_mut(data : &mut [u8]) -> std::result::Result<&mut Price, ProgramError> {
+    let price = cast_mut::<Price>(data)?;
+    if price.magic != pyth_client::MAGIC || price.atype != AccountType::Price as u32 {
+        return Err(MockOracleError::NotAPriceAccount.into());
+    }
+    Ok(price)
+}
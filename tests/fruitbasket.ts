@@ -91,6 +91,7 @@ describe("fruitbasket", () => {
       produce_oracles.push(oracle.createProductAccount());
     }
     let oracle_promises = [];
+    const pub_slot = BigInt(await connection.getSlot());
     for (let i = 0; i < nb_tokens; ++i) {
       oracle_promises.push(
         oracle.updatePriceAccount(await price_oracles[i], {
@@ -98,6 +99,7 @@ describe("fruitbasket", () => {
           aggregatePriceInfo: {
             price: token_prices[i],
             conf: token_prices[i] / 100n, // 100 bps or 1% of the price of USDC
+            pubSlot: pub_slot,
           },
         })
       );
@@ -106,12 +108,18 @@ describe("fruitbasket", () => {
     for (let i = 0; i < nb_tokens; ++i) {
       await Promise.all(oracle_promises);
     }
+    for (let i = 0; i < nb_tokens; ++i) {
+      await oracle.updateProductAccount(await produce_oracles[i], await price_oracles[i], {
+        attributes: { symbol: token_names[i] + "/USD" },
+      });
+    }
   });
 
   let frt_bsk_group = null;
   let fruitbasket_authority: web3.PublicKey;
   let fb_auth_bump: number;
   let quote_token_transaction_pool: web3.PublicKey;
+  let fee_vault: web3.PublicKey;
 
   /// Create group
   it("Group initialized", async () => {
@@ -150,6 +158,119 @@ describe("fruitbasket", () => {
       },
       signers: [owner],
     });
+
+    const [tmp_fee_vault, bump_fee_vault] = await web3.PublicKey.findProgramAddress(
+      [Buffer.from("fruitbasket_fee_vault"), frt_bsk_group.toBuffer(), quote_token.publicKey.toBuffer()],
+      program.programId
+    );
+    fee_vault = tmp_fee_vault;
+    await program.rpc.createFeeVault(bump_fee_vault, {
+      accounts: {
+        owner: owner.publicKey,
+        group: frt_bsk_group,
+        quoteTokenMint: quote_token.publicKey,
+        feeVault: fee_vault,
+        fruitBasketAuthority: fruitbasket_authority,
+        systemProgram: web3.SystemProgram.programId,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        rent: web3.SYSVAR_RENT_PUBKEY,
+      },
+      signers: [owner],
+    });
+
+    const group_info: FruitBasketGroup = await program.account.fruitBasketGroup.fetch(frt_bsk_group);
+    assert.ok(group_info.maxPriceAgeSlots.toNumber() == 50);
+    // test oracles are published once, keep their prices usable for the whole test
+    await program.rpc.setMaxPriceAge(new anchor.BN(1_000_000), {
+      accounts: {
+        owner: owner.publicKey,
+        group: frt_bsk_group,
+      },
+      signers: [owner],
+    });
+    // bound trade contexts by the moving average of the basket price
+    await program.rpc.setEmaConfig(new anchor.BN(150), true, {
+      accounts: {
+        owner: owner.publicKey,
+        group: frt_bsk_group,
+      },
+      signers: [owner],
+    });
+    const group_info_ema: FruitBasketGroup = await program.account.fruitBasketGroup.fetch(frt_bsk_group);
+    assert.ok(group_info_ema.emaWindowSlots.toNumber() == 150);
+    assert.equal(group_info_ema.emaBound, 1);
+  });
+
+  it("group ownership transferred and roles delegated", async () => {
+    const new_owner = web3.Keypair.generate();
+    const fee_manager = web3.Keypair.generate();
+    const accept = async (signer: web3.Keypair) => {
+      await program.rpc.acceptOwnership({
+        accounts: {
+          newOwner: signer.publicKey,
+          group: frt_bsk_group,
+        },
+        signers: [signer],
+      });
+    };
+    const propose = async (current: web3.Keypair, proposed: web3.PublicKey) => {
+      await program.rpc.proposeOwner(proposed, {
+        accounts: {
+          owner: current.publicKey,
+          group: frt_bsk_group,
+        },
+        signers: [current],
+      });
+    };
+    await propose(owner, new_owner.publicKey);
+    // only the proposed owner can accept
+    let failed = false;
+    try {
+      await accept(fee_manager);
+    } catch (e) {
+      failed = true;
+    }
+    assert.ok(failed);
+    await accept(new_owner);
+    let group_info: FruitBasketGroup = await program.account.fruitBasketGroup.fetch(frt_bsk_group);
+    assert.ok(group_info.owner.equals(new_owner.publicKey));
+    assert.ok(group_info.pendingOwner.equals(web3.PublicKey.default));
+
+    // delegated fee manager can change fees but not other parameters
+    await program.rpc.setRole({ feeManager: {} }, fee_manager.publicKey, {
+      accounts: {
+        owner: new_owner.publicKey,
+        group: frt_bsk_group,
+      },
+      signers: [new_owner],
+    });
+    await program.rpc.setFees(new anchor.BN(0), new anchor.BN(0), {
+      accounts: {
+        owner: fee_manager.publicKey,
+        group: frt_bsk_group,
+      },
+      signers: [fee_manager],
+    });
+    failed = false;
+    try {
+      await program.rpc.setLegSlippage(new anchor.BN(100), {
+        accounts: {
+          owner: fee_manager.publicKey,
+          group: frt_bsk_group,
+        },
+        signers: [fee_manager],
+      });
+    } catch (e) {
+      failed = true;
+    }
+    assert.ok(failed);
+
+    // give the group back to the original owner for the rest of the tests
+    await propose(new_owner, owner.publicKey);
+    await accept(owner);
+    group_info = await program.account.fruitBasketGroup.fetch(frt_bsk_group);
+    assert.ok(group_info.owner.equals(owner.publicKey));
+    assert.ok(group_info.feeManager.equals(fee_manager.publicKey));
   });
 
   let open_orders_by_token: web3.Keypair[];
@@ -208,6 +329,7 @@ describe("fruitbasket", () => {
   let basket_2: web3.PublicKey;
   let basket_3: web3.PublicKey;
   let basket_1_mint: web3.PublicKey;
+  let basket_1_creator_fee_vault: web3.PublicKey;
   let basket_2_mint: web3.PublicKey;
   let basket_3_mint: web3.PublicKey;
 
@@ -249,6 +371,15 @@ describe("fruitbasket", () => {
     comp_sh2.amount = new anchor.BN(exp * 100000); // 100000 SHIT1
     comp_sh2.decimal = 6;
 
+    // the program checks each component against its token description
+    const component_token_descs = async (components) => Promise.all(components.map(async (x) => {
+      const [token_desc, _bump] = await web3.PublicKey.findProgramAddress(
+        [Buffer.from("fruitbasket_token"), x.tokenMint.toBuffer()],
+        program.programId
+      );
+      return { pubkey: token_desc, isWritable: false, isSigner: false };
+    }));
+
     // first basket
     let basket_nb = new anchor.BN(0);
     const [_basket_1, bump_b1] = await web3.PublicKey.findProgramAddress(
@@ -269,7 +400,9 @@ describe("fruitbasket", () => {
       bump_b1m,
       "First tier coins",
       "Basket for first teer coins",
+      components_1.length,
       components_1,
+      new anchor.BN(20),
       {
         accounts: {
           client: owner.publicKey,
@@ -280,6 +413,7 @@ describe("fruitbasket", () => {
           tokenProgram: TOKEN_PROGRAM_ID,
           rent: web3.SYSVAR_RENT_PUBKEY,
         },
+        remainingAccounts: await component_token_descs(components_1),
         signers: [owner],
       }
     );
@@ -303,7 +437,9 @@ describe("fruitbasket", () => {
       bump_b2m,
       "Solana coins",
       "Basket for coins base on solana",
+      components_2.length,
       components_2,
+      new anchor.BN(0),
       {
         accounts: {
           client: owner.publicKey,
@@ -314,6 +450,7 @@ describe("fruitbasket", () => {
           tokenProgram: TOKEN_PROGRAM_ID,
           rent: web3.SYSVAR_RENT_PUBKEY,
         },
+        remainingAccounts: await component_token_descs(components_2),
         signers: [owner],
       }
     );
@@ -337,7 +474,9 @@ describe("fruitbasket", () => {
       bump_b3m,
       "Shit coins",
       "Basket for shit coins that have potential in future",
+      components_3.length,
       components_3,
+      new anchor.BN(0),
       {
         accounts: {
           client: owner.publicKey,
@@ -348,9 +487,93 @@ describe("fruitbasket", () => {
           tokenProgram: TOKEN_PROGRAM_ID,
           rent: web3.SYSVAR_RENT_PUBKEY,
         },
+        remainingAccounts: await component_token_descs(components_3),
         signers: [owner],
       }
     );
+    // creator fees of first basket are accrued in its creator fee vault
+    const [_creator_fee_vault_1, bump_cfv1] = await web3.PublicKey.findProgramAddress(
+      [Buffer.from("fruitbasket_creator_fee_vault"), basket_1.toBuffer(), quote_token.publicKey.toBuffer()],
+      program.programId
+    );
+    basket_1_creator_fee_vault = _creator_fee_vault_1;
+    await program.rpc.createCreatorFeeVault(bump_cfv1, {
+      accounts: {
+        payer: owner.publicKey,
+        basket: basket_1,
+        quoteTokenMint: quote_token.publicKey,
+        creatorFeeVault: basket_1_creator_fee_vault,
+        fruitBasketAuthority: fruitbasket_authority,
+        systemProgram: web3.SystemProgram.programId,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        rent: web3.SYSVAR_RENT_PUBKEY,
+      },
+      signers: [owner],
+    });
+  });
+
+  // vault where the tokens backing a basket are kept
+  async function basket_vault(basket: web3.PublicKey, x: number): Promise<web3.PublicKey> {
+    const [vault, _bump] = await web3.PublicKey.findProgramAddress(
+      [Buffer.from("fruitbasket_vault"), basket.toBuffer(), (await tokens[x]).publicKey.toBuffer()],
+      program.programId
+    );
+    return vault;
+  }
+
+  // serum open orders with which a basket trades on the market of a token
+  async function basket_open_orders(basket: web3.PublicKey, x: number): Promise<web3.PublicKey> {
+    const [open_orders, _bump] = await web3.PublicKey.findProgramAddress(
+      [Buffer.from("fruitbasket_open_orders"), basket.toBuffer(), markets_by_tokens[x].publicKey.toBuffer()],
+      program.programId
+    );
+    return open_orders;
+  }
+
+  it("Basket vaults created", async () => {
+    const token_mints = (await Promise.all(tokens)).map((x) => x.publicKey);
+    for (const basket of [basket_1, basket_2, basket_3]) {
+      const basket_info: Basket = await program.account.basket.fetch(basket);
+      for (const component of basket_info.components) {
+        const x = token_mints.findIndex((mint) => mint.equals(component.tokenMint));
+        const [vault, bump] = await web3.PublicKey.findProgramAddress(
+          [Buffer.from("fruitbasket_vault"), basket.toBuffer(), component.tokenMint.toBuffer()],
+          program.programId
+        );
+        assert.ok(vault.equals(await basket_vault(basket, x)));
+        await program.rpc.createBasketVault(bump, {
+          accounts: {
+            payer: owner.publicKey,
+            basket,
+            tokenMint: component.tokenMint,
+            basketVault: vault,
+            fruitBasketAuthority: fruitbasket_authority,
+            systemProgram: web3.SystemProgram.programId,
+            tokenProgram: TOKEN_PROGRAM_ID,
+            rent: web3.SYSVAR_RENT_PUBKEY,
+          },
+          signers: [owner],
+        });
+        const [open_orders, open_orders_bump] = await web3.PublicKey.findProgramAddress(
+          [Buffer.from("fruitbasket_open_orders"), basket.toBuffer(), markets_by_tokens[x].publicKey.toBuffer()],
+          program.programId
+        );
+        assert.ok(open_orders.equals(await basket_open_orders(basket, x)));
+        await program.rpc.createBasketOpenOrders(open_orders_bump, {
+          accounts: {
+            payer: owner.publicKey,
+            basket,
+            market: markets_by_tokens[x].publicKey,
+            openOrders: open_orders,
+            fruitbasketAuthority: fruitbasket_authority,
+            dexProgram: serum.DEX_ID,
+            systemProgram: web3.SystemProgram.programId,
+            rent: web3.SYSVAR_RENT_PUBKEY,
+          },
+          signers: [owner],
+        });
+      }
+    }
   });
 
   it("oracle group tests", async () => {
@@ -361,10 +584,56 @@ describe("fruitbasket", () => {
     assert.ok(group_info.nbUsers == 0);
     assert.ok(group_info.numberOfBaskets.toNumber() == 3);
     assert.ok(group_info.tokenCount.toNumber() == nb_tokens);
+    assert.ok(group_info.legSlippageBps.toNumber() == 100);
+    assert.ok(group_info.maxCreatorFeeBps.toNumber() == 100);
+    const basket_1_info: Basket = await program.account.basket.fetch(basket_1);
+    assert.ok(basket_1_info.creator.equals(owner.publicKey));
+    assert.ok(basket_1_info.creatorFeeBps.toNumber() == 20);
+  });
+
+  it("Quote currency added", async () => {
+    const usdt = await test_utils.createToken(6, wallet.publicKey);
+    const usdt_transaction_pool = await usdt.createAccount(owner.publicKey);
+    const usdt_oracle = await oracle.createPriceAccount();
+    await oracle.updatePriceAccount(usdt_oracle, {
+      exponent: -6,
+      aggregatePriceInfo: {
+        price: 1000000n,
+        conf: 1000n,
+        pubSlot: BigInt(await connection.getSlot()),
+      },
+    });
+    await program.rpc.addQuoteCurrency({
+      accounts: {
+        owner: owner.publicKey,
+        group: frt_bsk_group,
+        quoteTokenMint: usdt.publicKey,
+        quoteTokenTransactionPool: usdt_transaction_pool,
+        priceOracle: usdt_oracle.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      },
+      signers: [owner],
+    });
+    await program.rpc.updateQuotePrice({
+      accounts: {
+        group: frt_bsk_group,
+        oracleAi: usdt_oracle.publicKey,
+      },
+    });
+    const group_info: FruitBasketGroup = await program.account.fruitBasketGroup.fetch(frt_bsk_group);
+    assert.equal(group_info.nbQuoteCurrencies, 2);
+    assert.ok(group_info.quoteCurrencies[0].mint.equals(quote_token.publicKey));
+    assert.ok(group_info.quoteCurrencies[0].transactionPool.equals(quote_token_transaction_pool));
+    assert.ok(group_info.quoteCurrencies[1].mint.equals(usdt.publicKey));
+    assert.equal(group_info.quoteCurrencies[1].price.toNumber(), 1000000);
   });
 
   /// update token cache
   it("cache updated", async () => {
+    const updated_mints = new Set<string>();
+    const listener = program.addEventListener("PriceUpdated", (event) => {
+      updated_mints.add(event.tokenMint.toString());
+    });
     await Promise.all(
       Array.from(Array(tokens.length).keys()).map(async (x) => {
         await program.rpc.updatePrice({
@@ -376,19 +645,121 @@ describe("fruitbasket", () => {
         });
       })
     );
+    // give the websocket a moment to deliver the logs
+    await new Promise((resolve) => setTimeout(resolve, 1000));
+    await program.removeEventListener(listener);
+    for (const token of tokens) {
+      assert.ok(updated_mints.has((await token).publicKey.toString()));
+    }
+  });
+
+  it("token oracle guards configured", async () => {
+    const configure_btc = async (max_confidence_bps: number, max_deviation_bps: number) => {
+      await program.rpc.configureToken(new anchor.BN(max_confidence_bps), new anchor.BN(max_deviation_bps), {
+        accounts: {
+          owner: owner.publicKey,
+          group: frt_bsk_group,
+          tokenDesc: fruitbasket_token_descs[0],
+        },
+        signers: [owner],
+      });
+    };
+    const update_btc = async () => {
+      await program.rpc.updatePrice({
+        accounts: {
+          group: frt_bsk_group,
+          tokenDesc: fruitbasket_token_descs[0],
+          oracleAi: (await price_oracles[0]).publicKey,
+        },
+      });
+    };
+    // btc oracle confidence is 1% of the price
+    await configure_btc(50, 0);
+    let failed = false;
+    try {
+      await update_btc();
+    } catch (e) {
+      failed = true;
+    }
+    assert.ok(failed);
+    // price did not move so any deviation is accepted
+    await configure_btc(200, 10);
+    await update_btc();
+    const token_desc = await program.account.tokenDescription.fetch(fruitbasket_token_descs[0]);
+    assert.equal(token_desc.maxConfidenceBps.toNumber(), 200);
+    assert.equal(token_desc.maxDeviationBps.toNumber(), 10);
+  });
+
+  it("halted and uncertain oracles rejected", async () => {
+    const btc_oracle = await price_oracles[0];
+    const publish_btc = async (conf: bigint, status: pyth.PriceStatus) => {
+      await oracle.updatePriceAccount(btc_oracle, {
+        exponent: token_exp[0],
+        aggregatePriceInfo: { price: token_prices[0], conf, status },
+      });
+    };
+    const update_btc = async () => {
+      await program.rpc.updatePrice({
+        accounts: {
+          group: frt_bsk_group,
+          tokenDesc: fruitbasket_token_descs[0],
+          oracleAi: btc_oracle.publicKey,
+        },
+      });
+    };
+    const rejected = async () => {
+      try {
+        await update_btc();
+      } catch (e) {
+        return true;
+      }
+      return false;
+    };
+    await publish_btc(token_prices[0] / 100n, "halted");
+    assert.ok(await rejected());
+    // wider than the 200 bps configured for btc
+    await publish_btc(token_prices[0] / 20n, "trading");
+    assert.ok(await rejected());
+    await publish_btc(token_prices[0] / 100n, "trading");
+    await update_btc();
   });
   let basket_1_price : anchor.BN;
   let basket_1_confidence : anchor.BN;
 
   /// price basket
+  let basket_navs: web3.PublicKey[] = [];
+
   it("basket priced", async () => {
     let token_desc_metas = [];
     fruitbasket_token_descs.forEach( x => { token_desc_metas.push( {isSigner : false, isWritable : false, pubkey : x} ); })
+    for (const basket of [basket_1, basket_2, basket_3]) {
+      const [basket_nav, _bump] = await web3.PublicKey.findProgramAddress(
+        [Buffer.from("fruitbasket_nav"), basket.toBuffer()],
+        program.programId
+      );
+      basket_navs.push(basket_nav);
+    }
+    // only first basket publishes its price
+    const [_nav, nav_bump] = await web3.PublicKey.findProgramAddress(
+      [Buffer.from("fruitbasket_nav"), basket_1.toBuffer()],
+      program.programId
+    );
+    await program.rpc.createBasketNav(nav_bump, {
+      accounts: {
+        payer: owner.publicKey,
+        basket: basket_1,
+        basketNav: basket_navs[0],
+        systemProgram: web3.SystemProgram.programId,
+      },
+      signers: [owner],
+    });
     
     // price basket 1
     await program.rpc.updateBasketPrice({
       accounts: {
+        group: frt_bsk_group,
         basket: basket_1,
+        basketNav: basket_navs[0],
       },
       remainingAccounts : token_desc_metas,
     });
@@ -398,11 +769,15 @@ describe("fruitbasket", () => {
     assert.equal(basket_1_info.lastPrice.toNumber(), 1200000000);
     assert.equal(basket_1_info.decimal, 6);
     assert.equal(basket_1_info.confidence.toNumber(), 12000000);
+    // first priced update seeds the moving average
+    assert.equal(basket_1_info.emaPrice.toNumber(), 1200000000);
 
     // price basket 2
     await program.rpc.updateBasketPrice({
       accounts: {
+        group: frt_bsk_group,
         basket: basket_2,
+        basketNav: basket_navs[1],
       },
       remainingAccounts : token_desc_metas,
     });
@@ -415,7 +790,9 @@ describe("fruitbasket", () => {
     // price basket 3
     await program.rpc.updateBasketPrice({
       accounts: {
+        group: frt_bsk_group,
         basket: basket_3,
+        basketNav: basket_navs[2],
       },
       remainingAccounts : token_desc_metas,
     });
@@ -423,6 +800,54 @@ describe("fruitbasket", () => {
     assert.ok(basket_3_info.lastPrice.toNumber() > 0);
     assert.ok(basket_3_info.decimal == 6);
     assert.ok(basket_3_info.confidence.toNumber() > 0);
+
+    // nav account of first basket is laid out like a pyth price account
+    const nav_data = (await connection.getAccountInfo(basket_navs[0])).data;
+    assert.equal(nav_data.length, 3312);
+    assert.equal(nav_data.readUInt32LE(0), 0xa1b2c3d4);
+    assert.equal(nav_data.readInt32LE(20), -6);
+    assert.equal(nav_data.readBigInt64LE(208), BigInt(basket_1_info.lastPrice.toString()));
+    assert.equal(nav_data.readBigUInt64LE(216), BigInt(basket_1_info.confidence.toString()));
+    assert.equal(nav_data.readUInt32LE(224), 1); // trading
+  });
+
+  it("basket priced from orderbooks", async () => {
+    // components of first basket are btc, eth and sol
+    const remaining_accounts = [];
+    for (let i = 0; i < 3; ++i) {
+      const market = markets_by_tokens[i];
+      remaining_accounts.push({ pubkey: fruitbasket_token_descs[i], isWritable: false, isSigner: false });
+      remaining_accounts.push({ pubkey: market.publicKey, isWritable: false, isSigner: false });
+      remaining_accounts.push({ pubkey: market._decoded.bids, isWritable: false, isSigner: false });
+      remaining_accounts.push({ pubkey: market._decoded.asks, isWritable: false, isSigner: false });
+    }
+    // a small size so that larger trades are still priced from the oracles
+    // the first component is walked alone, prices are updated once the others are walked
+    await program.rpc.updateBasketBookPrice(new anchor.BN(100000), 0, {
+      accounts: {
+        group: frt_bsk_group,
+        basket: basket_1,
+        dexProgram: serum.DEX_ID,
+      },
+      remainingAccounts: remaining_accounts.slice(0, 4),
+    });
+    const pending_info: Basket = await program.account.basket.fetch(basket_1);
+    assert.equal(pending_info.bookCursor, 1);
+    assert.equal(pending_info.bookPriceAmount.toNumber(), 0);
+    await program.rpc.updateBasketBookPrice(new anchor.BN(100000), 1, {
+      accounts: {
+        group: frt_bsk_group,
+        basket: basket_1,
+        dexProgram: serum.DEX_ID,
+      },
+      remainingAccounts: remaining_accounts.slice(4),
+    });
+    const basket_1_info: Basket = await program.account.basket.fetch(basket_1);
+    assert.equal(basket_1_info.bookCursor, 0);
+    assert.equal(basket_1_info.bookPriceAmount.toNumber(), 100000);
+    assert.ok(basket_1_info.bookSellPrice.toNumber() > 0);
+    assert.ok(basket_1_info.bookBuyPrice.toNumber() >= basket_1_info.bookSellPrice.toNumber());
+    assert.ok(basket_1_info.bookPriceSlot.toNumber() > 0);
   });
   let market_data;
   let client_1 = web3.Keypair.generate();
@@ -439,6 +864,7 @@ describe("fruitbasket", () => {
   type ContextSide = anchor.IdlTypes<Fruitbasket>["ContextSide"];
   const buy_side = ContextSide.Buy;
   const sell_side = ContextSide.Sell;
+  const market_order = { market: {} };
 
   /// Buy basket
   it("Buy Basket", async () => {
@@ -490,6 +916,7 @@ describe("fruitbasket", () => {
       buy_side,
       new anchor.BN(1000000), // buy 1 basket
       new anchor.BN(1224120000),
+      market_order,
       {
         accounts: {
           group: frt_bsk_group,
@@ -516,13 +943,16 @@ describe("fruitbasket", () => {
       const basket_components : [BasketComponent] = basket_1_info.components;
       const buy_context_info: BasketTradeContext = await program.account.basketTradeContext.fetch(buy_context);
       const worst_basket_price = 1224120000;
+      // creator fee of 20 bps is deposited on top of the worst price
+      const fee_reserve = Math.floor(worst_basket_price * 20 / 10000);
       
       assert.equal(buy_context_info.basket.toString(), basket_1.toString());
       assert.equal(buy_context_info.reverting, 0);
-      assert.equal(buy_context_info.usdcAmountLeft.toNumber(), worst_basket_price);
+      assert.equal(buy_context_info.usdcAmountLeft.toNumber(), worst_basket_price + fee_reserve);
       assert.equal(buy_context_info.quoteTokenAccount.toString(), client_usdc_acc.toString());
       assert.equal(buy_context_info.basketTokenAccount.toString(), client_basket_token_acc.toString());
-      assert.equal(buy_context_info.initialUsdcTransferAmount.toNumber(), worst_basket_price);
+      assert.equal(buy_context_info.initialUsdcTransferAmount.toNumber(), worst_basket_price + fee_reserve);
+      assert.equal(buy_context_info.feeReserve.toNumber(), fee_reserve);
       for( let i = 0; i < basket_1_info.numberOfComponents; ++i)
       {
         const component = basket_1_info.components[i];
@@ -545,13 +975,14 @@ describe("fruitbasket", () => {
       await program.rpc.processTokenForContext(
         {
           accounts : {
+            group : frt_bsk_group,
             tokenDesc : fruitbasket_token_descs[x],
             tradeContext : buy_context,
             tokenMint : token.publicKey,
             quoteTokenMint : quote_token.publicKey,
             fruitbasket : basket_1,
             market : market.publicKey,
-            openOrders : open_orders_by_token[x].publicKey,
+            openOrders : await basket_open_orders(basket_1, x),
             requestQueue : market._decoded.requestQueue,
             eventQueue : market._decoded.eventQueue,
             bids : market._decoded.bids,
@@ -559,7 +990,7 @@ describe("fruitbasket", () => {
             tokenVault: market._decoded.baseVault,
             quoteTokenVault : market._decoded.quoteVault,
             vaultSigner : vault_signer,
-            tokenPool : token_pools[x],
+            tokenPool : await basket_vault(basket_1, x),
             quoteTokenTransactionPool : quote_token_transaction_pool,
             fruitBasketAuthority : fruitbasket_authority,
             dexProgram : serum.DEX_ID,
@@ -568,7 +999,7 @@ describe("fruitbasket", () => {
           }
         }
       );
-      const amount_of_token_in_pool = (await token.getAccountInfo(token_pools[x])).amount;
+      const amount_of_token_in_pool = (await token.getAccountInfo(await basket_vault(basket_1, x))).amount;
       assert.equal(amount_of_token_in_pool.toNumber(), token_amounts_in_basket[x]);
     }
     const amount_of_usdc_in_pool = (await quote_token.getAccountInfo(quote_token_transaction_pool)).amount;
@@ -599,6 +1030,8 @@ describe("fruitbasket", () => {
           quoteTokenAccount : client_usdc_acc,
           basketTokenAccount : client_basket_token_acc,
           quoteTokenTransactionPool : quote_token_transaction_pool,
+          feeVault : fee_vault,
+          creatorFeeVault : basket_1_creator_fee_vault,
           fruitBasketAuthority : fruitbasket_authority,
           quoteTokenMint : quote_token.publicKey,
           basketTokenMint : basket_1_mint,
@@ -611,15 +1044,33 @@ describe("fruitbasket", () => {
     // remaining usdc returned back to client account
     const amount_of_usdc_with_client_after_finalize = (await quote_token.getAccountInfo(client_usdc_acc)).amount;
     const diff = amount_of_usdc_with_client_after_finalize.toNumber() - amount_of_usdc_with_client.toNumber();
-    assert.equal(diff, buy_context_info.usdcAmountLeft.toNumber());
+    // creator fee of the basket is accrued in the creator fee vault
+    const creator_fees = (await quote_token.getAccountInfo(basket_1_creator_fee_vault)).amount;
+    assert.ok(creator_fees.toNumber() > 0);
+    assert.equal(diff, buy_context_info.usdcAmountLeft.toNumber() - creator_fees.toNumber());
+    // creator claims the accrued fees
+    const creator_usdc_acc = await quote_token.createAccount(owner.publicKey);
+    await program.rpc.claimCreatorFees({
+      accounts: {
+        creator: owner.publicKey,
+        basket: basket_1,
+        creatorFeeVault: basket_1_creator_fee_vault,
+        destination: creator_usdc_acc,
+        fruitBasketAuthority: fruitbasket_authority,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      },
+      signers: [owner],
+    });
+    assert.equal((await quote_token.getAccountInfo(creator_usdc_acc)).amount.toNumber(), creator_fees.toNumber());
+    assert.equal((await quote_token.getAccountInfo(basket_1_creator_fee_vault)).amount.toNumber(), 0);
     // congratulations you are onwer of a basket
     const amount_of_basket_tokens_with_client = (await basket_1_token.getAccountInfo(client_basket_token_acc)).amount;
     assert.equal(amount_of_basket_tokens_with_client.toNumber(), 1000000);
 
     
-    const amount_of_btc_in_pool = (await (await btc).getAccountInfo(token_pools[0])).amount;
-    const amount_of_eth_in_pool = (await (await eth).getAccountInfo(token_pools[1])).amount;
-    const amount_of_sol_in_pool = (await (await sol).getAccountInfo(token_pools[2])).amount;
+    const amount_of_btc_in_pool = (await (await btc).getAccountInfo(await basket_vault(basket_1, 0))).amount;
+    const amount_of_eth_in_pool = (await (await eth).getAccountInfo(await basket_vault(basket_1, 1))).amount;
+    const amount_of_sol_in_pool = (await (await sol).getAccountInfo(await basket_vault(basket_1, 2))).amount;
     assert.equal(10000, amount_of_btc_in_pool.toNumber());
     assert.equal(100000, amount_of_eth_in_pool.toNumber());
     assert.equal(2000000, amount_of_sol_in_pool.toNumber());
@@ -642,6 +1093,7 @@ describe("fruitbasket", () => {
       buy_side,
       new anchor.BN(600000), // buy 0.6 basket
       new anchor.BN(2024120000),
+      market_order,
       {
         accounts: {
           group: frt_bsk_group,
@@ -667,13 +1119,14 @@ describe("fruitbasket", () => {
         await program.rpc.processTokenForContext(
           {
             accounts : {
+              group : frt_bsk_group,
               tokenDesc : fruitbasket_token_descs[x],
               tradeContext : buy_context,
               tokenMint : token.publicKey,
               quoteTokenMint : quote_token.publicKey,
               fruitbasket : basket_1,
               market : market.publicKey,
-              openOrders : open_orders_by_token[x].publicKey,
+              openOrders : await basket_open_orders(basket_1, x),
               requestQueue : market._decoded.requestQueue,
               eventQueue : market._decoded.eventQueue,
               bids : market._decoded.bids,
@@ -681,7 +1134,7 @@ describe("fruitbasket", () => {
               tokenVault: market._decoded.baseVault,
               quoteTokenVault : market._decoded.quoteVault,
               vaultSigner : vault_signer,
-              tokenPool : token_pools[x],
+              tokenPool : await basket_vault(basket_1, x),
               quoteTokenTransactionPool : quote_token_transaction_pool,
               fruitBasketAuthority : fruitbasket_authority,
               dexProgram : serum.DEX_ID,
@@ -700,6 +1153,8 @@ describe("fruitbasket", () => {
             quoteTokenAccount : client_usdc_acc,
             basketTokenAccount : client_basket_token_acc,
             quoteTokenTransactionPool : quote_token_transaction_pool,
+            feeVault : fee_vault,
+            creatorFeeVault : basket_1_creator_fee_vault,
             fruitBasketAuthority : fruitbasket_authority,
             quoteTokenMint : quote_token.publicKey,
             basketTokenMint : basket_1_mint,
@@ -711,9 +1166,9 @@ describe("fruitbasket", () => {
       );
       const amount_of_basket_tokens_with_client = (await basket_1_token.getAccountInfo(client_basket_token_acc)).amount;
       assert.equal(amount_of_basket_tokens_with_client.toNumber(), 1600000);
-      const amount_of_btc_in_pool = (await (await btc).getAccountInfo(token_pools[0])).amount;
-      const amount_of_eth_in_pool = (await (await eth).getAccountInfo(token_pools[1])).amount;
-      const amount_of_sol_in_pool = (await (await sol).getAccountInfo(token_pools[2])).amount;
+      const amount_of_btc_in_pool = (await (await btc).getAccountInfo(await basket_vault(basket_1, 0))).amount;
+      const amount_of_eth_in_pool = (await (await eth).getAccountInfo(await basket_vault(basket_1, 1))).amount;
+      const amount_of_sol_in_pool = (await (await sol).getAccountInfo(await basket_vault(basket_1, 2))).amount;
       assert.equal(16000, amount_of_btc_in_pool.toNumber());
       assert.equal(160000, amount_of_eth_in_pool.toNumber());
       assert.equal(3200000, amount_of_sol_in_pool.toNumber());
@@ -736,6 +1191,7 @@ describe("fruitbasket", () => {
       sell_side,
       new anchor.BN(900000), // buy 1 basket
       new anchor.BN(1024120000),
+      market_order,
       {
         accounts: {
           group: frt_bsk_group,
@@ -761,13 +1217,14 @@ describe("fruitbasket", () => {
         await program.rpc.processTokenForContext(
           {
             accounts : {
+              group : frt_bsk_group,
               tokenDesc : fruitbasket_token_descs[x],
               tradeContext : sell_context,
               tokenMint : token.publicKey,
               quoteTokenMint : quote_token.publicKey,
               fruitbasket : basket_1,
               market : market.publicKey,
-              openOrders : open_orders_by_token[x].publicKey,
+              openOrders : await basket_open_orders(basket_1, x),
               requestQueue : market._decoded.requestQueue,
               eventQueue : market._decoded.eventQueue,
               bids : market._decoded.bids,
@@ -775,7 +1232,7 @@ describe("fruitbasket", () => {
               tokenVault: market._decoded.baseVault,
               quoteTokenVault : market._decoded.quoteVault,
               vaultSigner : vault_signer,
-              tokenPool : token_pools[x],
+              tokenPool : await basket_vault(basket_1, x),
               quoteTokenTransactionPool : quote_token_transaction_pool,
               fruitBasketAuthority : fruitbasket_authority,
               dexProgram : serum.DEX_ID,
@@ -794,6 +1251,8 @@ describe("fruitbasket", () => {
             quoteTokenAccount : client_usdc_acc,
             basketTokenAccount : client_basket_token_acc,
             quoteTokenTransactionPool : quote_token_transaction_pool,
+            feeVault : fee_vault,
+            creatorFeeVault : basket_1_creator_fee_vault,
             fruitBasketAuthority : fruitbasket_authority,
             quoteTokenMint : quote_token.publicKey,
             basketTokenMint : basket_1_mint,
@@ -805,9 +1264,9 @@ describe("fruitbasket", () => {
       );
       const amount_of_basket_tokens_with_client = (await basket_1_token.getAccountInfo(client_basket_token_acc)).amount;
       assert.equal(amount_of_basket_tokens_with_client.toNumber(), 700000);
-      const amount_of_btc_in_pool = (await (await btc).getAccountInfo(token_pools[0])).amount;
-      const amount_of_eth_in_pool = (await (await eth).getAccountInfo(token_pools[1])).amount;
-      const amount_of_sol_in_pool = (await (await sol).getAccountInfo(token_pools[2])).amount;
+      const amount_of_btc_in_pool = (await (await btc).getAccountInfo(await basket_vault(basket_1, 0))).amount;
+      const amount_of_eth_in_pool = (await (await eth).getAccountInfo(await basket_vault(basket_1, 1))).amount;
+      const amount_of_sol_in_pool = (await (await sol).getAccountInfo(await basket_vault(basket_1, 2))).amount;
       assert.equal(7000, amount_of_btc_in_pool.toNumber());
       assert.equal(70000, amount_of_eth_in_pool.toNumber());
       assert.equal(1400000, amount_of_sol_in_pool.toNumber());
@@ -824,9 +1283,9 @@ describe("fruitbasket", () => {
     );
     const amount_of_basket_tokens_with_client_before = (await basket_1_token.getAccountInfo(client_basket_token_acc)).amount;
     const amount_of_usdc_with_client_before = (await quote_token.getAccountInfo(client_usdc_acc)).amount;
-    const amount_of_btc_in_pool_before = (await (await btc).getAccountInfo(token_pools[0])).amount;
-    const amount_of_eth_in_pool_before = (await (await eth).getAccountInfo(token_pools[1])).amount;
-    const amount_of_sol_in_pool_before = (await (await sol).getAccountInfo(token_pools[2])).amount;
+    const amount_of_btc_in_pool_before = (await (await btc).getAccountInfo(await basket_vault(basket_1, 0))).amount;
+    const amount_of_eth_in_pool_before = (await (await eth).getAccountInfo(await basket_vault(basket_1, 1))).amount;
+    const amount_of_sol_in_pool_before = (await (await sol).getAccountInfo(await basket_vault(basket_1, 2))).amount;
 
     // Initiate a buy context that is to be reverted
     const [buy_context, buy_context_bump] =
@@ -844,6 +1303,7 @@ describe("fruitbasket", () => {
       buy_side,
       new anchor.BN(1000000), // buy 1 basket
       new anchor.BN(2024120000),
+      market_order,
       {
         accounts: {
           group: frt_bsk_group,
@@ -869,13 +1329,14 @@ describe("fruitbasket", () => {
         await program.rpc.processTokenForContext(
           {
             accounts : {
+              group : frt_bsk_group,
               tokenDesc: fruitbasket_token_descs[x],
               tradeContext : buy_context,
               tokenMint : token.publicKey,
               quoteTokenMint : quote_token.publicKey,
               fruitbasket : basket_1,
               market : market.publicKey,
-              openOrders : open_orders_by_token[x].publicKey,
+              openOrders : await basket_open_orders(basket_1, x),
               requestQueue : market._decoded.requestQueue,
               eventQueue : market._decoded.eventQueue,
               bids : market._decoded.bids,
@@ -883,7 +1344,7 @@ describe("fruitbasket", () => {
               tokenVault: market._decoded.baseVault,
               quoteTokenVault : market._decoded.quoteVault,
               vaultSigner : vault_signer,
-              tokenPool : token_pools[x],
+              tokenPool : await basket_vault(basket_1, x),
               quoteTokenTransactionPool : quote_token_transaction_pool,
               fruitBasketAuthority : fruitbasket_authority,
               dexProgram : serum.DEX_ID,
@@ -912,13 +1373,14 @@ describe("fruitbasket", () => {
         await program.rpc.processTokenForContext(
           {
             accounts : {
+              group : frt_bsk_group,
               tokenDesc : fruitbasket_token_descs[x],
               tradeContext : buy_context,
               tokenMint : token.publicKey,
               quoteTokenMint : quote_token.publicKey,
               fruitbasket : basket_1,
               market : market.publicKey,
-              openOrders : open_orders_by_token[x].publicKey,
+              openOrders : await basket_open_orders(basket_1, x),
               requestQueue : market._decoded.requestQueue,
               eventQueue : market._decoded.eventQueue,
               bids : market._decoded.bids,
@@ -926,7 +1388,7 @@ describe("fruitbasket", () => {
               tokenVault: market._decoded.baseVault,
               quoteTokenVault : market._decoded.quoteVault,
               vaultSigner : vault_signer,
-              tokenPool : token_pools[x],
+              tokenPool : await basket_vault(basket_1, x),
               quoteTokenTransactionPool : quote_token_transaction_pool,
               fruitBasketAuthority : fruitbasket_authority,
               dexProgram : serum.DEX_ID,
@@ -946,6 +1408,8 @@ describe("fruitbasket", () => {
             quoteTokenAccount : client_usdc_acc,
             basketTokenAccount : client_basket_token_acc,
             quoteTokenTransactionPool : quote_token_transaction_pool,
+            feeVault : fee_vault,
+            creatorFeeVault : basket_1_creator_fee_vault,
             fruitBasketAuthority : fruitbasket_authority,
             quoteTokenMint : quote_token.publicKey,
             basketTokenMint : basket_1_mint,
@@ -958,9 +1422,9 @@ describe("fruitbasket", () => {
     // check all accounts are untouched
     const amount_of_basket_tokens_with_client_after = (await basket_1_token.getAccountInfo(client_basket_token_acc)).amount;
     const amount_of_usdc_with_client_after = (await quote_token.getAccountInfo(client_usdc_acc)).amount;
-    const amount_of_btc_in_pool_after = (await (await btc).getAccountInfo(token_pools[0])).amount;
-    const amount_of_eth_in_pool_after = (await (await eth).getAccountInfo(token_pools[1])).amount;
-    const amount_of_sol_in_pool_after = (await (await sol).getAccountInfo(token_pools[2])).amount;
+    const amount_of_btc_in_pool_after = (await (await btc).getAccountInfo(await basket_vault(basket_1, 0))).amount;
+    const amount_of_eth_in_pool_after = (await (await eth).getAccountInfo(await basket_vault(basket_1, 1))).amount;
+    const amount_of_sol_in_pool_after = (await (await sol).getAccountInfo(await basket_vault(basket_1, 2))).amount;
     assert.equal(amount_of_basket_tokens_with_client_before.toNumber(), amount_of_basket_tokens_with_client_after.toNumber());
     assert.equal(amount_of_usdc_with_client_before.toNumber(), amount_of_usdc_with_client_after.toNumber());
     assert.equal(amount_of_btc_in_pool_before.toNumber(), amount_of_btc_in_pool_after.toNumber());
@@ -972,9 +1436,9 @@ describe("fruitbasket", () => {
   it("Revert context tested for sell context", async() => {
     const amount_of_basket_tokens_with_client_before = (await basket_1_token.getAccountInfo(client_basket_token_acc)).amount;
     const amount_of_usdc_with_client_before = (await quote_token.getAccountInfo(client_usdc_acc)).amount;
-    const amount_of_btc_in_pool_before = (await (await btc).getAccountInfo(token_pools[0])).amount;
-    const amount_of_eth_in_pool_before = (await (await eth).getAccountInfo(token_pools[1])).amount;
-    const amount_of_sol_in_pool_before = (await (await sol).getAccountInfo(token_pools[2])).amount;
+    const amount_of_btc_in_pool_before = (await (await btc).getAccountInfo(await basket_vault(basket_1, 0))).amount;
+    const amount_of_eth_in_pool_before = (await (await eth).getAccountInfo(await basket_vault(basket_1, 1))).amount;
+    const amount_of_sol_in_pool_before = (await (await sol).getAccountInfo(await basket_vault(basket_1, 2))).amount;
 
     // Initiate a buy context that is to be reverted
     const [sell_context, sell_context_bump] =
@@ -992,6 +1456,7 @@ describe("fruitbasket", () => {
       sell_side,
       new anchor.BN(200000), // sell 0.2 basket
       new anchor.BN(1024120000),
+      market_order,
       {
         accounts: {
           group: frt_bsk_group,
@@ -1017,13 +1482,14 @@ describe("fruitbasket", () => {
         await program.rpc.processTokenForContext(
           {
             accounts : {
+              group : frt_bsk_group,
               tokenDesc : fruitbasket_token_descs[x],
               tradeContext : sell_context,
               tokenMint : token.publicKey,
               quoteTokenMint : quote_token.publicKey,
               fruitbasket : basket_1,
               market : market.publicKey,
-              openOrders : open_orders_by_token[x].publicKey,
+              openOrders : await basket_open_orders(basket_1, x),
               requestQueue : market._decoded.requestQueue,
               eventQueue : market._decoded.eventQueue,
               bids : market._decoded.bids,
@@ -1031,7 +1497,7 @@ describe("fruitbasket", () => {
               tokenVault: market._decoded.baseVault,
               quoteTokenVault : market._decoded.quoteVault,
               vaultSigner : vault_signer,
-              tokenPool : token_pools[x],
+              tokenPool : await basket_vault(basket_1, x),
               quoteTokenTransactionPool : quote_token_transaction_pool,
               fruitBasketAuthority : fruitbasket_authority,
               dexProgram : serum.DEX_ID,
@@ -1060,13 +1526,14 @@ describe("fruitbasket", () => {
         await program.rpc.processTokenForContext(
           {
             accounts : {
+              group : frt_bsk_group,
               tokenDesc : fruitbasket_token_descs[x],
               tradeContext : sell_context,
               tokenMint : token.publicKey,
               quoteTokenMint : quote_token.publicKey,
               fruitbasket : basket_1,
               market : market.publicKey,
-              openOrders : open_orders_by_token[x].publicKey,
+              openOrders : await basket_open_orders(basket_1, x),
               requestQueue : market._decoded.requestQueue,
               eventQueue : market._decoded.eventQueue,
               bids : market._decoded.bids,
@@ -1074,7 +1541,7 @@ describe("fruitbasket", () => {
               tokenVault: market._decoded.baseVault,
               quoteTokenVault : market._decoded.quoteVault,
               vaultSigner : vault_signer,
-              tokenPool : token_pools[x],
+              tokenPool : await basket_vault(basket_1, x),
               quoteTokenTransactionPool : quote_token_transaction_pool,
               fruitBasketAuthority : fruitbasket_authority,
               dexProgram : serum.DEX_ID,
@@ -1094,6 +1561,8 @@ describe("fruitbasket", () => {
             quoteTokenAccount : client_usdc_acc,
             basketTokenAccount : client_basket_token_acc,
             quoteTokenTransactionPool : quote_token_transaction_pool,
+            feeVault : fee_vault,
+            creatorFeeVault : basket_1_creator_fee_vault,
             fruitBasketAuthority : fruitbasket_authority,
             quoteTokenMint : quote_token.publicKey,
             basketTokenMint : basket_1_mint,
@@ -1112,9 +1581,9 @@ describe("fruitbasket", () => {
     // check all accounts are untouched
     const amount_of_basket_tokens_with_client_after = (await basket_1_token.getAccountInfo(client_basket_token_acc)).amount;
     const amount_of_usdc_with_client_after = (await quote_token.getAccountInfo(client_usdc_acc)).amount;
-    const amount_of_btc_in_pool_after = (await (await btc).getAccountInfo(token_pools[0])).amount;
-    const amount_of_eth_in_pool_after = (await (await eth).getAccountInfo(token_pools[1])).amount;
-    const amount_of_sol_in_pool_after = (await (await sol).getAccountInfo(token_pools[2])).amount;
+    const amount_of_btc_in_pool_after = (await (await btc).getAccountInfo(await basket_vault(basket_1, 0))).amount;
+    const amount_of_eth_in_pool_after = (await (await eth).getAccountInfo(await basket_vault(basket_1, 1))).amount;
+    const amount_of_sol_in_pool_after = (await (await sol).getAccountInfo(await basket_vault(basket_1, 2))).amount;
     assert.equal(amount_of_basket_tokens_with_client_before.toNumber(), amount_of_basket_tokens_with_client_after.toNumber());
     assert.equal(amount_of_usdc_with_client_before.toNumber(), amount_of_usdc_with_client_after.toNumber());
     assert.equal(amount_of_btc_in_pool_before.toNumber(), amount_of_btc_in_pool_after.toNumber());
@@ -1122,6 +1591,421 @@ describe("fruitbasket", () => {
     assert.equal(amount_of_sol_in_pool_before.toNumber(), amount_of_sol_in_pool_after.toNumber());
   });
 
+  it("Create and redeem basket in kind", async() => {
+    const components = [btc, eth, sol];
+    const component_tokens = await Promise.all(components);
+    // client already owns the components of first basket
+    const client_token_accs = await Promise.all(
+      component_tokens.map(async (x) => {
+        const acc = await x.createAccount(client_1.publicKey);
+        await x.mintTo(acc, wallet.publicKey, [test_utils.payer()], 1_000_000_000);
+        return acc;
+      })
+    );
+    const remaining_accounts = [];
+    for (let i = 0; i < components.length; ++i) {
+      remaining_accounts.push({ pubkey: fruitbasket_token_descs[i], isWritable: false, isSigner: false });
+      remaining_accounts.push({ pubkey: client_token_accs[i], isWritable: true, isSigner: false });
+      remaining_accounts.push({ pubkey: await basket_vault(basket_1, i), isWritable: true, isSigner: false });
+    }
+    // a single in kind context per user and basket, its components are moved before it is finalized
+    const [in_kind_context, in_kind_bump] = await web3.PublicKey.findProgramAddress(
+      [Buffer.from("fruitbasket_in_kind"), client_1.publicKey.toBuffer(), basket_1.toBuffer()],
+      program.programId
+    );
+    const init_in_kind_context = async (amount: number, redeem: boolean) => {
+      await program.rpc.initInKindContext(in_kind_bump, new anchor.BN(amount), redeem, {
+        accounts: {
+          user: client_1.publicKey,
+          group: frt_bsk_group,
+          fruitbasket: basket_1,
+          basketTokenAccount: client_basket_token_acc,
+          basketTokenMint: basket_1_mint,
+          inKindContext: in_kind_context,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: web3.SystemProgram.programId,
+        },
+        signers: [client_1],
+      });
+    };
+    const in_kind = async (amount: number, redeem: boolean) => {
+      await init_in_kind_context(amount, redeem);
+      await program.rpc.processInKind(0, {
+        accounts: {
+          user: client_1.publicKey,
+          inKindContext: in_kind_context,
+          fruitbasket: basket_1,
+          fruitBasketAuthority: fruitbasket_authority,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        },
+        remainingAccounts: remaining_accounts,
+        signers: [client_1],
+      });
+      await program.rpc.finalizeInKindContext({
+        accounts: {
+          inKindContext: in_kind_context,
+          fruitbasket: basket_1,
+          basketTokenAccount: client_basket_token_acc,
+          basketTokenMint: basket_1_mint,
+          user: client_1.publicKey,
+          fruitBasketAuthority: fruitbasket_authority,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        },
+      });
+    };
+    const basket_tokens_before = (await basket_1_token.getAccountInfo(client_basket_token_acc)).amount;
+    const pools_before = await Promise.all(component_tokens.map(async (x, i) => (await x.getAccountInfo(await basket_vault(basket_1, i))).amount));
+
+    // new baskets cannot be created while the basket is paused
+    const pause_basket_1 = async (pause_flags: number) => {
+      await program.rpc.setBasketPause(pause_flags, {
+        accounts: {
+          pauser: owner.publicKey,
+          group: frt_bsk_group,
+          basket: basket_1,
+        },
+        signers: [owner],
+      });
+    };
+    await pause_basket_1(1);
+    let failed = false;
+    try {
+      await init_in_kind_context(2000000, false);
+    } catch (e) {
+      failed = true;
+    }
+    assert.ok(failed);
+    await pause_basket_1(0);
+
+    await in_kind(2000000, false);
+    const basket_tokens_after_create = (await basket_1_token.getAccountInfo(client_basket_token_acc)).amount;
+    // vaults of the first basket hold at least what its supply requires
+    const backing_accounts = [];
+    for (let i = 0; i < components.length; ++i) {
+      backing_accounts.push({ pubkey: fruitbasket_token_descs[i], isWritable: false, isSigner: false });
+      backing_accounts.push({ pubkey: await basket_vault(basket_1, i), isWritable: false, isSigner: false });
+    }
+    const backing = await program.simulate.viewBasketBacking(0, {
+      accounts: {
+        basket: basket_1,
+        basketTokenMint: basket_1_mint,
+      },
+      remainingAccounts: backing_accounts,
+    });
+    const backing_event = backing.events.find((x) => x.name == "BasketBacking");
+    for (let i = 0; i < components.length; ++i) {
+      assert.ok(backing_event.data.vaultAmounts[i].gte(backing_event.data.requiredAmounts[i]));
+    }
+    // audit records the solvency of the basket, fully backed baskets are not paused
+    await program.rpc.setAuditAutoPause(true, {
+      accounts: {
+        owner: owner.publicKey,
+        group: frt_bsk_group,
+      },
+      signers: [owner],
+    });
+    await program.rpc.auditBasket(0, {
+      accounts: {
+        group: frt_bsk_group,
+        basket: basket_1,
+        basketTokenMint: basket_1_mint,
+      },
+      remainingAccounts: backing_accounts,
+    });
+    const audited_basket: Basket = await program.account.basket.fetch(basket_1);
+    assert.equal(audited_basket.auditCursor, 0);
+    assert.ok(audited_basket.solvencyBps.toNumber() >= 10000);
+    assert.ok(audited_basket.auditTimestamp.toNumber() > 0);
+    assert.equal(audited_basket.pauseFlags, 0);
+    assert.equal(basket_tokens_after_create.toNumber() - basket_tokens_before.toNumber(), 2000000);
+    // 2 baskets are backed by 2 times each component
+    const basket_1_info: Basket = await program.account.basket.fetch(basket_1);
+    for (let i = 0; i < components.length; ++i) {
+      const pool_amount = (await component_tokens[i].getAccountInfo(await basket_vault(basket_1, i))).amount;
+      assert.equal(pool_amount.toNumber() - pools_before[i].toNumber(), 2 * basket_1_info.components[i].amount.toNumber());
+    }
+
+    await in_kind(2000000, true);
+    const basket_tokens_after_redeem = (await basket_1_token.getAccountInfo(client_basket_token_acc)).amount;
+    assert.equal(basket_tokens_after_redeem.toNumber(), basket_tokens_before.toNumber());
+    for (let i = 0; i < components.length; ++i) {
+      const pool_amount = (await component_tokens[i].getAccountInfo(await basket_vault(basket_1, i))).amount;
+      assert.equal(pool_amount.toNumber(), pools_before[i].toNumber());
+      const client_amount = (await component_tokens[i].getAccountInfo(client_token_accs[i])).amount;
+      assert.equal(client_amount.toNumber(), 1_000_000_000);
+    }
+  });
+
+  it("Swap basket for another basket", async() => {
+    // creator fee vault of the destination basket
+    const [basket_2_creator_fee_vault, bump_cfv2] = await web3.PublicKey.findProgramAddress(
+      [Buffer.from("fruitbasket_creator_fee_vault"), basket_2.toBuffer(), quote_token.publicKey.toBuffer()],
+      program.programId
+    );
+    await program.rpc.createCreatorFeeVault(bump_cfv2, {
+      accounts: {
+        payer: owner.publicKey,
+        basket: basket_2,
+        quoteTokenMint: quote_token.publicKey,
+        creatorFeeVault: basket_2_creator_fee_vault,
+        fruitBasketAuthority: fruitbasket_authority,
+        systemProgram: web3.SystemProgram.programId,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        rent: web3.SYSVAR_RENT_PUBKEY,
+      },
+      signers: [owner],
+    });
+    const basket_2_token = new Token(connection, basket_2_mint, TOKEN_PROGRAM_ID, owner);
+    const client_basket_2_token_acc = await basket_2_token.createAccount(client_1.publicKey);
+
+    const [swap_context, swap_context_bump] =
+      await web3.PublicKey.findProgramAddress(
+        [
+          Buffer.from("fruitbasket_context"),
+          client_1.publicKey.toBuffer(),
+          Buffer.from([0]),
+        ],
+        programId
+      );
+    const basket_1_tokens_before = (await basket_1_token.getAccountInfo(client_basket_token_acc)).amount;
+    const usdc_before = (await quote_token.getAccountInfo(client_usdc_acc)).amount;
+    // 0.5 first basket (~600 USDC) for 0.2 second basket (~440 USDC), SOL is in both baskets
+    await program.rpc.initSwapContext(
+      0,
+      swap_context_bump,
+      new anchor.BN(500000),
+      new anchor.BN(200000),
+      new anchor.BN(0),
+      {
+        accounts: {
+          group: frt_bsk_group,
+          user: client_1.publicKey,
+          sourceBasket: basket_1,
+          destinationBasket: basket_2,
+          quoteTokenAccount: client_usdc_acc,
+          sourceBasketTokenAccount: client_basket_token_acc,
+          destinationBasketTokenAccount: client_basket_2_token_acc,
+          quoteTokenMint: quote_token.publicKey,
+          sourceBasketTokenMint: basket_1_mint,
+          tradeContext: swap_context,
+          quoteTokenTransactionPool: quote_token_transaction_pool,
+          fruitBasketAuthority: fruitbasket_authority,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: web3.SystemProgram.programId,
+        },
+        signers: [client_1],
+      });
+    const swap_context_info: BasketTradeContext = await program.account.basketTradeContext.fetch(swap_context);
+    // btc, eth, sol, srm, mngo
+    assert.equal(swap_context_info.tokenMints.length, 5);
+    // only the difference of sol is sold
+    assert.equal(swap_context_info.tokenAmounts[2].toNumber(), 600000);
+
+    const process_leg = async (x: number) => {
+      const token = await tokens[x];
+      const market = markets_by_tokens[x];
+      const [vault_signer, _vault_bump] = await serum_utils.findVaultOwner(market.publicKey);
+      await program.rpc.processTokenForContext(
+        {
+          accounts : {
+            group : frt_bsk_group,
+            tokenDesc : fruitbasket_token_descs[x],
+            tradeContext : swap_context,
+            tokenMint : token.publicKey,
+            quoteTokenMint : quote_token.publicKey,
+            fruitbasket : basket_1,
+            market : market.publicKey,
+            openOrders : await basket_open_orders(x < 3 ? basket_1 : basket_2, x),
+            requestQueue : market._decoded.requestQueue,
+            eventQueue : market._decoded.eventQueue,
+            bids : market._decoded.bids,
+            asks: market._decoded.asks,
+            tokenVault: market._decoded.baseVault,
+            quoteTokenVault : market._decoded.quoteVault,
+            vaultSigner : vault_signer,
+            tokenPool : await basket_vault(x < 3 ? basket_1 : basket_2, x),
+            quoteTokenTransactionPool : quote_token_transaction_pool,
+            fruitBasketAuthority : fruitbasket_authority,
+            dexProgram : serum.DEX_ID,
+            tokenProgram : TOKEN_PROGRAM_ID,
+            rent : web3.SYSVAR_RENT_PUBKEY,
+          }
+        }
+      );
+    };
+    // sell legs fund the buy legs so they are processed first
+    await Promise.all([0, 1, 2].map(process_leg));
+    await Promise.all([3, 4].map(process_leg));
+
+    await program.rpc.finalizeSwapContext(
+      {
+        accounts : {
+          fruitbasketGroup : frt_bsk_group,
+          tradeContext : swap_context,
+          sourceBasket : basket_1,
+          destinationBasket : basket_2,
+          quoteTokenAccount : client_usdc_acc,
+          sourceBasketTokenAccount : client_basket_token_acc,
+          destinationBasketTokenAccount : client_basket_2_token_acc,
+          quoteTokenTransactionPool : quote_token_transaction_pool,
+          feeVault : fee_vault,
+          sourceCreatorFeeVault : basket_1_creator_fee_vault,
+          destinationCreatorFeeVault : basket_2_creator_fee_vault,
+          fruitBasketAuthority : fruitbasket_authority,
+          quoteTokenMint : quote_token.publicKey,
+          sourceBasketTokenMint : basket_1_mint,
+          destinationBasketTokenMint : basket_2_mint,
+          user : client_1.publicKey,
+          tokenProgram : TOKEN_PROGRAM_ID,
+          systemProgram : web3.SystemProgram.programId,
+        },
+        // sol kept by both baskets moves from the first basket vault to the second one
+        remainingAccounts : [
+          { pubkey: fruitbasket_token_descs[2], isWritable: false, isSigner: false },
+          { pubkey: await basket_vault(basket_1, 2), isWritable: true, isSigner: false },
+          { pubkey: await basket_vault(basket_2, 2), isWritable: true, isSigner: false },
+        ],
+      }
+    );
+    const basket_1_tokens_after = (await basket_1_token.getAccountInfo(client_basket_token_acc)).amount;
+    assert.equal(basket_1_tokens_before.toNumber() - basket_1_tokens_after.toNumber(), 500000);
+    const basket_2_tokens = (await basket_2_token.getAccountInfo(client_basket_2_token_acc)).amount;
+    assert.equal(basket_2_tokens.toNumber(), 200000);
+    // source basket was worth more, the difference is given back in usdc
+    const usdc_after = (await quote_token.getAccountInfo(client_usdc_acc)).amount;
+    assert.ok(usdc_after.toNumber() > usdc_before.toNumber());
+  });
+
+  it("Rebalance basket to new components", async() => {
+    const basket_2_info: Basket = await program.account.basket.fetch(basket_2);
+    // more SOL and less SRM, MNGO is unchanged
+    const new_components = basket_2_info.components.map((x) => ({ ...x }));
+    new_components[0].amount = new anchor.BN(2_500_000);
+    new_components[1].amount = new anchor.BN(50_000_000);
+
+    const [rebalance_context, rebalance_context_bump] =
+      await web3.PublicKey.findProgramAddress(
+        [
+          Buffer.from("fruitbasket_context"),
+          owner.publicKey.toBuffer(),
+          Buffer.from([7]),
+        ],
+        programId
+      );
+    // legs are netted against the tokens held by the vaults of the basket
+    const token_mints = (await Promise.all(tokens)).map((x) => x.publicKey);
+    const vault_accounts = [];
+    const vault_amounts = [];
+    for (const component of basket_2_info.components) {
+      const x = token_mints.findIndex((mint) => mint.equals(component.tokenMint));
+      const vault = await basket_vault(basket_2, x);
+      vault_accounts.push({ pubkey: fruitbasket_token_descs[x], isWritable: false, isSigner: false });
+      vault_accounts.push({ pubkey: vault, isWritable: false, isSigner: false });
+      vault_amounts.push((await (await tokens[x]).getAccountInfo(vault)).amount);
+    }
+    await program.rpc.initRebalanceContext(
+      7,
+      rebalance_context_bump,
+      new_components,
+      {
+        accounts: {
+          group: frt_bsk_group,
+          curator: owner.publicKey,
+          basket: basket_2,
+          basketTokenMint: basket_2_mint,
+          tradeContext: rebalance_context,
+          systemProgram: web3.SystemProgram.programId,
+        },
+        remainingAccounts: vault_accounts,
+        signers: [owner],
+      });
+    const rebalance_context_info: BasketTradeContext = await program.account.basketTradeContext.fetch(rebalance_context);
+    // 0.2 basket in supply : SOL is bought and SRM is sold up to the backing of the new components
+    for (let i = 0; i < new_components.length; ++i) {
+      const backing = new_components[i].amount.divn(5);
+      assert.equal(rebalance_context_info.tokenAmounts[i].toNumber(), Math.abs(backing.toNumber() - vault_amounts[i].toNumber()));
+    }
+    const rebalancing_basket: Basket = await program.account.basket.fetch(basket_2);
+    assert.ok(rebalancing_basket.rebalanceContext.equals(rebalance_context));
+
+    const process_leg = async (x: number) => {
+      const token = await tokens[x];
+      const market = markets_by_tokens[x];
+      const [vault_signer, _vault_bump] = await serum_utils.findVaultOwner(market.publicKey);
+      await program.rpc.processTokenForContext(
+        {
+          accounts : {
+            group : frt_bsk_group,
+            tokenDesc : fruitbasket_token_descs[x],
+            tradeContext : rebalance_context,
+            tokenMint : token.publicKey,
+            quoteTokenMint : quote_token.publicKey,
+            fruitbasket : basket_2,
+            market : market.publicKey,
+            openOrders : await basket_open_orders(basket_2, x),
+            requestQueue : market._decoded.requestQueue,
+            eventQueue : market._decoded.eventQueue,
+            bids : market._decoded.bids,
+            asks: market._decoded.asks,
+            tokenVault: market._decoded.baseVault,
+            quoteTokenVault : market._decoded.quoteVault,
+            vaultSigner : vault_signer,
+            tokenPool : await basket_vault(basket_2, x),
+            quoteTokenTransactionPool : quote_token_transaction_pool,
+            fruitBasketAuthority : fruitbasket_authority,
+            dexProgram : serum.DEX_ID,
+            tokenProgram : TOKEN_PROGRAM_ID,
+            rent : web3.SYSVAR_RENT_PUBKEY,
+          }
+        }
+      );
+    };
+    // SRM is sold before SOL is bought, MNGO is only traded if its vault is off its backing
+    await process_leg(3);
+    await process_leg(4);
+    await process_leg(2);
+
+    // quote left between the legs is kept by the basket in its vault of the base mint
+    const [basket_quote_vault, basket_quote_vault_bump] = await web3.PublicKey.findProgramAddress(
+      [Buffer.from("fruitbasket_vault"), basket_2.toBuffer(), quote_token.publicKey.toBuffer()],
+      program.programId
+    );
+    await program.rpc.createBasketVault(basket_quote_vault_bump, {
+      accounts: {
+        payer: owner.publicKey,
+        basket: basket_2,
+        tokenMint: quote_token.publicKey,
+        basketVault: basket_quote_vault,
+        fruitBasketAuthority: fruitbasket_authority,
+        systemProgram: web3.SystemProgram.programId,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        rent: web3.SYSVAR_RENT_PUBKEY,
+      },
+      signers: [owner],
+    });
+    await program.rpc.finalizeRebalanceContext(
+      {
+        accounts : {
+          group : frt_bsk_group,
+          tradeContext : rebalance_context,
+          basket : basket_2,
+          quoteTokenTransactionPool : quote_token_transaction_pool,
+          basketQuoteVault : basket_quote_vault,
+          fruitBasketAuthority : fruitbasket_authority,
+          curator : owner.publicKey,
+          tokenProgram : TOKEN_PROGRAM_ID,
+        }
+      }
+    );
+    const rebalanced_basket: Basket = await program.account.basket.fetch(basket_2);
+    assert.ok(rebalanced_basket.rebalanceContext.equals(web3.PublicKey.default));
+    assert.equal(rebalanced_basket.components[0].amount.toNumber(), 2_500_000);
+    assert.equal(rebalanced_basket.components[1].amount.toNumber(), 50_000_000);
+    assert.equal(rebalanced_basket.pendingComponents.length, 0);
+    const basket_quote = (await quote_token.getAccountInfo(basket_quote_vault)).amount;
+    assert.ok(basket_quote.toNumber() > 0);
+  });
+
   function ComponentInfo() {
     this.tokenMint;
     this.amount;